@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+
+pub const DIALOGUE_FILE_TYPE: &str = "dialogue";
+pub const INVOICING_FILE_TYPE: &str = "invoicing";
+
+/// Fields each import file type maps header aliases onto.
+pub const DIALOGUE_FIELDS: &[&str] = &["start", "finish", "shift", "shift_group", "teacher_name"];
+pub const INVOICING_FIELDS: &[&str] = &[
+    "teacher_name",
+    "eligible",
+    "activity_start",
+    "activity_end",
+    "shift",
+];
+
+/// The aliases shipped with the API. `migrations/0003_Column_Mappings.up.sql` seeds the same
+/// list, and it is used as-is when no database is available (CLI dry runs, tests).
+pub const DEFAULT_COLUMN_MAPPINGS: &[(&str, &str, &[&str])] = &[
+    (DIALOGUE_FILE_TYPE, "start", &["Start", "Activity Start"]),
+    (
+        DIALOGUE_FILE_TYPE,
+        "finish",
+        &["Finish", "End", "Activity End"],
+    ),
+    (
+        DIALOGUE_FILE_TYPE,
+        "shift",
+        &["Shift: Shift Number", "Shift Number", "Shift"],
+    ),
+    (
+        DIALOGUE_FILE_TYPE,
+        "shift_group",
+        &["Resource: Shift Group", "Shift Group"],
+    ),
+    (
+        DIALOGUE_FILE_TYPE,
+        "teacher_name",
+        &["Resource: Resource Name", "Resource Name", "Teacher Name"],
+    ),
+    (
+        INVOICING_FILE_TYPE,
+        "teacher_name",
+        &[
+            "Resource: Resource Name",
+            "Resource Name",
+            "Teacher_Name",
+            "Teacher Name",
+        ],
+    ),
+    (
+        INVOICING_FILE_TYPE,
+        "eligible",
+        &["Eligible_Status", "Eligible Status", "Eligible"],
+    ),
+    (
+        INVOICING_FILE_TYPE,
+        "activity_start",
+        &[
+            "Activity_Start_Time",
+            "Activity Start Time",
+            "Activity Start",
+            "Start",
+        ],
+    ),
+    (
+        INVOICING_FILE_TYPE,
+        "activity_end",
+        &[
+            "Activity_End_Time",
+            "Activity End Time",
+            "Activity End",
+            "Finish",
+        ],
+    ),
+    (
+        INVOICING_FILE_TYPE,
+        "shift",
+        &[
+            "shift_name_tsm",
+            "Shift_Name",
+            "Shift Name",
+            "Shift: Shift Number",
+            "Shift Number",
+            "Shift",
+        ],
+    ),
+];
+
+/// Header aliases per file type and field, in match order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMappings {
+    aliases: HashMap<(String, String), Vec<String>>,
+}
+
+impl ColumnMappings {
+    pub fn defaults() -> ColumnMappings {
+        let mut mappings = ColumnMappings::default();
+
+        for (file_type, field, aliases) in DEFAULT_COLUMN_MAPPINGS {
+            for alias in *aliases {
+                mappings.push(file_type, field, alias);
+            }
+        }
+
+        mappings
+    }
+
+    /// Builds mappings from `(file_type, field, alias)` entries that are already in match order.
+    pub fn from_aliases<'a, I>(entries: I) -> ColumnMappings
+    where
+        I: IntoIterator<Item = (&'a str, &'a str, &'a str)>,
+    {
+        let mut mappings = ColumnMappings::default();
+
+        for (file_type, field, alias) in entries {
+            mappings.push(file_type, field, alias);
+        }
+
+        mappings
+    }
+
+    /// A field whose aliases were all deleted falls back to the shipped defaults rather than
+    /// failing every import.
+    pub fn with_default_fallback(mut self) -> ColumnMappings {
+        for (key, aliases) in ColumnMappings::defaults().aliases {
+            self.aliases.entry(key).or_insert(aliases);
+        }
+
+        self
+    }
+
+    pub fn aliases(&self, file_type: &str, field: &str) -> Vec<&str> {
+        self.aliases
+            .get(&(file_type.to_string(), field.to_string()))
+            .map(|aliases| aliases.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    fn push(&mut self, file_type: &str, field: &str, alias: &str) {
+        self.aliases
+            .entry((file_type.to_string(), field.to_string()))
+            .or_default()
+            .push(alias.to_string());
+    }
+}
+
+/// Checks that `field` is one of the fields `file_type` maps.
+pub fn validate_mapping_field(file_type: &str, field: &str) -> Result<(), Error> {
+    let fields = match file_type {
+        DIALOGUE_FILE_TYPE => DIALOGUE_FIELDS,
+        INVOICING_FILE_TYPE => INVOICING_FIELDS,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unknown file_type {:?}. Expected one of: {}, {}",
+                file_type,
+                DIALOGUE_FILE_TYPE,
+                INVOICING_FILE_TYPE
+            ))
+        }
+    };
+
+    if fields.contains(&field) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Unknown {} field {:?}. Expected one of: {}",
+            file_type,
+            field,
+            fields.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_mapping_field, ColumnMappings};
+
+    #[test]
+    fn falls_back_to_default_aliases_for_unmapped_fields() {
+        let mappings =
+            ColumnMappings::from_aliases([("invoicing", "shift", "Slot")]).with_default_fallback();
+
+        assert_eq!(mappings.aliases("invoicing", "shift"), vec!["Slot"]);
+        assert_eq!(
+            mappings.aliases("dialogue", "shift_group"),
+            vec!["Resource: Shift Group", "Shift Group"]
+        );
+    }
+
+    #[test]
+    fn validates_file_types_and_fields() {
+        assert!(validate_mapping_field("dialogue", "shift_group").is_ok());
+        assert!(validate_mapping_field("invoicing", "shift_group").is_err());
+        assert!(validate_mapping_field("payroll", "shift").is_err());
+    }
+}
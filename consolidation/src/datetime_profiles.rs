@@ -0,0 +1,373 @@
+use anyhow::Error;
+use chrono::NaiveDateTime;
+
+/// Keeps the historical behaviour: every known format is tried and the candidate that falls on
+/// the process date wins.
+pub const FLEXIBLE_PROFILE: &str = "flexible";
+
+/// Scans the whole file and picks the single profile that parses every datetime in it.
+pub const AUTO_PROFILE: &str = "auto";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimeProfile {
+    pub name: String,
+    pub formats: Vec<String>,
+    /// `Some(true)` for day-first slash dates, `Some(false)` for month-first, `None` when the
+    /// profile does not accept slash dates with single-digit parts.
+    pub day_first: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateTimeProfileSelection {
+    Flexible,
+    Auto,
+    Named(DateTimeProfile),
+}
+
+impl DateTimeProfile {
+    fn new(name: &str, formats: &[&str], day_first: Option<bool>) -> DateTimeProfile {
+        DateTimeProfile {
+            name: name.to_string(),
+            formats: formats.iter().map(|format| format.to_string()).collect(),
+            day_first,
+        }
+    }
+
+    pub fn us() -> DateTimeProfile {
+        DateTimeProfile::new(
+            "us",
+            &[
+                "%m/%d/%Y %I:%M%p",
+                "%m/%d/%Y %I:%M %p",
+                "%m/%d/%Y %I:%M:%S %p",
+                "%m/%d/%Y %I:%M:%S%p",
+                "%m/%d/%Y %H:%M",
+                "%m/%d/%Y %H:%M:%S",
+            ],
+            Some(false),
+        )
+    }
+
+    pub fn uk() -> DateTimeProfile {
+        DateTimeProfile::new(
+            "uk",
+            &[
+                "%d/%m/%Y %I:%M%p",
+                "%d/%m/%Y %I:%M %p",
+                "%d/%m/%Y %I:%M:%S %p",
+                "%d/%m/%Y %I:%M:%S%p",
+                "%d/%m/%Y %H:%M",
+                "%d/%m/%Y %H:%M:%S",
+            ],
+            Some(true),
+        )
+    }
+
+    pub fn iso() -> DateTimeProfile {
+        DateTimeProfile::new(
+            "iso",
+            &[
+                "%Y-%m-%d %H:%M:%S",
+                "%Y-%m-%d %H:%M",
+                "%Y-%m-%dT%H:%M:%S",
+                "%Y-%m-%dT%H:%M",
+                "%Y/%m/%d %H:%M:%S",
+                "%Y/%m/%d %H:%M",
+            ],
+            None,
+        )
+    }
+
+    /// The profiles available before any are configured. A configured profile with the same name
+    /// replaces its built-in one.
+    pub fn builtin() -> Vec<DateTimeProfile> {
+        vec![
+            DateTimeProfile::us(),
+            DateTimeProfile::uk(),
+            DateTimeProfile::iso(),
+        ]
+    }
+
+    pub fn parse(&self, value: &str) -> Option<NaiveDateTime> {
+        let value = value.trim().trim_matches('"');
+
+        self.formats
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| {
+                self.day_first
+                    .and_then(|day_first| parse_slash_datetime(value, day_first))
+            })
+    }
+}
+
+impl DateTimeProfileSelection {
+    pub fn resolve(
+        name: &str,
+        profiles: &[DateTimeProfile],
+    ) -> Result<DateTimeProfileSelection, Error> {
+        let name = name.trim().to_ascii_lowercase();
+
+        match name.as_str() {
+            FLEXIBLE_PROFILE => Ok(DateTimeProfileSelection::Flexible),
+            AUTO_PROFILE => Ok(DateTimeProfileSelection::Auto),
+            _ => profiles
+                .iter()
+                .find(|profile| profile.name == name)
+                .cloned()
+                .map(DateTimeProfileSelection::Named)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown datetime profile {:?}. Expected one of: {}",
+                        name,
+                        available_profile_names(profiles).join(", ")
+                    )
+                }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DateTimeProfileSelection::Flexible => FLEXIBLE_PROFILE,
+            DateTimeProfileSelection::Auto => AUTO_PROFILE,
+            DateTimeProfileSelection::Named(profile) => &profile.name,
+        }
+    }
+
+    /// Turns [DateTimeProfileSelection::Auto] into a concrete profile using every datetime value
+    /// in the file. `Ok(None)` means the flexible parser should be used.
+    pub fn resolve_for_values<'a, I>(
+        &self,
+        values: I,
+        profiles: &[DateTimeProfile],
+    ) -> Result<Option<DateTimeProfile>, Error>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        match self {
+            DateTimeProfileSelection::Flexible => Ok(None),
+            DateTimeProfileSelection::Named(profile) => Ok(Some(profile.clone())),
+            DateTimeProfileSelection::Auto => detect_profile(values, profiles).cloned().map(Some),
+        }
+    }
+}
+
+pub fn available_profile_names(profiles: &[DateTimeProfile]) -> Vec<String> {
+    let mut names = vec![FLEXIBLE_PROFILE.to_string(), AUTO_PROFILE.to_string()];
+    names.extend(profiles.iter().map(|profile| profile.name.clone()));
+    names
+}
+
+/// Picks the single profile that parses every non-empty value. Fails when no profile fits or
+/// when more than one does (e.g. every date has a day of 12 or less).
+pub fn detect_profile<'a, 'p, I>(
+    values: I,
+    profiles: &'p [DateTimeProfile],
+) -> Result<&'p DateTimeProfile, Error>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut detector = ProfileDetector::new(profiles);
+
+    for value in values {
+        detector.observe(value);
+
+        if detector.exhausted() {
+            break;
+        }
+    }
+
+    detector.finish()
+}
+
+/// Incremental form of [detect_profile] for files streamed from disk, so the values never need
+/// to be held at once.
+pub struct ProfileDetector<'p> {
+    candidates: Vec<&'p DateTimeProfile>,
+    rejections: Vec<(String, String)>,
+    scanned_values: usize,
+}
+
+impl<'p> ProfileDetector<'p> {
+    pub fn new(profiles: &'p [DateTimeProfile]) -> ProfileDetector<'p> {
+        ProfileDetector {
+            candidates: profiles.iter().collect(),
+            rejections: Vec::new(),
+            scanned_values: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: &str) {
+        let value = value.trim();
+
+        if value.is_empty() || self.exhausted() {
+            return;
+        }
+
+        self.scanned_values += 1;
+
+        let rejections = &mut self.rejections;
+        self.candidates.retain(|profile| {
+            let parses = profile.parse(value).is_some();
+
+            if !parses {
+                rejections.push((profile.name.clone(), value.to_string()));
+            }
+
+            parses
+        });
+    }
+
+    /// True once no profile is left, so further values cannot change the outcome.
+    pub fn exhausted(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    pub fn scanned_values(&self) -> usize {
+        self.scanned_values
+    }
+
+    pub fn finish(self) -> Result<&'p DateTimeProfile, Error> {
+        if self.scanned_values == 0 {
+            return Err(anyhow::anyhow!(
+                "Cannot auto-detect datetime profile: the file contains no datetime values"
+            ));
+        }
+
+        match self.candidates.as_slice() {
+            [profile] => Ok(profile),
+            [] => Err(anyhow::anyhow!(
+                "Cannot auto-detect datetime profile: no profile parses every row ({})",
+                self.rejections
+                    .iter()
+                    .map(|(name, value)| format!("{} rejected {:?}", name, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => Err(anyhow::anyhow!(
+                "Cannot auto-detect datetime profile: the file is ambiguous, profiles {} all parse every row. \
+                 Pass datetime_profile explicitly.",
+                self.candidates
+                    .iter()
+                    .map(|profile| profile.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+/// Parses `d/m/Y` or `m/d/Y` dates with an optional 12-hour suffix, accepting single-digit parts
+/// and lowercase meridiems that the strict chrono formats reject.
+pub fn parse_slash_datetime(value: &str, day_first: bool) -> Option<NaiveDateTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() < 2 {
+        return None;
+    }
+
+    let date_parts: Vec<&str> = parts[0].split('/').collect();
+    if date_parts.len() != 3 {
+        return None;
+    }
+
+    let first: u32 = date_parts[0].parse().ok()?;
+    let second: u32 = date_parts[1].parse().ok()?;
+    let year: i32 = date_parts[2].parse().ok()?;
+
+    let (month, day) = if day_first {
+        (second, first)
+    } else {
+        (first, second)
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let meridiem = parts
+        .get(2)
+        .filter(|part| part.eq_ignore_ascii_case("AM") || part.eq_ignore_ascii_case("PM"));
+
+    let time_parts: Vec<&str> = parts[1].split(':').collect();
+    if time_parts.len() < 2 {
+        return None;
+    }
+
+    let mut hour: u32 = time_parts[0].parse().ok()?;
+    let minute: u32 = time_parts[1].parse().ok()?;
+    let second = time_parts
+        .get(2)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+
+    if let Some(meridiem) = meridiem {
+        if meridiem.eq_ignore_ascii_case("PM") && hour < 12 {
+            hour += 12;
+        } else if meridiem.eq_ignore_ascii_case("AM") && hour == 12 {
+            hour = 0;
+        }
+    }
+
+    NaiveDateTime::parse_from_str(
+        &format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        ),
+        "%Y-%m-%d %H:%M:%S",
+    )
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{detect_profile, DateTimeProfile, DateTimeProfileSelection};
+
+    #[test]
+    fn detects_uk_profile_when_a_day_exceeds_twelve() {
+        let profiles = DateTimeProfile::builtin();
+        let values = ["03/04/2026 9:00 AM", "25/04/2026 9:00 AM"];
+
+        let profile = detect_profile(values, &profiles).expect("profile");
+
+        assert_eq!(profile.name, "uk");
+        assert_eq!(
+            profile.parse(values[0]).unwrap().date(),
+            NaiveDate::from_ymd_opt(2026, 4, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_files_where_us_and_uk_both_fit() {
+        let profiles = DateTimeProfile::builtin();
+        let error = detect_profile(["03/04/2026 9:00 AM", "5/2/2026 11:00 AM"], &profiles)
+            .expect_err("ambiguous");
+
+        assert!(error.to_string().contains("ambiguous"));
+    }
+
+    #[test]
+    fn rejects_files_that_mix_day_first_and_month_first_dates() {
+        let profiles = DateTimeProfile::builtin();
+        let error = detect_profile(["04/25/2026 9:00 AM", "25/04/2026 9:00 AM"], &profiles)
+            .expect_err("no profile");
+
+        assert!(error.to_string().contains("no profile"));
+    }
+
+    #[test]
+    fn resolves_named_and_special_profiles() {
+        let profiles = DateTimeProfile::builtin();
+
+        assert_eq!(
+            DateTimeProfileSelection::resolve("AUTO", &profiles).unwrap(),
+            DateTimeProfileSelection::Auto
+        );
+        assert_eq!(
+            DateTimeProfileSelection::resolve("iso", &profiles).unwrap(),
+            DateTimeProfileSelection::Named(DateTimeProfile::iso())
+        );
+        assert!(DateTimeProfileSelection::resolve("mars", &profiles).is_err());
+    }
+}
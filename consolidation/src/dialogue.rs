@@ -0,0 +1,1602 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+};
+
+use anyhow::Error;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use csv::{ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    column_mappings::{ColumnMappings, DIALOGUE_FILE_TYPE},
+    datetime_profiles::{parse_slash_datetime, DateTimeProfile},
+    import::{
+        convert_source_to_app_timezone, find_mapped_header_index, is_unambiguous_in_source,
+        is_xlsx_path, resolve_source_datetime, resolve_source_profile, unwrap_excel_quoted_rows,
+        ImportOptions, ImportSource, IngestionDiagnostics,
+    },
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DialogueRow {
+    pub shift_group: String,
+    pub shift: String,
+    pub teacher_name: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DialogueConsolidatedRow {
+    pub shift_group: String,
+    pub shift: String,
+    pub shift_type: String,
+    pub teacher_name: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+pub(crate) struct DialogueCsvColumns {
+    start: usize,
+    finish: usize,
+    shift: usize,
+    shift_group: usize,
+    teacher_name: usize,
+}
+
+// #[derive(Debug, Clone, Deserialize, Serialize)]
+// pub struct InternalPickup {
+//     pub shift: String,
+//     pub initial_teacher: String,
+//     pub new_teacher: String,
+//     pub shift_group: String,
+// }
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DialogueMatchKey {
+    shift: String,
+    start_date: String,
+    end_date: String,
+}
+
+pub(crate) const DIALOGUE_DATETIME_FORMATS: &[&str] = &[
+    "%m/%d/%Y %I:%M%p",
+    "%m/%d/%Y %I:%M %p",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M:%S%p",
+    "%m/%d/%Y %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%d/%m/%Y %I:%M %p",
+    "%d/%m/%Y %I:%M:%S %p",
+    "%d/%m/%Y %H:%M",
+    "%d/%m/%Y %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+pub(crate) fn push_unique_source_datetime(
+    candidates: &mut Vec<NaiveDateTime>,
+    parsed: NaiveDateTime,
+) {
+    if !candidates.contains(&parsed) {
+        candidates.push(parsed);
+    }
+}
+
+/// Parsed naive datetimes as they appear in the export (interpreted as UTC by default).
+pub(crate) fn collect_source_dialogue_datetimes(value: &str) -> Vec<NaiveDateTime> {
+    let value = value.trim().trim_matches('"');
+    let mut candidates = Vec::new();
+
+    for format in DIALOGUE_DATETIME_FORMATS {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(value, format) {
+            push_unique_source_datetime(&mut candidates, parsed);
+        }
+    }
+
+    if let Some(parsed) = parse_slash_datetime(value, false) {
+        push_unique_source_datetime(&mut candidates, parsed);
+    }
+
+    if let Some(parsed) = parse_slash_datetime(value, true) {
+        push_unique_source_datetime(&mut candidates, parsed);
+    }
+
+    candidates
+}
+
+/// Like [collect_source_dialogue_datetimes], but a resolved profile yields at most one candidate
+/// so ambiguous dates such as 03/04 are never guessed.
+pub(crate) fn collect_profile_dialogue_datetimes(
+    value: &str,
+    profile: Option<&DateTimeProfile>,
+) -> Vec<NaiveDateTime> {
+    match profile {
+        Some(profile) => profile.parse(value).into_iter().collect(),
+        None => collect_source_dialogue_datetimes(value),
+    }
+}
+
+pub(crate) fn dialogue_datetime_matches_process_calendar(
+    source_naive: NaiveDateTime,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+) -> bool {
+    let local = convert_source_to_app_timezone(source_naive, options);
+
+    // Primary: shift falls on the selected business day in local (Johannesburg) time.
+    if local.date() == process_calendar {
+        return true;
+    }
+
+    // Fallback: CSV date is the UTC calendar day (common in Dialogue exports).
+    source_naive.date() == process_calendar
+}
+
+/// Parses a dialogue datetime without any timezone conversion. Loaded rows are already in app
+/// local time, so this is what consolidation uses to compare and store them.
+pub(crate) fn parse_dialogue_datetime(value: &str) -> Result<NaiveDateTime, Error> {
+    collect_source_dialogue_datetimes(value)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Unsupported dialogue datetime format: {}", value.trim()))
+}
+
+/// Picks the start/finish candidates that place the shift on the process date and returns them
+/// as they appear in the export (source timezone).
+pub(crate) fn select_dialogue_start_end(
+    start: &str,
+    finish: &str,
+    process_calendar: NaiveDate,
+    profile: Option<&DateTimeProfile>,
+    options: &ImportOptions,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    select_dialogue_start_end_candidates(
+        collect_profile_dialogue_datetimes(start, profile),
+        collect_profile_dialogue_datetimes(finish, profile),
+        process_calendar,
+        options,
+    )
+}
+
+pub(crate) fn select_dialogue_start_end_candidates(
+    starts: Vec<NaiveDateTime>,
+    ends: Vec<NaiveDateTime>,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    if starts.is_empty() || ends.is_empty() {
+        return None;
+    }
+
+    let app_ends = ends
+        .iter()
+        .map(|source_end| {
+            (
+                *source_end,
+                convert_source_to_app_timezone(*source_end, options),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for source_start in &starts {
+        if !dialogue_datetime_matches_process_calendar(*source_start, process_calendar, options) {
+            continue;
+        }
+
+        let app_start = convert_source_to_app_timezone(*source_start, options);
+        let (source_end, _) = app_ends
+            .iter()
+            .copied()
+            .filter(|(_, end)| *end >= app_start)
+            .min_by_key(|(_, end)| *end - app_start)
+            .or_else(|| app_ends.first().copied())?;
+
+        return Some((*source_start, source_end));
+    }
+
+    let app_starts = starts
+        .iter()
+        .map(|source_start| {
+            (
+                *source_start,
+                convert_source_to_app_timezone(*source_start, options),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for source_end in &ends {
+        if !dialogue_datetime_matches_process_calendar(*source_end, process_calendar, options) {
+            continue;
+        }
+
+        let app_end = convert_source_to_app_timezone(*source_end, options);
+        let (source_start, _) = app_starts
+            .iter()
+            .copied()
+            .filter(|(_, start)| *start <= app_end)
+            .max_by_key(|(_, start)| *start)
+            .or_else(|| app_starts.first().copied())?;
+
+        return Some((source_start, *source_end));
+    }
+
+    None
+}
+
+pub(crate) fn format_dialogue_datetime(parsed: NaiveDateTime) -> String {
+    parsed.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub(crate) fn format_app_datetime(datetime: DateTime<Utc>, options: &ImportOptions) -> String {
+    format_dialogue_datetime(datetime.with_timezone(&options.app_timezone).naive_local())
+}
+
+pub(crate) fn is_excel_dialogue_export(contents: &str) -> bool {
+    contents.contains("\"\"Finish\"\"")
+        || contents.contains("\"\"Start\"\"")
+        || contents.contains(",\"\"")
+}
+
+pub(crate) fn dialogue_csv_splits_cleanly(
+    contents: &str,
+    delimiter: u8,
+    mappings: &ColumnMappings,
+) -> bool {
+    let mut reader = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers,
+        Err(_) => return false,
+    };
+
+    if headers.len() < 5 || build_dialogue_csv_columns(headers, mappings).is_err() {
+        return false;
+    }
+
+    let expected_columns = headers.len();
+
+    match reader.records().next() {
+        Some(Ok(record)) => record.len() >= expected_columns.saturating_sub(1),
+        Some(Err(_)) => false,
+        None => true,
+    }
+}
+
+/// Decides from a sample of the file whether dialogue rows need [unwrap_excel_quoted_row]
+/// applied before they can be parsed.
+pub(crate) fn dialogue_csv_needs_unwrap(
+    sample: &str,
+    delimiter: u8,
+    mappings: &ColumnMappings,
+) -> bool {
+    if is_excel_dialogue_export(sample) {
+        return true;
+    }
+
+    if dialogue_csv_splits_cleanly(sample, delimiter, mappings) {
+        return false;
+    }
+
+    dialogue_csv_splits_cleanly(
+        &unwrap_excel_quoted_rows(sample, delimiter),
+        delimiter,
+        mappings,
+    )
+}
+
+pub(crate) fn normalize_identifier(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn normalize_shift_identifier(value: &str) -> String {
+    let value = normalize_identifier(value);
+    let numeric_candidate = value.replace(',', "");
+
+    if let Ok(integer) = numeric_candidate.parse::<i64>() {
+        return integer.to_string();
+    }
+
+    if let Ok(float) = numeric_candidate.parse::<f64>() {
+        if float.fract().abs() < f64::EPSILON {
+            return format!("{float:.0}");
+        }
+
+        let mut formatted = float.to_string();
+
+        if formatted.contains('.') {
+            while formatted.ends_with('0') {
+                formatted.pop();
+            }
+
+            if formatted.ends_with('.') {
+                formatted.pop();
+            }
+        }
+
+        return formatted;
+    }
+
+    value.to_ascii_lowercase()
+}
+
+pub(crate) fn canonicalize_dialogue_datetime(value: &str) -> String {
+    match parse_dialogue_datetime(value) {
+        Ok(parsed) => format_dialogue_datetime(parsed),
+        Err(_) => normalize_identifier(value).to_ascii_lowercase(),
+    }
+}
+
+pub(crate) fn build_dialogue_match_key(row: &DialogueRow) -> DialogueMatchKey {
+    DialogueMatchKey {
+        shift: normalize_shift_identifier(&row.shift),
+        start_date: canonicalize_dialogue_datetime(&row.start_date),
+        end_date: canonicalize_dialogue_datetime(&row.end_date),
+    }
+}
+
+pub fn consolidate_dialogue_rows(
+    first_dialogue_rows: &[DialogueRow],
+    second_dialogue_rows: &[DialogueRow],
+) -> Vec<DialogueConsolidatedRow> {
+    let first_keys = first_dialogue_rows
+        .iter()
+        .map(build_dialogue_match_key)
+        .collect::<Vec<_>>();
+    let second_keys = second_dialogue_rows
+        .iter()
+        .map(build_dialogue_match_key)
+        .collect::<Vec<_>>();
+
+    let first_key_set = first_keys.iter().cloned().collect::<HashSet<_>>();
+    let second_key_set = second_keys.iter().cloned().collect::<HashSet<_>>();
+
+    let dropped_keys = first_key_set
+        .difference(&second_key_set)
+        .cloned()
+        .collect::<HashSet<_>>();
+    let pickup_keys = second_key_set
+        .difference(&first_key_set)
+        .cloned()
+        .collect::<HashSet<_>>();
+
+    // Every (teacher, shift group) a key had in the first snapshot, so duplicate keys classify
+    // the same way whatever order the rows arrive in.
+    let mut previous_shift_assignments: HashMap<DialogueMatchKey, Vec<(String, String)>> =
+        HashMap::new();
+
+    for row in first_dialogue_rows {
+        previous_shift_assignments
+            .entry(build_dialogue_match_key(row))
+            .or_default()
+            .push((
+                normalize_identifier(&row.teacher_name).to_ascii_lowercase(),
+                normalize_identifier(&row.shift_group).to_ascii_lowercase(),
+            ));
+    }
+
+    let mut internal_pick_up_keys = HashSet::new();
+    let mut dropped_and_picked_up_keys = HashSet::new();
+
+    for second_dialogue_row in second_dialogue_rows {
+        let match_key = build_dialogue_match_key(second_dialogue_row);
+
+        if pickup_keys.contains(&match_key) {
+            continue;
+        }
+
+        if let Some(previous_assignments) = previous_shift_assignments.get(&match_key) {
+            let current_teacher =
+                normalize_identifier(&second_dialogue_row.teacher_name).to_ascii_lowercase();
+            let current_shift_group =
+                normalize_identifier(&second_dialogue_row.shift_group).to_ascii_lowercase();
+
+            if previous_assignments
+                .iter()
+                .any(|(previous_teacher, _)| previous_teacher == &current_teacher)
+            {
+                continue;
+            }
+
+            if previous_assignments
+                .iter()
+                .any(|(_, previous_shift_group)| previous_shift_group == &current_shift_group)
+            {
+                internal_pick_up_keys.insert(match_key);
+            } else {
+                dropped_and_picked_up_keys.insert(match_key);
+            }
+        }
+    }
+
+    let mut consolidated_rows = Vec::new();
+
+    for current_dialogue_row in first_dialogue_rows {
+        let match_key = build_dialogue_match_key(current_dialogue_row);
+
+        if dropped_keys.contains(&match_key) {
+            consolidated_rows.push(DialogueConsolidatedRow {
+                shift_group: current_dialogue_row.shift_group.clone(),
+                shift: current_dialogue_row.shift.clone(),
+                shift_type: "Dropped".to_string(),
+                teacher_name: current_dialogue_row.teacher_name.clone(),
+                start_date: current_dialogue_row.start_date.clone(),
+                end_date: current_dialogue_row.end_date.clone(),
+            });
+        }
+    }
+
+    for current_dialogue_row in second_dialogue_rows {
+        let match_key = build_dialogue_match_key(current_dialogue_row);
+
+        let shift_type = if pickup_keys.contains(&match_key) {
+            "Pickup"
+        } else if internal_pick_up_keys.contains(&match_key) {
+            "Internal Pickup"
+        } else if dropped_and_picked_up_keys.contains(&match_key) {
+            "Dropped & Picked Up"
+        } else {
+            "-"
+        };
+
+        consolidated_rows.push(DialogueConsolidatedRow {
+            shift_group: current_dialogue_row.shift_group.clone(),
+            shift: current_dialogue_row.shift.clone(),
+            shift_type: shift_type.to_string(),
+            teacher_name: current_dialogue_row.teacher_name.clone(),
+            start_date: current_dialogue_row.start_date.clone(),
+            end_date: current_dialogue_row.end_date.clone(),
+        });
+    }
+
+    consolidated_rows
+}
+
+pub(crate) fn build_dialogue_csv_columns(
+    headers: &StringRecord,
+    mappings: &ColumnMappings,
+) -> Result<DialogueCsvColumns, Error> {
+    let find = |field: &str| find_mapped_header_index(headers, mappings, DIALOGUE_FILE_TYPE, field);
+
+    let start =
+        find("start").ok_or_else(|| anyhow::anyhow!("Dialogue CSV is missing a Start column"))?;
+    let finish =
+        find("finish").ok_or_else(|| anyhow::anyhow!("Dialogue CSV is missing a Finish column"))?;
+    let shift =
+        find("shift").ok_or_else(|| anyhow::anyhow!("Dialogue CSV is missing a Shift column"))?;
+    let shift_group = find("shift_group")
+        .ok_or_else(|| anyhow::anyhow!("Dialogue CSV is missing a Shift Group column"))?;
+    let teacher_name = find("teacher_name")
+        .ok_or_else(|| anyhow::anyhow!("Dialogue CSV is missing a Teacher Name column"))?;
+
+    Ok(DialogueCsvColumns {
+        start,
+        finish,
+        shift,
+        shift_group,
+        teacher_name,
+    })
+}
+
+pub(crate) fn scan_dialogue_csv_start_dates(
+    file_path: &str,
+    mappings: &ColumnMappings,
+) -> Result<(HashMap<NaiveDate, usize>, usize, usize), Error> {
+    let source = ImportSource::open_csv(
+        file_path,
+        &mut IngestionDiagnostics::default(),
+        |sample, delimiter| dialogue_csv_needs_unwrap(sample, delimiter, mappings),
+    )?;
+    let columns = build_dialogue_csv_columns(&source.headers, mappings)?;
+
+    let mut counts_by_date = HashMap::new();
+    let mut invalid_datetime_rows = 0usize;
+    let mut total_rows = 0usize;
+
+    source.for_each_record(|record| {
+        total_rows += 1;
+
+        let source_starts = collect_source_dialogue_datetimes(&record.text(columns.start));
+
+        if let Some(source_start) = source_starts.first() {
+            *counts_by_date.entry(source_start.date()).or_insert(0) += 1;
+        } else {
+            invalid_datetime_rows += 1;
+        }
+
+        ControlFlow::Continue(())
+    })?;
+
+    Ok((counts_by_date, invalid_datetime_rows, total_rows))
+}
+
+pub(crate) fn log_dialogue_file_date_diagnosis(
+    file_path: &str,
+    process_calendar: NaiveDate,
+    mappings: &ColumnMappings,
+) {
+    match scan_dialogue_csv_start_dates(file_path, mappings) {
+        Ok((counts_by_date, invalid_datetime_rows, total_rows)) => {
+            let rows_on_process_date = counts_by_date.get(&process_calendar).copied().unwrap_or(0);
+
+            let mut dates = counts_by_date.keys().copied().collect::<Vec<_>>();
+            dates.sort();
+
+            let date_range = match (dates.first(), dates.last()) {
+                (Some(min), Some(max)) => format!("{} to {}", min, max),
+                _ => "unknown".to_string(),
+            };
+
+            let mut top_dates = counts_by_date.into_iter().collect::<Vec<_>>();
+            top_dates.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+            let top_dates = top_dates
+                .into_iter()
+                .take(5)
+                .map(|(date, count)| format!("{} ({})", date, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            tracing::warn!(
+                "Dialogue file date scan for {}: {} total rows, {} on process date {}, range {}, top start dates: [{}], invalid datetimes: {}",
+                file_path,
+                total_rows,
+                rows_on_process_date,
+                process_calendar,
+                date_range,
+                top_dates,
+                invalid_datetime_rows
+            );
+        }
+        Err(error) => {
+            tracing::warn!(
+                "Could not scan dialogue dates in {}: {:?}",
+                file_path,
+                error
+            );
+        }
+    }
+}
+
+pub(crate) fn load_dialogue_rows_from_csv(
+    file_path: &str,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Result<Vec<DialogueRow>, Error> {
+    let source = ImportSource::open_csv(file_path, diagnostics, |sample, delimiter| {
+        dialogue_csv_needs_unwrap(sample, delimiter, &options.column_mappings)
+    })?;
+    let columns = build_dialogue_csv_columns(&source.headers, &options.column_mappings)?;
+    let profile = resolve_source_profile(&source, &[columns.start, columns.finish], options)?;
+
+    tracing::info!(
+        "🕐 Dialogue CSV {} datetime profile: {}",
+        file_path,
+        profile
+            .as_ref()
+            .map(|profile| profile.name.as_str())
+            .unwrap_or(options.profile_selection.name())
+    );
+
+    // Only rows on the process date are kept, so memory follows one day of shifts rather than
+    // the size of the export.
+    let mut rows = Vec::new();
+    let mut skipped_missing_columns = 0usize;
+    let mut skipped_invalid_datetime = 0usize;
+    let mut skipped_outside_process_date = 0usize;
+    let mut sample_outside_process_date: Option<(String, String)> = None;
+    let mut total_records = 0usize;
+    let mut previous_start: Option<DateTime<Utc>> = None;
+
+    source.for_each_record(|record| {
+        total_records += 1;
+        source.flag_undecodable(&record, diagnostics);
+
+        let start = record.text(columns.start);
+        let finish = record.text(columns.finish);
+        let shift = record.text(columns.shift);
+        let shift_group = record.text(columns.shift_group);
+        let teacher_name = record.text(columns.teacher_name);
+
+        if start.is_empty()
+            || finish.is_empty()
+            || shift.is_empty()
+            || shift_group.is_empty()
+            || teacher_name.is_empty()
+        {
+            skipped_missing_columns += 1;
+            if skipped_missing_columns <= 3 {
+                tracing::warn!(
+                    "Skipping dialogue CSV row {} in {} due to missing required columns",
+                    record.row,
+                    file_path
+                );
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let Some((source_start, source_end)) =
+            select_dialogue_start_end(&start, &finish, process_calendar, profile.as_ref(), options)
+        else {
+            if collect_profile_dialogue_datetimes(&start, profile.as_ref()).is_empty()
+                || collect_profile_dialogue_datetimes(&finish, profile.as_ref()).is_empty()
+            {
+                skipped_invalid_datetime += 1;
+                if skipped_invalid_datetime <= 3 {
+                    tracing::warn!(
+                        "Skipping dialogue CSV row {} in {} due to invalid start/finish datetimes: start={:?}, finish={:?}",
+                        record.row,
+                        file_path,
+                        start,
+                        finish
+                    );
+                }
+            } else {
+                skipped_outside_process_date += 1;
+                if sample_outside_process_date.is_none() {
+                    sample_outside_process_date = Some((start, finish));
+                }
+            }
+            return ControlFlow::Continue(());
+        };
+
+        let location = (file_path, record.row);
+        let Some(start_date) = resolve_source_datetime(
+            source_start,
+            options,
+            previous_start,
+            location,
+            diagnostics,
+        ) else {
+            return ControlFlow::Continue(());
+        };
+        let Some(end_date) =
+            resolve_source_datetime(source_end, options, Some(start_date), location, diagnostics)
+        else {
+            return ControlFlow::Continue(());
+        };
+
+        if is_unambiguous_in_source(source_start, options) {
+            previous_start = Some(start_date);
+        }
+
+        rows.push(DialogueRow {
+            shift_group,
+            shift,
+            teacher_name,
+            start_date: format_app_datetime(start_date, options),
+            end_date: format_app_datetime(end_date, options),
+        });
+
+        ControlFlow::Continue(())
+    })?;
+
+    if skipped_missing_columns > 3 {
+        tracing::warn!(
+            "Skipped {} additional dialogue rows in {} due to missing required columns",
+            skipped_missing_columns - 3,
+            file_path
+        );
+    }
+
+    if skipped_invalid_datetime > 3 {
+        tracing::warn!(
+            "Skipped {} additional dialogue rows in {} due to invalid datetimes",
+            skipped_invalid_datetime - 3,
+            file_path
+        );
+    }
+
+    if skipped_outside_process_date > 0 {
+        tracing::info!(
+            "Skipped {} dialogue rows in {} that do not fall on process date {}",
+            skipped_outside_process_date,
+            file_path,
+            process_calendar.format("%Y-%m-%d")
+        );
+        if let Some((sample_start, sample_finish)) = sample_outside_process_date {
+            tracing::info!(
+                "Example row outside process date in {}: start={:?}, finish={:?} (source tz: {})",
+                file_path,
+                sample_start,
+                sample_finish,
+                options.source_timezone
+            );
+        }
+    }
+
+    if rows.is_empty() && total_records > 0 {
+        tracing::warn!(
+            "No dialogue rows loaded from {} for process date {} (records: {}, missing columns: {}, invalid datetimes: {}, outside process date: {})",
+            file_path,
+            process_calendar,
+            total_records,
+            skipped_missing_columns,
+            skipped_invalid_datetime,
+            skipped_outside_process_date
+        );
+        log_dialogue_file_date_diagnosis(file_path, process_calendar, &options.column_mappings);
+    }
+
+    tracing::info!("📄 Loaded {} dialogue rows from {}", rows.len(), file_path);
+
+    Ok(rows)
+}
+
+pub(crate) fn load_dialogue_rows_from_xlsx(
+    file_path: &str,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Result<Vec<DialogueRow>, Error> {
+    let source = ImportSource::open_xlsx(file_path, |headers| {
+        build_dialogue_csv_columns(headers, &options.column_mappings).is_ok()
+    })
+    .map_err(|_| {
+        anyhow::anyhow!(
+            "Dialogue XLSX {} has no header row with Start, Finish, Shift, Shift Group and Teacher Name columns",
+            file_path
+        )
+    })?;
+    let columns = build_dialogue_csv_columns(&source.headers, &options.column_mappings)?;
+    let profile = resolve_source_profile(&source, &[columns.start, columns.finish], options)?;
+
+    let mut rows = Vec::new();
+
+    // Grouped report layouts only print the shift group and teacher on the first row of a group.
+    let mut shift_group_temp = String::new();
+    let mut teacher_name_temp = String::new();
+    let mut previous_start: Option<DateTime<Utc>> = None;
+
+    source.for_each_record(|record| {
+        if source.skip_summary_row(&record, &[columns.start, columns.finish], diagnostics) {
+            return ControlFlow::Continue(());
+        }
+
+        let shift_group = record.text(columns.shift_group);
+        if !shift_group.is_empty() {
+            shift_group_temp = shift_group;
+        }
+
+        let teacher_name = record.text(columns.teacher_name);
+        if !teacher_name.is_empty() {
+            teacher_name_temp = teacher_name;
+        }
+
+        let shift = record.text(columns.shift);
+
+        let (Some(start), Some(finish)) = (record.cell(columns.start), record.cell(columns.finish))
+        else {
+            return ControlFlow::Continue(());
+        };
+
+        if teacher_name_temp.is_empty() || shift.is_empty() {
+            return ControlFlow::Continue(());
+        }
+
+        let Some((source_start, source_end)) = select_dialogue_start_end_candidates(
+            start.dialogue_candidates(profile.as_ref()),
+            finish.dialogue_candidates(profile.as_ref()),
+            process_calendar,
+            options,
+        ) else {
+            return ControlFlow::Continue(());
+        };
+
+        let location = (file_path, record.row);
+        let Some(start_date) =
+            resolve_source_datetime(source_start, options, previous_start, location, diagnostics)
+        else {
+            return ControlFlow::Continue(());
+        };
+        let Some(end_date) =
+            resolve_source_datetime(source_end, options, Some(start_date), location, diagnostics)
+        else {
+            return ControlFlow::Continue(());
+        };
+
+        if is_unambiguous_in_source(source_start, options) {
+            previous_start = Some(start_date);
+        }
+
+        rows.push(DialogueRow {
+            shift_group: shift_group_temp.clone(),
+            shift,
+            teacher_name: teacher_name_temp.clone(),
+            start_date: format_app_datetime(start_date, options),
+            end_date: format_app_datetime(end_date, options),
+        });
+
+        ControlFlow::Continue(())
+    })?;
+
+    tracing::info!("📄 Loaded {} dialogue rows from {}", rows.len(), file_path);
+
+    Ok(rows)
+}
+
+pub fn load_dialogue_rows(
+    file_path: &str,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Result<Vec<DialogueRow>, Error> {
+    if is_xlsx_path(file_path) {
+        tracing::info!("📄 Loading {} as XLSX", file_path);
+        load_dialogue_rows_from_xlsx(file_path, process_calendar, options, diagnostics)
+    } else {
+        tracing::info!("📄 Loading {} as CSV", file_path);
+        load_dialogue_rows_from_csv(file_path, process_calendar, options, diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Timelike};
+    use chrono_tz::America::New_York;
+    use csv::ReaderBuilder;
+    use xlsx_writer::{write_xlsx, XlsxCell};
+
+    use super::{
+        build_dialogue_csv_columns, consolidate_dialogue_rows, dialogue_csv_needs_unwrap,
+        load_dialogue_rows_from_csv, load_dialogue_rows_from_xlsx, normalize_shift_identifier,
+        parse_dialogue_datetime, DialogueRow,
+    };
+    use crate::{
+        column_mappings::ColumnMappings,
+        datetime_profiles::{DateTimeProfile, DateTimeProfileSelection},
+        import::{
+            convert_source_to_app_timezone, parse_process_calendar_date, unwrap_excel_quoted_rows,
+            ImportOptions, IngestionDiagnostics,
+        },
+        test_support::{import_options, load_invoicing},
+        timezones::DstPolicy,
+    };
+
+    fn make_row(
+        shift_group: &str,
+        shift: &str,
+        teacher_name: &str,
+        start_date: &str,
+        end_date: &str,
+    ) -> DialogueRow {
+        DialogueRow {
+            shift_group: shift_group.to_string(),
+            shift: shift.to_string(),
+            teacher_name: teacher_name.to_string(),
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+        }
+    }
+
+    #[test]
+    fn normalizes_numeric_shift_identifiers_from_csv_and_xlsx() {
+        assert_eq!(normalize_shift_identifier("12345"), "12345");
+        assert_eq!(normalize_shift_identifier("12345.0"), "12345");
+        assert_eq!(normalize_shift_identifier("12,345.000"), "12345");
+    }
+
+    #[test]
+    fn does_not_mark_rows_as_dropped_when_shift_only_differs_by_numeric_format() {
+        let first_dialogue_rows = vec![make_row(
+            "Alpha",
+            "12345",
+            "Teacher One",
+            "2026-04-20 08:00:00",
+            "2026-04-20 09:00:00",
+        )];
+        let second_dialogue_rows = vec![make_row(
+            "Alpha",
+            "12345.0",
+            "Teacher One",
+            "2026-04-20 08:00:00",
+            "2026-04-20 09:00:00",
+        )];
+
+        let consolidated_rows =
+            consolidate_dialogue_rows(&first_dialogue_rows, &second_dialogue_rows);
+
+        assert_eq!(consolidated_rows.len(), 1);
+        assert_eq!(consolidated_rows[0].shift_type, "-");
+        assert_eq!(consolidated_rows[0].shift, "12345.0");
+    }
+
+    #[test]
+    fn parses_dialogue_datetime_with_single_digit_month_day_and_hour() {
+        let parsed = convert_source_to_app_timezone(
+            parse_dialogue_datetime("5/2/2026 9:00 AM").expect("datetime"),
+            &import_options(DateTimeProfileSelection::Flexible),
+        );
+        assert_eq!(parsed.date(), NaiveDate::from_ymd_opt(2026, 5, 2).unwrap());
+        // 09:00 UTC -> 11:00 Africa/Johannesburg (UTC+2)
+        assert_eq!(parsed.hour(), 11);
+        assert_eq!(parsed.minute(), 0);
+    }
+
+    #[test]
+    fn us_late_evening_shift_falls_on_next_day_in_johannesburg() {
+        let parsed = convert_source_to_app_timezone(
+            parse_dialogue_datetime("5/1/2026 11:00 PM").expect("datetime"),
+            &import_options(DateTimeProfileSelection::Flexible),
+        );
+        assert_eq!(parsed.date(), NaiveDate::from_ymd_opt(2026, 5, 2).unwrap());
+        assert_eq!(parsed.hour(), 1);
+
+        let dir =
+            std::env::temp_dir().join(format!("sergio-ar-dialogue-tz-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-2.csv");
+        std::fs::write(
+            &file_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name,Day of Week\n\
+             5/1/2026 11:00 PM,5/2/2026 1:00 AM,T-1,Group,Teacher,Saturday\n",
+        )
+        .unwrap();
+
+        let rows_may_1 = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-05-01").unwrap(),
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+        let rows_may_2 = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-05-02").unwrap(),
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(rows_may_1.len(), 1);
+        assert_eq!(rows_may_2.len(), 1);
+    }
+
+    #[test]
+    fn parses_dialogue_export_csv_headers_and_row() {
+        let contents = r#""Start,""Finish"",""Shift: Shift Number"",""Resource: Shift Group"",""Resource: Resource Name"",""Day of Week"""#
+            .to_string()
+            + "\n"
+            + r#""5/2/2026 9:00 AM,""5/2/2026 11:00 AM"",""T-5412533"",""JEN 4 - PM"",""Babalwa Magongo"",""Saturday"""#;
+
+        let preprocessed = unwrap_excel_quoted_rows(&contents, b',');
+        let mut reader = ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .delimiter(b',')
+            .flexible(true)
+            .from_reader(preprocessed.as_bytes());
+
+        let headers = reader.headers().unwrap().clone();
+        let columns =
+            build_dialogue_csv_columns(&headers, &ColumnMappings::defaults()).expect("columns");
+
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(record.get(columns.start).unwrap(), "5/2/2026 9:00 AM");
+        assert_eq!(record.get(columns.finish).unwrap(), "5/2/2026 11:00 AM");
+        assert_eq!(record.get(columns.shift).unwrap(), "T-5412533");
+        assert_eq!(record.get(columns.shift_group).unwrap(), "JEN 4 - PM");
+        assert_eq!(record.get(columns.teacher_name).unwrap(), "Babalwa Magongo");
+    }
+
+    #[test]
+    fn loads_uk_formatted_dates_on_us_process_date() {
+        let dir =
+            std::env::temp_dir().join(format!("sergio-ar-dialogue-uk-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-2.csv");
+        std::fs::write(
+            &file_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name,Day of Week\n\
+             1/5/2026 9:00 AM,1/5/2026 11:00 AM,T-5412533,JEN 4 - PM,Babalwa Magongo,Friday\n",
+        )
+        .unwrap();
+
+        let process_calendar = parse_process_calendar_date("2026-05-01").unwrap();
+        let rows = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            process_calendar,
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].start_date, "2026-05-01 11:00:00");
+    }
+
+    #[test]
+    fn auto_profile_reads_ambiguous_rows_using_the_rest_of_the_file() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-auto-profile-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &file_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n\
+             03/04/2026 9:00 AM,03/04/2026 11:00 AM,T-1,Group,Teacher One\n\
+             25/04/2026 9:00 AM,25/04/2026 11:00 AM,T-2,Group,Teacher Two\n",
+        )
+        .unwrap();
+
+        let rows = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-04-03").unwrap(),
+            &import_options(DateTimeProfileSelection::Auto),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].shift, "T-1");
+        assert_eq!(rows[0].start_date, "2026-04-03 11:00:00");
+    }
+
+    #[test]
+    fn auto_profile_fails_when_every_row_is_ambiguous() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-ambiguous-profile-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &file_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n\
+             03/04/2026 9:00 AM,03/04/2026 11:00 AM,T-1,Group,Teacher One\n",
+        )
+        .unwrap();
+
+        let result = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-04-03").unwrap(),
+            &import_options(DateTimeProfileSelection::Auto),
+            &mut IngestionDiagnostics::default(),
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(result.unwrap_err().to_string().contains("ambiguous"));
+    }
+
+    #[test]
+    fn us_fall_back_rows_follow_the_dst_policy_and_are_flagged() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-dst-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &file_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n\
+             11/1/2026 1:30 AM,11/1/2026 3:00 AM,T-1,Group,Teacher One\n",
+        )
+        .unwrap();
+
+        let load = |dst_policy| {
+            let mut diagnostics = IngestionDiagnostics::default();
+            let rows = load_dialogue_rows_from_csv(
+                file_path.to_str().unwrap(),
+                parse_process_calendar_date("2026-11-01").unwrap(),
+                &ImportOptions {
+                    source_timezone: New_York,
+                    dst_policy,
+                    ..import_options(DateTimeProfileSelection::Named(DateTimeProfile::us()))
+                },
+                &mut diagnostics,
+            )
+            .expect("rows");
+
+            (rows, diagnostics)
+        };
+
+        let (latest_rows, latest_diagnostics) = load(DstPolicy::Latest);
+        let (rejected_rows, rejected_diagnostics) = load(DstPolicy::Reject);
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        // 01:30 EST is 06:30Z, 08:30 in Johannesburg; 03:00 EST is 10:00 in Johannesburg.
+        assert_eq!(latest_rows.len(), 1);
+        assert_eq!(latest_rows[0].start_date, "2026-11-01 08:30:00");
+        assert_eq!(latest_rows[0].end_date, "2026-11-01 10:00:00");
+        assert_eq!(latest_diagnostics.count("dst_ambiguous"), 1);
+        assert_eq!(latest_diagnostics.issues[0].row, 2);
+
+        assert!(rejected_rows.is_empty());
+        assert_eq!(rejected_diagnostics.count("dst_ambiguous"), 1);
+    }
+
+    #[test]
+    fn infer_policy_resolves_consecutive_ambiguous_rows_against_the_last_unambiguous_one() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-dst-infer-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &file_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n\
+             11/1/2026 3:00 AM,11/1/2026 4:00 AM,T-1,Group,Teacher One\n\
+             11/1/2026 1:05 AM,11/1/2026 3:00 AM,T-2,Group,Teacher Two\n\
+             11/1/2026 1:40 AM,11/1/2026 3:30 AM,T-3,Group,Teacher Three\n",
+        )
+        .unwrap();
+
+        let mut diagnostics = IngestionDiagnostics::default();
+        let rows = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-11-01").unwrap(),
+            &ImportOptions {
+                source_timezone: New_York,
+                dst_policy: DstPolicy::Infer,
+                ..import_options(DateTimeProfileSelection::Named(DateTimeProfile::us()))
+            },
+            &mut diagnostics,
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let start_of = |shift: &str| {
+            rows.iter()
+                .find(|row| row.shift == shift)
+                .map(|row| row.start_date.clone())
+                .unwrap()
+        };
+
+        // Both 01:05 and 01:40 sit closer to 03:00 EST (08:00Z) as EST. Measured from T-2's
+        // guessed 06:05Z instead, 01:40 would have been read as EDT (05:40Z, 07:40 here).
+        assert_eq!(start_of("T-2"), "2026-11-01 08:05:00");
+        assert_eq!(start_of("T-3"), "2026-11-01 08:40:00");
+        assert_eq!(diagnostics.count("dst_ambiguous"), 2);
+    }
+
+    #[test]
+    fn excel_export_requires_preprocess_for_data_rows_even_when_headers_parse() {
+        let contents = r#""Start,""Finish"",""Shift: Shift Number"",""Resource: Shift Group"",""Resource: Resource Name"",""Day of Week"""#
+            .to_string()
+            + "\n"
+            + r#""5/2/2026 9:00 AM,""5/2/2026 11:00 AM"",""T-5412533"",""JEN 4 - PM"",""Babalwa Magongo"",""Saturday"""#;
+
+        assert!(
+            dialogue_csv_needs_unwrap(&contents, b',', &ColumnMappings::defaults()),
+            "excel export must be unwrapped before parsing"
+        );
+
+        let process_calendar_may_1 = parse_process_calendar_date("2026-05-01").unwrap();
+        let process_calendar_may_2 = parse_process_calendar_date("2026-05-02").unwrap();
+
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-excel-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-2.csv");
+        std::fs::write(&file_path, &contents).unwrap();
+
+        let rows_may_1 = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            process_calendar_may_1,
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+        let rows_may_2 = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            process_calendar_may_2,
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(rows_may_1.len(), 0);
+        assert_eq!(rows_may_2.len(), 1);
+        assert_eq!(rows_may_2[0].shift, "T-5412533");
+    }
+
+    #[test]
+    fn unwrapped_excel_export_splits_into_dialogue_columns() {
+        let contents = r#""Start,""Finish"",""Shift: Shift Number"",""Resource: Shift Group"",""Resource: Resource Name"",""Day of Week"""#
+            .to_string()
+            + "\n"
+            + r#""5/2/2026 9:00 AM,""5/2/2026 11:00 AM"",""T-5412533"",""JEN 4 - PM"",""Babalwa Magongo"",""Saturday"""#;
+
+        assert!(dialogue_csv_needs_unwrap(
+            &contents,
+            b',',
+            &ColumnMappings::defaults()
+        ));
+
+        let prepared = unwrap_excel_quoted_rows(&contents, b',');
+        let mut reader = ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .delimiter(b',')
+            .flexible(true)
+            .from_reader(prepared.as_bytes());
+
+        let headers = reader.headers().unwrap();
+        assert_eq!(headers.len(), 6);
+        build_dialogue_csv_columns(headers, &ColumnMappings::defaults()).expect("dialogue columns");
+    }
+
+    #[test]
+    fn identical_export_rows_match_across_two_dialogue_snapshots() {
+        let row = make_row(
+            "JEN 4 - PM",
+            "T-5412533",
+            "Babalwa Magongo",
+            "5/2/2026 9:00 AM",
+            "5/2/2026 11:00 AM",
+        );
+        let first = vec![row.clone()];
+        let second = vec![row];
+
+        let consolidated = consolidate_dialogue_rows(&first, &second);
+        assert_eq!(consolidated.len(), 1);
+        assert_eq!(consolidated[0].shift_type, "-");
+        assert!(
+            consolidated
+                .iter()
+                .all(|entry| entry.shift_type != "Dropped"),
+            "expected no dropped rows when snapshots match"
+        );
+    }
+
+    #[test]
+    fn loads_dialogue_rows_from_export_style_csv() {
+        let dir =
+            std::env::temp_dir().join(format!("sergio-ar-dialogue-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &file_path,
+            r#""Start,""Finish"",""Shift: Shift Number"",""Resource: Shift Group"",""Resource: Resource Name"",""Day of Week"""#
+                .to_string()
+                + "\n"
+                + r#""5/2/2026 9:00 AM,""5/2/2026 11:00 AM"",""T-5412533"",""JEN 4 - PM"",""Babalwa Magongo"",""Saturday"""#,
+        )
+        .unwrap();
+
+        let process_calendar = parse_process_calendar_date("2026-05-02").unwrap();
+        let rows = load_dialogue_rows_from_csv(
+            file_path.to_str().unwrap(),
+            process_calendar,
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].shift, "T-5412533");
+        assert_eq!(rows[0].teacher_name, "Babalwa Magongo");
+        assert_eq!(rows[0].start_date, "2026-05-02 11:00:00");
+        assert_eq!(rows[0].end_date, "2026-05-02 13:00:00");
+    }
+
+    #[test]
+    fn matching_snapshots_with_different_datetime_strings_are_not_marked_dropped() {
+        let first_dialogue_rows = vec![make_row(
+            "JEN 4 - PM",
+            "T-5412533",
+            "Babalwa Magongo",
+            "5/2/2026 9:00 AM",
+            "5/2/2026 11:00 AM",
+        )];
+        let second_dialogue_rows = vec![make_row(
+            "JEN 4 - PM",
+            "T-5412533",
+            "Babalwa Magongo",
+            "2026-05-02 09:00:00",
+            "2026-05-02 11:00:00",
+        )];
+
+        let consolidated = consolidate_dialogue_rows(&first_dialogue_rows, &second_dialogue_rows);
+
+        assert_eq!(consolidated.len(), 1);
+        assert_eq!(consolidated[0].shift_type, "-");
+    }
+
+    #[test]
+    fn marks_internal_pickups_after_shift_normalization() {
+        let first_dialogue_rows = vec![make_row(
+            "Alpha",
+            "12345",
+            "Teacher One",
+            "2026-04-20 08:00:00",
+            "2026-04-20 09:00:00",
+        )];
+        let second_dialogue_rows = vec![make_row(
+            "Alpha",
+            "12345.0",
+            "Teacher Two",
+            "2026-04-20 08:00:00",
+            "2026-04-20 09:00:00",
+        )];
+
+        let consolidated_rows =
+            consolidate_dialogue_rows(&first_dialogue_rows, &second_dialogue_rows);
+
+        assert_eq!(consolidated_rows.len(), 1);
+        assert_eq!(consolidated_rows[0].shift_type, "Internal Pickup");
+    }
+
+    #[test]
+    fn loads_xlsx_dialogue_rows_by_header_with_excel_date_cells() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-xlsx-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-1.xlsx");
+
+        let at = |hour: u32| {
+            XlsxCell::DateTime(
+                NaiveDate::from_ymd_opt(2026, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(hour, 0, 0)
+                    .unwrap(),
+            )
+        };
+        let text = XlsxCell::text;
+
+        let workbook = write_xlsx(
+            "Report",
+            &[
+                vec![text("Shift Report")],
+                vec![text("Filtered By: Status equals Published")],
+                vec![],
+                vec![
+                    text("Day of Week"),
+                    text("Resource: Resource Name"),
+                    text("Resource: Shift Group"),
+                    text("Shift: Shift Number"),
+                    text("Start"),
+                    text("Finish"),
+                ],
+                vec![
+                    text("Friday"),
+                    text("Teacher One"),
+                    text("JEN 4 - PM"),
+                    text("T-1"),
+                    at(7),
+                    at(9),
+                ],
+                vec![
+                    XlsxCell::Empty,
+                    XlsxCell::Empty,
+                    XlsxCell::Empty,
+                    text("T-2"),
+                    text("5/1/2026 10:00 AM"),
+                    text("5/1/2026 11:00 AM"),
+                ],
+                vec![text("Subtotal"), XlsxCell::Number(2.0)],
+                vec![
+                    text("Friday"),
+                    text("Teacher Two"),
+                    XlsxCell::Empty,
+                    XlsxCell::Number(12.0),
+                    at(12),
+                    at(13),
+                ],
+                vec![
+                    XlsxCell::Empty,
+                    text("Sum Yee Lin"),
+                    text("Total Learning"),
+                    text("T-3"),
+                    at(14),
+                    at(15),
+                ],
+                vec![text("Grand Total (4 records)")],
+                vec![text("Confidential Information - Do Not Distribute")],
+                vec![text("Copyright (c) 2000-2026 salesforce.com, inc.")],
+            ],
+        )
+        .unwrap();
+        std::fs::write(&file_path, workbook).unwrap();
+
+        let mut diagnostics = IngestionDiagnostics::default();
+        let rows = load_dialogue_rows_from_xlsx(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-05-01").unwrap(),
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut diagnostics,
+        )
+        .expect("rows");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let summary = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.shift_group.as_str(),
+                    row.teacher_name.as_str(),
+                    row.shift.as_str(),
+                    row.start_date.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("JEN 4 - PM", "Teacher One", "T-1", "2026-05-01 09:00:00"),
+                ("JEN 4 - PM", "Teacher One", "T-2", "2026-05-01 12:00:00"),
+                ("JEN 4 - PM", "Teacher Two", "12", "2026-05-01 14:00:00"),
+                (
+                    "Total Learning",
+                    "Sum Yee Lin",
+                    "T-3",
+                    "2026-05-01 16:00:00"
+                ),
+            ]
+        );
+        assert_eq!(diagnostics.count("summary_row"), 4);
+    }
+
+    #[test]
+    fn rejects_xlsx_without_a_dialogue_header_row() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-xlsx-no-header-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("dialogue-2.xlsx");
+        let workbook = write_xlsx(
+            "Report",
+            &[vec![XlsxCell::text("Name"), XlsxCell::text("Value")]],
+        )
+        .unwrap();
+        std::fs::write(&file_path, workbook).unwrap();
+
+        let error = load_dialogue_rows_from_xlsx(
+            file_path.to_str().unwrap(),
+            parse_process_calendar_date("2026-05-01").unwrap(),
+            &import_options(DateTimeProfileSelection::Flexible),
+            &mut IngestionDiagnostics::default(),
+        )
+        .expect_err("no header");
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(error.to_string().contains("no header row"));
+    }
+
+    #[test]
+    fn maps_renamed_columns_through_configured_aliases() {
+        let headers =
+            csv::StringRecord::from(vec!["Start", "Finish", "Shift Ref", "Group", "Tutor"]);
+
+        assert!(build_dialogue_csv_columns(&headers, &ColumnMappings::defaults()).is_err());
+
+        let mappings = ColumnMappings::from_aliases([
+            ("dialogue", "start", "Start"),
+            ("dialogue", "finish", "Finish"),
+            ("dialogue", "shift", "Shift Ref"),
+            ("dialogue", "shift_group", "Group"),
+            ("dialogue", "teacher_name", "tutor"),
+        ]);
+
+        let columns = build_dialogue_csv_columns(&headers, &mappings).expect("columns");
+
+        assert_eq!(columns.shift, 2);
+        assert_eq!(columns.shift_group, 3);
+        assert_eq!(columns.teacher_name, 4);
+    }
+
+    #[test]
+    fn loads_dialogue_names_containing_commas_from_excel_and_plain_csv() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-dialogue-comma-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let excel_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &excel_path,
+            [
+                r#""Start,""Finish"",""Shift: Shift Number"",""Resource: Shift Group"",""Resource: Resource Name"",""Day of Week"""#,
+                r#""5/2/2026 9:00 AM,""5/2/2026 11:00 AM"",""T-5412533"",""JEN 4, PM"",""Magongo, Babalwa"",""Saturday"""#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let plain_path = dir.join("dialogue-2.csv");
+        std::fs::write(
+            &plain_path,
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n\
+             5/2/2026 9:00 AM,5/2/2026 11:00 AM,T-5412533,\"JEN 4, PM\",\"Magongo, Babalwa\"\n",
+        )
+        .unwrap();
+
+        let load = |path: &std::path::Path| {
+            load_dialogue_rows_from_csv(
+                path.to_str().unwrap(),
+                parse_process_calendar_date("2026-05-02").unwrap(),
+                &import_options(DateTimeProfileSelection::Flexible),
+                &mut IngestionDiagnostics::default(),
+            )
+            .expect("rows")
+        };
+
+        let excel_rows = load(&excel_path);
+        let plain_rows = load(&plain_path);
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        for rows in [&excel_rows, &plain_rows] {
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].teacher_name, "Magongo, Babalwa");
+            assert_eq!(rows[0].shift_group, "JEN 4, PM");
+            assert_eq!(rows[0].shift, "T-5412533");
+        }
+    }
+
+    #[test]
+    fn decodes_windows_1252_and_bomless_utf16_imports() {
+        let dir =
+            std::env::temp_dir().join(format!("sergio-ar-encoding-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let dialogue_path = dir.join("dialogue-1.csv");
+        std::fs::write(
+            &dialogue_path,
+            b"Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n\
+              5/2/2026 9:00 AM,5/2/2026 11:00 AM,T-1,JEN 4 - PM,Jos\xE9 M\xFCller\n",
+        )
+        .unwrap();
+
+        let invoicing = "Teacher_Name\tEligible_Status\tActivity_Start_Time\tActivity_End_Time\tshift_name_tsm\n\
+                         José Müller\tEligible\t05/02/2026 09:00:00 AM\t05/02/2026 11:00:00 AM\tT-1\n";
+        std::fs::write(
+            dir.join("invoicing-report.csv"),
+            invoicing
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        let options = import_options(DateTimeProfileSelection::Flexible);
+        let process_calendar = parse_process_calendar_date("2026-05-02").unwrap();
+        let mut diagnostics = IngestionDiagnostics::default();
+
+        let dialogue_rows = load_dialogue_rows_from_csv(
+            dialogue_path.to_str().unwrap(),
+            process_calendar,
+            &options,
+            &mut diagnostics,
+        )
+        .expect("dialogue rows");
+        let invoicing_rows = load_invoicing(&dir, process_calendar, &options, &mut diagnostics);
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(dialogue_rows[0].teacher_name, "José Müller");
+        assert_eq!(invoicing_rows[0].teacher_name, "José Müller");
+        assert_eq!(diagnostics.count("encoding_fallback"), 1);
+        assert_eq!(diagnostics.count("encoding_error"), 0);
+    }
+}
@@ -0,0 +1,378 @@
+//! Generated checks for the invariants of [consolidate_dialogue_rows].
+//!
+//! Snapshots are drawn from small pools of shifts, times, teachers and shift groups, with
+//! spelling variants that share a match key, so collisions and duplicates are common. A failing
+//! case is shrunk by dropping rows and reported with its seed. Shrunk failures are kept in
+//! [REGRESSIONS] and checked before any generated case.
+
+use std::collections::HashSet;
+
+use crate::dialogue::{build_dialogue_match_key, consolidate_dialogue_rows, DialogueRow};
+
+const CASES: u64 = 512;
+
+const SHIFTS: &[&str] = &["T-1", "T-2", "12345", "12345.0", "12,345"];
+
+/// The first two slots are the same times written in the two formats Dialogue exports use.
+const SLOTS: &[(&str, &str)] = &[
+    ("2026-05-01 09:00:00", "2026-05-01 11:00:00"),
+    ("5/1/2026 9:00 AM", "5/1/2026 11:00 AM"),
+    ("2026-05-01 12:00:00", "2026-05-01 13:00:00"),
+];
+
+const TEACHERS: &[&str] = &[
+    "Ann Smith",
+    "ann  smith",
+    "Babalwa Magongo",
+    "Teacher Three",
+];
+
+const SHIFT_GROUPS: &[&str] = &["JEN 4", "jen 4 ", "JEN 5"];
+
+/// `(first, second)` snapshots as `(shift_group, shift, teacher, slot)`.
+type Fixture = (
+    &'static [(&'static str, &'static str, &'static str, usize)],
+    &'static [(&'static str, &'static str, &'static str, usize)],
+);
+
+/// Counterexamples found by the generators, kept so they are checked on every run.
+const REGRESSIONS: &[Fixture] = &[
+    // Two teachers holding the same shift in different groups compared as "Dropped & Picked Up"
+    // against themselves, because only the last assignment per key was remembered.
+    (
+        &[
+            ("jen 4 ", "12345.0", "Babalwa Magongo", 2),
+            ("JEN 5", "12,345", "Teacher Three", 2),
+        ],
+        &[],
+    ),
+    // Which duplicate came last decided between "Internal Pickup" and "Dropped & Picked Up".
+    (
+        &[
+            ("JEN 5", "12,345", "Babalwa Magongo", 0),
+            ("jen 4 ", "12345", "Babalwa Magongo", 0),
+        ],
+        &[("jen 4 ", "12345", "Ann Smith", 0)],
+    ),
+];
+
+type Property = fn(&[DialogueRow], &[DialogueRow], u64) -> Result<(), String>;
+
+/// xorshift64*, so failures reproduce from the printed seed without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, values: &[&'a str]) -> &'a str {
+        values[self.below(values.len())]
+    }
+
+    fn shuffle<T>(&mut self, values: &mut [T]) {
+        for index in (1..values.len()).rev() {
+            values.swap(index, self.below(index + 1));
+        }
+    }
+}
+
+fn row(shift_group: &str, shift: &str, teacher_name: &str, slot: usize) -> DialogueRow {
+    DialogueRow {
+        shift_group: shift_group.to_string(),
+        shift: shift.to_string(),
+        teacher_name: teacher_name.to_string(),
+        start_date: SLOTS[slot].0.to_string(),
+        end_date: SLOTS[slot].1.to_string(),
+    }
+}
+
+fn random_row(rng: &mut Rng) -> DialogueRow {
+    let slot = rng.below(SLOTS.len());
+
+    row(
+        rng.pick(SHIFT_GROUPS),
+        rng.pick(SHIFTS),
+        rng.pick(TEACHERS),
+        slot,
+    )
+}
+
+/// A first snapshot and a second one derived from it, so most keys overlap.
+fn snapshots(rng: &mut Rng) -> (Vec<DialogueRow>, Vec<DialogueRow>) {
+    let first = (0..rng.below(7))
+        .map(|_| random_row(rng))
+        .collect::<Vec<_>>();
+    let mut second = Vec::new();
+
+    for first_row in &first {
+        let mut second_row = first_row.clone();
+
+        match rng.below(6) {
+            0 => continue,
+            1 => second_row.teacher_name = rng.pick(TEACHERS).to_string(),
+            2 => second_row.shift_group = rng.pick(SHIFT_GROUPS).to_string(),
+            3 => {
+                second_row.teacher_name = rng.pick(TEACHERS).to_string();
+                second_row.shift_group = rng.pick(SHIFT_GROUPS).to_string();
+            }
+            _ => {}
+        }
+
+        second.push(second_row);
+    }
+
+    for _ in 0..rng.below(3) {
+        second.push(random_row(rng));
+    }
+
+    rng.shuffle(&mut second);
+
+    (first, second)
+}
+
+fn fields(row: &DialogueRow) -> [&str; 5] {
+    [
+        &row.shift_group,
+        &row.shift,
+        &row.teacher_name,
+        &row.start_date,
+        &row.end_date,
+    ]
+}
+
+fn every_second_row_appears_once(
+    first: &[DialogueRow],
+    second: &[DialogueRow],
+    _seed: u64,
+) -> Result<(), String> {
+    let consolidated = consolidate_dialogue_rows(first, second);
+    let kept = consolidated
+        .iter()
+        .filter(|row| row.shift_type != "Dropped")
+        .map(|row| {
+            [
+                row.shift_group.as_str(),
+                &row.shift,
+                &row.teacher_name,
+                &row.start_date,
+                &row.end_date,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let expected = second.iter().map(fields).collect::<Vec<_>>();
+
+    if kept == expected {
+        Ok(())
+    } else {
+        Err(format!("kept {:?}, expected {:?}", kept, expected))
+    }
+}
+
+fn dropped_rows_come_from_the_first_snapshot(
+    first: &[DialogueRow],
+    second: &[DialogueRow],
+    _seed: u64,
+) -> Result<(), String> {
+    let second_keys = second
+        .iter()
+        .map(build_dialogue_match_key)
+        .collect::<HashSet<_>>();
+    let dropped = consolidate_dialogue_rows(first, second)
+        .into_iter()
+        .filter(|row| row.shift_type == "Dropped")
+        .map(|row| DialogueRow {
+            shift_group: row.shift_group,
+            shift: row.shift,
+            teacher_name: row.teacher_name,
+            start_date: row.start_date,
+            end_date: row.end_date,
+        })
+        .collect::<Vec<_>>();
+    let expected = first
+        .iter()
+        .filter(|row| !second_keys.contains(&build_dialogue_match_key(row)))
+        .collect::<Vec<_>>();
+
+    if dropped
+        .iter()
+        .map(fields)
+        .eq(expected.iter().map(|row| fields(row)))
+    {
+        Ok(())
+    } else {
+        Err(format!("dropped {:?}, expected {:?}", dropped, expected))
+    }
+}
+
+fn a_snapshot_compared_with_itself_is_unchanged(
+    first: &[DialogueRow],
+    _second: &[DialogueRow],
+    _seed: u64,
+) -> Result<(), String> {
+    let consolidated = consolidate_dialogue_rows(first, first);
+
+    if consolidated.len() == first.len() && consolidated.iter().all(|row| row.shift_type == "-") {
+        Ok(())
+    } else {
+        Err(format!("classified {:?}", consolidated))
+    }
+}
+
+fn row_order_does_not_change_classifications(
+    first: &[DialogueRow],
+    second: &[DialogueRow],
+    seed: u64,
+) -> Result<(), String> {
+    let classify = |first: &[DialogueRow], second: &[DialogueRow]| {
+        let mut classified = consolidate_dialogue_rows(first, second)
+            .into_iter()
+            .map(|row| {
+                (
+                    row.shift_type,
+                    row.shift_group,
+                    row.shift,
+                    row.teacher_name,
+                    row.start_date,
+                )
+            })
+            .collect::<Vec<_>>();
+        classified.sort();
+        classified
+    };
+
+    let original = classify(first, second);
+
+    // Reversing always swaps a pair of rows, which a short shuffle might not.
+    let mut reversed_first = first.to_vec();
+    let mut reversed_second = second.to_vec();
+    reversed_first.reverse();
+    reversed_second.reverse();
+
+    let mut rng = Rng::new(seed);
+    let mut shuffled_first = first.to_vec();
+    let mut shuffled_second = second.to_vec();
+    rng.shuffle(&mut shuffled_first);
+    rng.shuffle(&mut shuffled_second);
+
+    for (first, second) in [
+        (reversed_first, reversed_second),
+        (shuffled_first, shuffled_second),
+    ] {
+        let reordered = classify(&first, &second);
+
+        if reordered != original {
+            return Err(format!(
+                "original {:?}, after reordering {:?}",
+                original, reordered
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes rows one at a time for as long as the property keeps failing.
+fn shrink(
+    property: Property,
+    mut first: Vec<DialogueRow>,
+    mut second: Vec<DialogueRow>,
+    seed: u64,
+) -> (Vec<DialogueRow>, Vec<DialogueRow>) {
+    'shrinking: loop {
+        for index in 0..first.len() {
+            let mut candidate = first.clone();
+            candidate.remove(index);
+
+            if property(&candidate, &second, seed).is_err() {
+                first = candidate;
+                continue 'shrinking;
+            }
+        }
+
+        for index in 0..second.len() {
+            let mut candidate = second.clone();
+            candidate.remove(index);
+
+            if property(&first, &candidate, seed).is_err() {
+                second = candidate;
+                continue 'shrinking;
+            }
+        }
+
+        return (first, second);
+    }
+}
+
+fn fixture_rows(rows: &[(&str, &str, &str, usize)]) -> Vec<DialogueRow> {
+    rows.iter()
+        .map(|&(shift_group, shift, teacher_name, slot)| {
+            row(shift_group, shift, teacher_name, slot)
+        })
+        .collect()
+}
+
+fn check(name: &str, property: Property) {
+    for (index, (first, second)) in REGRESSIONS.iter().enumerate() {
+        if let Err(message) = property(&fixture_rows(first), &fixture_rows(second), index as u64) {
+            panic!("{} failed on regression {}: {}", name, index, message);
+        }
+    }
+
+    for seed in 0..CASES {
+        let (first, second) = snapshots(&mut Rng::new(seed));
+
+        if property(&first, &second, seed).is_err() {
+            let (first, second) = shrink(property, first, second, seed);
+            let message = property(&first, &second, seed).unwrap_err();
+
+            panic!(
+                "{} failed for seed {}\nfirst: {:#?}\nsecond: {:#?}\n{}",
+                name, seed, first, second, message
+            );
+        }
+    }
+}
+
+#[test]
+fn every_second_snapshot_row_appears_exactly_once() {
+    check(
+        "every_second_row_appears_once",
+        every_second_row_appears_once,
+    );
+}
+
+#[test]
+fn every_dropped_row_comes_from_the_first_snapshot() {
+    check(
+        "dropped_rows_come_from_the_first_snapshot",
+        dropped_rows_come_from_the_first_snapshot,
+    );
+}
+
+#[test]
+fn a_snapshot_compared_with_itself_is_only_unchanged_rows() {
+    check(
+        "a_snapshot_compared_with_itself_is_unchanged",
+        a_snapshot_compared_with_itself_is_unchanged,
+    );
+}
+
+#[test]
+fn permuting_row_order_does_not_change_classifications() {
+    check(
+        "row_order_does_not_change_classifications",
+        row_order_does_not_change_classifications,
+    );
+}
@@ -0,0 +1,277 @@
+use std::io::{self, Read};
+
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// How many leading bytes are inspected when guessing a BOM-less UTF-16 file.
+const UTF16_SAMPLE_BYTES: usize = 4096;
+
+const CHUNK_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectedEncoding {
+    pub encoding: &'static Encoding,
+    pub bom_length: usize,
+    /// True when the bytes were not valid UTF-8 and are read as Windows-1252 instead.
+    pub fallback: bool,
+}
+
+impl DetectedEncoding {
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+}
+
+/// Works out how an uploaded text file is encoded without holding it in memory. BOMs win;
+/// otherwise BOM-less UTF-16 is recognised by the NUL bytes ASCII text leaves in every other
+/// position, then the whole stream is checked for valid UTF-8, and anything else is read as
+/// Windows-1252 (a superset of Latin-1 for printable characters).
+pub fn detect_encoding<R: Read>(mut reader: R) -> io::Result<DetectedEncoding> {
+    let mut sample = Vec::with_capacity(UTF16_SAMPLE_BYTES);
+    (&mut reader)
+        .take(UTF16_SAMPLE_BYTES as u64)
+        .read_to_end(&mut sample)?;
+
+    if let Some((encoding, bom_length)) = Encoding::for_bom(&sample) {
+        return Ok(DetectedEncoding {
+            encoding,
+            bom_length,
+            fallback: false,
+        });
+    }
+
+    if let Some(encoding) = detect_bomless_utf16(&sample) {
+        return Ok(DetectedEncoding {
+            encoding,
+            bom_length: 0,
+            fallback: false,
+        });
+    }
+
+    let valid_utf8 = is_valid_utf8(io::Cursor::new(sample).chain(reader))?;
+
+    Ok(DetectedEncoding {
+        encoding: if valid_utf8 { UTF_8 } else { WINDOWS_1252 },
+        bom_length: 0,
+        fallback: !valid_utf8,
+    })
+}
+
+fn is_valid_utf8<R: Read>(mut reader: R) -> io::Result<bool> {
+    let mut chunk = vec![0u8; CHUNK_BYTES];
+    let mut pending = Vec::with_capacity(CHUNK_BYTES + 4);
+
+    loop {
+        let read = reader.read(&mut chunk)?;
+
+        if read == 0 {
+            return Ok(pending.is_empty());
+        }
+
+        pending.extend_from_slice(&chunk[..read]);
+
+        match std::str::from_utf8(&pending) {
+            Ok(_) => pending.clear(),
+            // A multi-byte sequence split across chunks; keep its start for the next read.
+            Err(error) if error.error_len().is_none() => {
+                pending.drain(..error.valid_up_to());
+            }
+            Err(_) => return Ok(false),
+        }
+    }
+}
+
+fn detect_bomless_utf16(sample: &[u8]) -> Option<&'static Encoding> {
+    let pairs = sample.len() / 2;
+
+    if pairs < 2 {
+        return None;
+    }
+
+    let even_nuls = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+    let odd_nuls = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|byte| **byte == 0)
+        .count();
+
+    // Latin text in UTF-16 has a NUL high byte on nearly every code unit; a handful of stray
+    // NULs in an 8-bit file should not trip this.
+    let mostly = |count: usize| count * 10 >= pairs * 6;
+    let rarely = |count: usize| count * 10 <= pairs;
+
+    if mostly(odd_nuls) && rarely(even_nuls) {
+        Some(UTF_16LE)
+    } else if mostly(even_nuls) && rarely(odd_nuls) {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Streams bytes in a [DetectedEncoding] out as UTF-8. Undecodable sequences become U+FFFD so
+/// the rows containing them can be reported.
+pub struct DecodingReader<R> {
+    inner: R,
+    decoder: Decoder,
+    bom_remaining: usize,
+    input: Vec<u8>,
+    input_start: usize,
+    input_end: usize,
+    output: Vec<u8>,
+    output_start: usize,
+    output_end: usize,
+    eof: bool,
+    finished: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub fn new(inner: R, detected: DetectedEncoding) -> DecodingReader<R> {
+        DecodingReader {
+            inner,
+            decoder: detected.encoding.new_decoder_without_bom_handling(),
+            bom_remaining: detected.bom_length,
+            input: vec![0u8; CHUNK_BYTES],
+            input_start: 0,
+            input_end: 0,
+            output: vec![0u8; CHUNK_BYTES],
+            output_start: 0,
+            output_end: 0,
+            eof: false,
+            finished: false,
+        }
+    }
+
+    fn fill_output(&mut self) -> io::Result<()> {
+        if self.input_start == self.input_end && !self.eof {
+            self.input_end = self.inner.read(&mut self.input)?;
+            self.input_start = 0;
+            self.eof = self.input_end == 0;
+
+            let bom = self.bom_remaining.min(self.input_end);
+            self.input_start += bom;
+            self.bom_remaining -= bom;
+        }
+
+        let (result, read, written, _) = self.decoder.decode_to_utf8(
+            &self.input[self.input_start..self.input_end],
+            &mut self.output,
+            self.eof,
+        );
+
+        self.input_start += read;
+        self.output_start = 0;
+        self.output_end = written;
+        self.finished = self.eof && result == CoderResult::InputEmpty;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_start == self.output_end {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.fill_output()?;
+        }
+
+        let count = buf.len().min(self.output_end - self.output_start);
+        buf[..count].copy_from_slice(&self.output[self.output_start..self.output_start + count]);
+        self.output_start += count;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::{detect_encoding, DecodingReader};
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| {
+                if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+
+    fn decode(bytes: &[u8]) -> (&'static str, bool, String) {
+        let detected = detect_encoding(bytes).unwrap();
+        let mut text = String::new();
+        DecodingReader::new(bytes, detected)
+            .read_to_string(&mut text)
+            .unwrap();
+
+        (detected.name(), detected.fallback, text)
+    }
+
+    #[test]
+    fn detects_bomless_utf16_in_both_byte_orders() {
+        let text = "Teacher_Name\tShift\nJosé Müller\tT-1\n";
+
+        assert_eq!(
+            decode(&utf16(text, true)),
+            ("UTF-16LE", false, text.to_string())
+        );
+        assert_eq!(
+            decode(&utf16(text, false)),
+            ("UTF-16BE", false, text.to_string())
+        );
+    }
+
+    #[test]
+    fn honours_boms() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16("Start,Finish", true));
+        assert_eq!(decode(&bytes).2, "Start,Finish");
+
+        assert_eq!(
+            decode(b"\xEF\xBB\xBFStart,Finish"),
+            ("UTF-8", false, "Start,Finish".to_string())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_windows_1252_for_accented_names() {
+        assert_eq!(
+            decode(b"Resource Name\nJos\xE9 M\xFCller \x96 Lead\n"),
+            (
+                "windows-1252",
+                true,
+                "Resource Name\nJosé Müller – Lead\n".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn replaces_undecodable_utf16() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16("Start\n", true));
+        // An unpaired high surrogate.
+        bytes.extend([0x00, 0xD8]);
+        bytes.extend(utf16("x\n", true));
+
+        assert_eq!(decode(&bytes).2, "Start\n\u{FFFD}x\n");
+    }
+
+    #[test]
+    fn keeps_valid_utf8_split_across_chunks() {
+        // Push a multi-byte character across the 16 KiB chunk boundary.
+        let text = format!("{}Zoë Ndlovu\n", "a".repeat(16 * 1024 - 3));
+
+        let (encoding, fallback, decoded) = decode(text.as_bytes());
+
+        assert_eq!(encoding, "UTF-8");
+        assert!(!fallback);
+        assert_eq!(decoded, text);
+    }
+}
@@ -0,0 +1,1026 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    ops::ControlFlow,
+};
+
+use anyhow::Error;
+use calamine::{open_workbook, Data, DataType, Reader, Xlsx};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use csv::{ReaderBuilder, StringRecord};
+use serde::Serialize;
+
+use crate::{
+    column_mappings::ColumnMappings,
+    datetime_profiles::{DateTimeProfile, DateTimeProfileSelection, ProfileDetector},
+    dialogue::{collect_profile_dialogue_datetimes, format_dialogue_datetime},
+    encoding::{detect_encoding, DecodingReader, DetectedEncoding},
+    invoicing::parse_invoicing_datetime,
+    timezones::{resolve_local_datetime, DstPolicy},
+};
+
+/// Decoded bytes read from the top of a CSV to detect its delimiter and row quoting.
+pub(crate) const CSV_SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Timezones, DST policies and datetime profiles shared by every file in a run.
+#[derive(Debug, Clone)]
+pub struct ConsolidationSettings {
+    /// Timezone schedules are filtered and stored in.
+    pub app_timezone: Tz,
+    /// Timezone naive Dialogue export timestamps are written in.
+    pub dialogue_timezone: Tz,
+    /// Timezone naive invoicing report timestamps are written in.
+    pub invoicing_timezone: Tz,
+    pub dialogue_dst_policy: DstPolicy,
+    pub invoicing_dst_policy: DstPolicy,
+    pub datetime_profiles: Vec<DateTimeProfile>,
+}
+
+/// How the timestamps of one uploaded file are parsed and which timezones they move between.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub(crate) profile_selection: DateTimeProfileSelection,
+    pub(crate) profiles: Vec<DateTimeProfile>,
+    pub(crate) source_timezone: Tz,
+    pub(crate) app_timezone: Tz,
+    pub(crate) dst_policy: DstPolicy,
+    pub(crate) column_mappings: ColumnMappings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestionIssue {
+    pub file: String,
+    pub row: usize,
+    pub kind: String,
+    pub message: String,
+}
+
+/// Issues of one kind kept as examples. The rest are only counted.
+pub(crate) const MAX_ISSUE_SAMPLES: usize = 10;
+
+/// Rows that were loaded with a caveat or skipped for a reason worth surfacing to operators.
+/// A bad file can flag every row, so only counts and the first few rows of each kind are kept.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestionDiagnostics {
+    /// Every flagged row, by kind.
+    pub counts: BTreeMap<String, usize>,
+    /// The first [MAX_ISSUE_SAMPLES] rows of each kind.
+    pub issues: Vec<IngestionIssue>,
+}
+
+impl IngestionDiagnostics {
+    pub(crate) fn flag(&mut self, file: &str, row: usize, kind: &str, message: String) {
+        self.record(IngestionIssue {
+            file: file.to_string(),
+            row,
+            kind: kind.to_string(),
+            message,
+        });
+    }
+
+    fn record(&mut self, issue: IngestionIssue) {
+        let count = self.counts.entry(issue.kind.clone()).or_insert(0);
+        *count += 1;
+
+        if *count <= MAX_ISSUE_SAMPLES {
+            self.issues.push(issue);
+        }
+    }
+
+    /// Adds the issues `other` flagged, keeping the samples within the limit.
+    pub(crate) fn extend(&mut self, other: IngestionDiagnostics) {
+        for issue in other.issues {
+            let samples = self
+                .issues
+                .iter()
+                .filter(|sample| sample.kind == issue.kind)
+                .count();
+
+            if samples < MAX_ISSUE_SAMPLES {
+                self.issues.push(issue);
+            }
+        }
+
+        for (kind, count) in other.counts {
+            *self.counts.entry(kind).or_insert(0) += count;
+        }
+    }
+
+    pub fn count(&self, kind: &str) -> usize {
+        self.counts.get(kind).copied().unwrap_or(0)
+    }
+
+    /// Flagged rows of every kind, including those past the samples.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+impl ImportOptions {
+    pub fn dialogue(
+        settings: &ConsolidationSettings,
+        profile_selection: DateTimeProfileSelection,
+        column_mappings: ColumnMappings,
+    ) -> ImportOptions {
+        ImportOptions {
+            profile_selection,
+            profiles: settings.datetime_profiles.clone(),
+            source_timezone: settings.dialogue_timezone,
+            app_timezone: settings.app_timezone,
+            dst_policy: settings.dialogue_dst_policy,
+            column_mappings,
+        }
+    }
+
+    pub fn invoicing(
+        settings: &ConsolidationSettings,
+        profile_selection: DateTimeProfileSelection,
+        column_mappings: ColumnMappings,
+    ) -> ImportOptions {
+        ImportOptions {
+            profile_selection,
+            profiles: settings.datetime_profiles.clone(),
+            source_timezone: settings.invoicing_timezone,
+            app_timezone: settings.app_timezone,
+            dst_policy: settings.invoicing_dst_policy,
+            column_mappings,
+        }
+    }
+}
+
+/// Lenient placement used while choosing between candidate parses. The values that end up in a
+/// row go through [resolve_source_datetime] so the source's [DstPolicy] applies.
+pub(crate) fn zoned_from_source(naive: NaiveDateTime, source: Tz) -> DateTime<Tz> {
+    resolve_local_datetime(naive, source, DstPolicy::Earliest, None)
+        .map(|resolved| resolved.datetime)
+        .unwrap_or_else(|_| source.from_utc_datetime(&naive))
+}
+
+/// Places a source timestamp using the file's [DstPolicy], flagging DST-affected rows. Returns
+/// `None` when the policy rejects the row.
+pub(crate) fn resolve_source_datetime(
+    naive: NaiveDateTime,
+    options: &ImportOptions,
+    neighbour: Option<DateTime<Utc>>,
+    location: (&str, usize),
+    diagnostics: &mut IngestionDiagnostics,
+) -> Option<DateTime<Utc>> {
+    let (file, row) = location;
+
+    match resolve_local_datetime(
+        naive,
+        options.source_timezone,
+        options.dst_policy,
+        neighbour,
+    ) {
+        Ok(resolved) => {
+            let datetime = resolved.datetime.with_timezone(&Utc);
+
+            if let Some(issue) = resolved.issue {
+                diagnostics.flag(
+                    file,
+                    row,
+                    &format!("dst_{}", issue),
+                    format!(
+                        "{} is {} in {}; resolved to {} using the {} policy",
+                        naive, issue, options.source_timezone, datetime, options.dst_policy
+                    ),
+                );
+            }
+
+            Some(datetime)
+        }
+        Err(issue) => {
+            diagnostics.flag(
+                file,
+                row,
+                &format!("dst_{}", issue),
+                format!(
+                    "{} is {} in {}; row rejected by the {} policy",
+                    naive, issue, options.source_timezone, options.dst_policy
+                ),
+            );
+
+            None
+        }
+    }
+}
+
+/// Whether `naive` names exactly one instant in the source timezone. Only such rows may become
+/// the neighbour that [DstPolicy::Infer] resolves later rows against; a guessed instant would
+/// carry its guess forward.
+pub(crate) fn is_unambiguous_in_source(naive: NaiveDateTime, options: &ImportOptions) -> bool {
+    options
+        .source_timezone
+        .from_local_datetime(&naive)
+        .single()
+        .is_some()
+}
+
+/// Export timestamps are read in the file's source timezone (`DIALOGUE_SOURCE_TIMEZONE`, default
+/// UTC, or `INVOICING_SOURCE_TIMEZONE`) and compared against the process date in `APP_TIMEZONE`
+/// (default Africa/Johannesburg).
+pub(crate) fn convert_source_to_app_timezone(
+    naive: NaiveDateTime,
+    options: &ImportOptions,
+) -> NaiveDateTime {
+    zoned_from_source(naive, options.source_timezone)
+        .with_timezone(&options.app_timezone)
+        .naive_local()
+}
+
+/// Consolidated rows carry app-local timestamps; the database stores the UTC instant.
+pub(crate) fn app_local_to_utc(naive: NaiveDateTime, app_timezone: Tz) -> DateTime<Utc> {
+    zoned_from_source(naive, app_timezone).with_timezone(&Utc)
+}
+
+pub fn parse_process_calendar_date(process_date: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(process_date, "%Y-%m-%d").map_err(Error::from)
+}
+
+pub(crate) fn detect_csv_delimiter(contents: &str) -> u8 {
+    let sample_line = contents
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+
+    let candidates = [b',', b';', b'\t', b'|'];
+
+    candidates
+        .into_iter()
+        .max_by_key(|candidate| sample_line.matches(*candidate as char).count())
+        .unwrap_or(b',')
+}
+
+pub(crate) fn normalize_csv_header(header: &str) -> String {
+    header
+        .trim()
+        .trim_start_matches('\u{feff}')
+        .to_ascii_lowercase()
+}
+
+/// Excel dialogue exports wrap the entire row in quotes and escape inner quotes as doubled
+/// quotes (e.g. "Start,""Finish"",""Shift...""), sometimes without the closing quote. Each such
+/// line is unwrapped by one level, which keeps field-level quoting such as "Magongo, Babalwa"
+/// intact. Lines that are not wrapped are passed through unchanged, so this is safe to run on
+/// any file.
+pub(crate) fn unwrap_excel_quoted_rows(contents: &str, delimiter: u8) -> String {
+    contents
+        .lines()
+        .map(|line| unwrap_excel_quoted_row(line, delimiter).unwrap_or_else(|| line.to_string()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn unwrap_excel_quoted_row(line: &str, delimiter: u8) -> Option<String> {
+    let wrapped = line.strip_prefix('"')?;
+
+    // A well-formed CSV line always has an even number of quotes, so an odd count means the
+    // wrapper's closing quote was dropped.
+    if line.matches('"').count() % 2 == 1 {
+        return Some(wrapped.replace("\"\"", "\""));
+    }
+
+    // Otherwise the line is wrapped only when it is one quoted field, i.e. every inner quote is
+    // doubled, and that field holds a delimiter. This runs on every line of large files, so it
+    // scans bytes rather than building a CSV reader per line.
+    let inner = wrapped.strip_suffix('"')?;
+    let mut bytes = inner.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'"' && bytes.next() != Some(b'"') {
+            return None;
+        }
+    }
+
+    if inner.as_bytes().contains(&delimiter) {
+        Some(inner.replace("\"\"", "\""))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn find_header_index(headers: &StringRecord, aliases: &[&str]) -> Option<usize> {
+    headers.iter().position(|header| {
+        let normalized_header = normalize_csv_header(header);
+
+        aliases
+            .iter()
+            .any(|alias| normalized_header == normalize_csv_header(alias))
+    })
+}
+
+pub(crate) fn find_mapped_header_index(
+    headers: &StringRecord,
+    mappings: &ColumnMappings,
+    file_type: &str,
+    field: &str,
+) -> Option<usize> {
+    find_header_index(headers, &mappings.aliases(file_type, field))
+}
+
+/// A cell read from an import file. CSV fields are always text; XLSX cells may be real dates.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ImportCell {
+    Text(String),
+    DateTime(NaiveDateTime),
+}
+
+impl ImportCell {
+    pub(crate) fn from_xlsx(cell: &Data) -> ImportCell {
+        match cell {
+            Data::DateTime(_) | Data::DateTimeIso(_) => cell
+                .as_datetime()
+                .map(ImportCell::DateTime)
+                .unwrap_or_else(|| ImportCell::Text(cell.to_string().trim().to_string())),
+            _ => ImportCell::Text(cell.to_string().trim().to_string()),
+        }
+    }
+
+    pub(crate) fn text(&self) -> Option<&str> {
+        match self {
+            ImportCell::Text(value) => Some(value),
+            ImportCell::DateTime(_) => None,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.text().map(str::is_empty).unwrap_or(false)
+    }
+
+    pub(crate) fn dialogue_candidates(
+        &self,
+        profile: Option<&DateTimeProfile>,
+    ) -> Vec<NaiveDateTime> {
+        match self {
+            ImportCell::DateTime(value) => vec![*value],
+            ImportCell::Text(value) => collect_profile_dialogue_datetimes(value, profile),
+        }
+    }
+
+    pub(crate) fn invoicing_datetime(
+        &self,
+        profile: Option<&DateTimeProfile>,
+    ) -> Result<NaiveDateTime, Error> {
+        match self {
+            ImportCell::DateTime(value) => Ok(*value),
+            ImportCell::Text(value) => parse_invoicing_datetime(value, profile),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportCell::Text(value) => write!(f, "{}", value),
+            ImportCell::DateTime(value) => write!(f, "{}", format_dialogue_datetime(*value)),
+        }
+    }
+}
+
+/// One data row of an import file, with its 1-based row number in the file for diagnostics.
+#[derive(Debug, Clone)]
+pub(crate) struct ImportRecord {
+    pub(crate) row: usize,
+    pub(crate) cells: Vec<ImportCell>,
+}
+
+impl ImportRecord {
+    pub(crate) fn cell(&self, column: usize) -> Option<&ImportCell> {
+        self.cells.get(column).filter(|cell| !cell.is_empty())
+    }
+
+    pub(crate) fn text(&self, column: usize) -> String {
+        self.cell(column)
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn first_non_empty_text(&self, columns: &[usize]) -> String {
+        columns
+            .iter()
+            .filter_map(|column| self.cell(*column))
+            .map(ToString::to_string)
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Text values of the given columns, used to auto-detect a datetime profile. Real date
+    /// cells need no profile and are left out.
+    pub(crate) fn datetime_texts<'a>(
+        &'a self,
+        columns: &'a [usize],
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        columns
+            .iter()
+            .filter_map(|column| self.cell(*column))
+            .filter_map(ImportCell::text)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ImportFormat {
+    Csv {
+        encoding: DetectedEncoding,
+        delimiter: u8,
+        unwrap_rows: bool,
+    },
+    Xlsx {
+        /// 0-based sheet row holding the header.
+        header_row: u32,
+    },
+}
+
+/// A CSV or XLSX import opened for streaming. Both file types go through this so the row
+/// mapping code is shared and produces identical rows for either. Only the header is held;
+/// every pass over the records reads them from disk again.
+#[derive(Debug, Clone)]
+pub struct ImportSource {
+    pub(crate) file_path: String,
+    pub(crate) format: ImportFormat,
+    pub(crate) headers: StringRecord,
+}
+
+impl ImportSource {
+    /// Detects the encoding and delimiter, then asks `needs_unwrap` whether the rows carry
+    /// Excel whole-row quoting, given a decoded sample from the top of the file.
+    pub(crate) fn open_csv<F>(
+        file_path: &str,
+        diagnostics: &mut IngestionDiagnostics,
+        needs_unwrap: F,
+    ) -> Result<ImportSource, Error>
+    where
+        F: FnOnce(&str, u8) -> bool,
+    {
+        let encoding = detect_encoding(BufReader::new(File::open(file_path)?))?;
+
+        if encoding.name() != "UTF-8" {
+            tracing::info!("📄 Decoding {} as {}", file_path, encoding.name());
+        }
+
+        if encoding.fallback {
+            diagnostics.flag(
+                file_path,
+                0,
+                "encoding_fallback",
+                format!(
+                    "File is not valid UTF-8 and was read as {}",
+                    encoding.name()
+                ),
+            );
+        }
+
+        let sample = read_csv_sample(file_path, encoding)?;
+        let delimiter = detect_csv_delimiter(&sample);
+        let unwrap_rows = needs_unwrap(&sample, delimiter);
+
+        tracing::info!(
+            "📄 Parsing CSV {} using delimiter {:?}{}",
+            file_path,
+            delimiter as char,
+            if unwrap_rows {
+                " (unwrapping Excel row quoting)"
+            } else {
+                ""
+            }
+        );
+
+        let mut source = ImportSource {
+            file_path: file_path.to_string(),
+            format: ImportFormat::Csv {
+                encoding,
+                delimiter,
+                unwrap_rows,
+            },
+            headers: StringRecord::new(),
+        };
+        source.headers = source
+            .csv_reader(encoding, delimiter, unwrap_rows)?
+            .headers()?
+            .clone();
+
+        Ok(source)
+    }
+
+    /// Reads the first sheet, using the first row `is_header` accepts as the header.
+    pub(crate) fn open_xlsx<F>(file_path: &str, is_header: F) -> Result<ImportSource, Error>
+    where
+        F: Fn(&StringRecord) -> bool,
+    {
+        let mut header = None;
+
+        for_each_xlsx_row(file_path, |row, cells| {
+            let headers = cells
+                .iter()
+                .map(ToString::to_string)
+                .collect::<StringRecord>();
+
+            if is_header(&headers) {
+                header = Some((row, headers));
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })?;
+
+        let (header_row, headers) = header
+            .ok_or_else(|| anyhow::anyhow!("{} has no recognisable header row", file_path))?;
+
+        tracing::info!(
+            "📄 XLSX {} header found on row {}",
+            file_path,
+            header_row + 1
+        );
+
+        Ok(ImportSource {
+            file_path: file_path.to_string(),
+            format: ImportFormat::Xlsx { header_row },
+            headers,
+        })
+    }
+
+    pub(crate) fn is_xlsx(&self) -> bool {
+        matches!(self.format, ImportFormat::Xlsx { .. })
+    }
+
+    pub(crate) fn csv_reader(
+        &self,
+        encoding: DetectedEncoding,
+        delimiter: u8,
+        unwrap_rows: bool,
+    ) -> Result<csv::Reader<Box<dyn Read>>, Error> {
+        let decoded = BufReader::new(DecodingReader::new(
+            BufReader::new(File::open(&self.file_path)?),
+            encoding,
+        ));
+
+        let input: Box<dyn Read> = if unwrap_rows {
+            Box::new(UnwrapRowsReader::new(decoded, delimiter))
+        } else {
+            Box::new(decoded)
+        };
+
+        Ok(ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(input))
+    }
+
+    /// Streams the data rows to `f` until it breaks. XLSX blank rows are dropped; malformed CSV
+    /// rows are logged and skipped. Summary rows are left to [Self::skip_summary_row], which
+    /// needs the caller's datetime columns.
+    pub(crate) fn for_each_record<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(ImportRecord) -> ControlFlow<()>,
+    {
+        match self.format {
+            ImportFormat::Csv {
+                encoding,
+                delimiter,
+                unwrap_rows,
+            } => {
+                let mut reader = self.csv_reader(encoding, delimiter, unwrap_rows)?;
+
+                for (index, record) in reader.records().enumerate() {
+                    let record = match record {
+                        Ok(record) => record,
+                        Err(error) if matches!(error.kind(), csv::ErrorKind::Io(_)) => {
+                            return Err(error.into())
+                        }
+                        Err(error) => {
+                            tracing::warn!(
+                                "Skipping malformed CSV row {} in {}: {:?}",
+                                index + 2,
+                                self.file_path,
+                                error
+                            );
+                            continue;
+                        }
+                    };
+
+                    let record = ImportRecord {
+                        row: index + 2,
+                        cells: record
+                            .iter()
+                            .map(|value| ImportCell::Text(value.to_string()))
+                            .collect(),
+                    };
+
+                    if f(record).is_break() {
+                        break;
+                    }
+                }
+            }
+            ImportFormat::Xlsx { header_row } => {
+                for_each_xlsx_row(&self.file_path, |row, cells| {
+                    if row <= header_row || cells.iter().all(ImportCell::is_empty) {
+                        return ControlFlow::Continue(());
+                    }
+
+                    f(ImportRecord {
+                        row: row as usize + 1,
+                        cells,
+                    })
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// True for an XLSX summary or footer row (see [is_xlsx_summary_row]), which is flagged so
+    /// every row that is not read shows up in the diagnostics.
+    pub(crate) fn skip_summary_row(
+        &self,
+        record: &ImportRecord,
+        datetime_columns: &[usize],
+        diagnostics: &mut IngestionDiagnostics,
+    ) -> bool {
+        if !self.is_xlsx() || !is_xlsx_summary_row(record, datetime_columns) {
+            return false;
+        }
+
+        diagnostics.flag(
+            &self.file_path,
+            record.row,
+            "summary_row",
+            format!(
+                "Skipped summary row {:?}",
+                summary_label(record).unwrap_or_default()
+            ),
+        );
+
+        true
+    }
+
+    /// Flags CSV rows where the detected encoding replaced undecodable bytes with U+FFFD, so a
+    /// mangled teacher name is visible instead of silently becoming a new teacher.
+    pub(crate) fn flag_undecodable(
+        &self,
+        record: &ImportRecord,
+        diagnostics: &mut IngestionDiagnostics,
+    ) {
+        let ImportFormat::Csv { encoding, .. } = self.format else {
+            return;
+        };
+
+        let undecodable = record
+            .cells
+            .iter()
+            .filter_map(ImportCell::text)
+            .any(|value| value.contains(char::REPLACEMENT_CHARACTER));
+
+        if undecodable {
+            diagnostics.flag(
+                &self.file_path,
+                record.row,
+                "encoding_error",
+                format!(
+                    "Row contains bytes that are not valid {} and were replaced",
+                    encoding.name()
+                ),
+            );
+        }
+    }
+}
+
+/// Decoded text from the top of a CSV, cut back to the last complete line.
+pub(crate) fn read_csv_sample(
+    file_path: &str,
+    encoding: DetectedEncoding,
+) -> Result<String, Error> {
+    let mut sample = Vec::new();
+    DecodingReader::new(BufReader::new(File::open(file_path)?), encoding)
+        .take(CSV_SAMPLE_BYTES)
+        .read_to_end(&mut sample)?;
+
+    if sample.len() as u64 == CSV_SAMPLE_BYTES {
+        if let Some(end) = sample.iter().rposition(|byte| *byte == b'\n') {
+            sample.truncate(end + 1);
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&sample).into_owned())
+}
+
+/// Applies [unwrap_excel_quoted_row] to each line as it is read.
+pub(crate) struct UnwrapRowsReader<R> {
+    inner: R,
+    delimiter: u8,
+    line: String,
+    position: usize,
+}
+
+impl<R: BufRead> UnwrapRowsReader<R> {
+    pub(crate) fn new(inner: R, delimiter: u8) -> UnwrapRowsReader<R> {
+        UnwrapRowsReader {
+            inner,
+            delimiter,
+            line: String::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for UnwrapRowsReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.line.len() {
+            self.line.clear();
+            self.position = 0;
+
+            if self.inner.read_line(&mut self.line)? == 0 {
+                return Ok(0);
+            }
+
+            let content = self.line.trim_end_matches(['\r', '\n']);
+
+            if let Some(unwrapped) = unwrap_excel_quoted_row(content, self.delimiter) {
+                self.line = unwrapped + "\n";
+            }
+        }
+
+        let remaining = &self.line.as_bytes()[self.position..];
+        let count = buf.len().min(remaining.len());
+        buf[..count].copy_from_slice(&remaining[..count]);
+        self.position += count;
+
+        Ok(count)
+    }
+}
+
+/// Streams the first sheet row by row without loading the worksheet into memory. Cells missing
+/// from the XML are filled with empty text so column indexes line up with the header.
+pub(crate) fn for_each_xlsx_row<F>(file_path: &str, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(u32, Vec<ImportCell>) -> ControlFlow<()>,
+{
+    let mut workbook: Xlsx<_> =
+        open_workbook(file_path).map_err(|e| anyhow::anyhow!("Cannot open xlsx file: {}", e))?;
+
+    let sheet_name = workbook
+        .sheet_names()
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("{} has no worksheets", file_path))?;
+
+    let mut cells_reader = workbook
+        .worksheet_cells_reader(&sheet_name)
+        .map_err(|e| anyhow::anyhow!("Cannot open xlsx sheet: {}", e))?;
+
+    let mut current_row = None;
+    let mut cells = Vec::new();
+
+    while let Some(cell) = cells_reader
+        .next_cell()
+        .map_err(|e| anyhow::anyhow!("Cannot read xlsx sheet: {}", e))?
+    {
+        let (row, column) = cell.get_position();
+
+        if current_row != Some(row) {
+            if let Some(previous_row) = current_row.replace(row) {
+                if f(previous_row, std::mem::take(&mut cells)).is_break() {
+                    return Ok(());
+                }
+            }
+        }
+
+        if cells.len() < column as usize {
+            cells.resize(column as usize, ImportCell::Text(String::new()));
+        }
+
+        cells.push(ImportCell::from_xlsx(&Data::from(cell.get_value().clone())));
+    }
+
+    if let Some(row) = current_row {
+        let _ = f(row, cells);
+    }
+
+    Ok(())
+}
+
+pub(crate) const XLSX_SUMMARY_MARKERS: &[&str] = &[
+    "grand total",
+    "subtotal",
+    "total",
+    "record count",
+    "count",
+    "sum",
+    "average",
+    "confidential information",
+    "copyright",
+    "generated by",
+    "filtered by",
+];
+
+/// Summary and footer rows (group totals, record counts, report footers) start with one of
+/// [XLSX_SUMMARY_MARKERS] as a whole word and have no date in any of `datetime_columns`. A real
+/// row that happens to start with such a word, like a teacher called "Sum Yee Lin", keeps its
+/// dates and is read as usual.
+pub(crate) fn is_xlsx_summary_row(record: &ImportRecord, datetime_columns: &[usize]) -> bool {
+    let Some(first) = summary_label(record) else {
+        return false;
+    };
+    let first = first.to_ascii_lowercase();
+
+    let starts_with_marker = XLSX_SUMMARY_MARKERS.iter().any(|marker| {
+        first
+            .strip_prefix(marker)
+            .map(|rest| !rest.starts_with(|c: char| c.is_alphanumeric()))
+            .unwrap_or(false)
+    });
+
+    starts_with_marker
+        && datetime_columns
+            .iter()
+            .all(|column| match record.cell(*column) {
+                None => true,
+                Some(cell) => {
+                    cell.dialogue_candidates(None).is_empty()
+                        && cell.invoicing_datetime(None).is_err()
+                }
+            })
+}
+
+fn summary_label(record: &ImportRecord) -> Option<&str> {
+    record
+        .cells
+        .iter()
+        .filter_map(ImportCell::text)
+        .find(|cell| !cell.is_empty())
+}
+
+/// Resolves the datetime profile for the given columns. Auto detection takes one pass over the
+/// file and stops as soon as no profile is left.
+pub(crate) fn resolve_source_profile(
+    source: &ImportSource,
+    datetime_columns: &[usize],
+    options: &ImportOptions,
+) -> Result<Option<DateTimeProfile>, Error> {
+    if !matches!(options.profile_selection, DateTimeProfileSelection::Auto) {
+        return options
+            .profile_selection
+            .resolve_for_values(std::iter::empty(), &options.profiles);
+    }
+
+    let mut detector = ProfileDetector::new(&options.profiles);
+
+    source.for_each_record(|record| {
+        if source.is_xlsx() && is_xlsx_summary_row(&record, datetime_columns) {
+            return ControlFlow::Continue(());
+        }
+
+        for value in record.datetime_texts(datetime_columns) {
+            detector.observe(value);
+        }
+
+        if detector.exhausted() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+
+    // Real Excel date cells need no profile; only text dates are checked for consistency.
+    if source.is_xlsx() && detector.scanned_values() == 0 {
+        return Ok(None);
+    }
+
+    detector
+        .finish()
+        .map(|profile| Some(profile.clone()))
+        .map_err(|error| anyhow::anyhow!("{}: {}", source.file_path, error))
+}
+
+pub(crate) fn is_xlsx_path(file_path: &str) -> bool {
+    std::path::Path::new(file_path)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xlsx"))
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::ReaderBuilder;
+
+    use chrono::NaiveDate;
+
+    use super::{
+        is_xlsx_summary_row, unwrap_excel_quoted_rows, ImportCell, ImportRecord,
+        IngestionDiagnostics, MAX_ISSUE_SAMPLES,
+    };
+
+    #[test]
+    fn counts_every_issue_but_keeps_only_the_first_samples_of_each_kind() {
+        let mut diagnostics = IngestionDiagnostics::default();
+        let mut invoicing = IngestionDiagnostics::default();
+
+        for row in 2..2 + 3 * MAX_ISSUE_SAMPLES {
+            diagnostics.flag("dialogue-1.csv", row, "dst_ambiguous", String::new());
+            invoicing.flag("invoicing-report.csv", row, "dst_ambiguous", String::new());
+        }
+        diagnostics.flag("dialogue-1.csv", 40, "summary_row", String::new());
+        invoicing.flag("invoicing-report.csv", 41, "summary_row", String::new());
+
+        diagnostics.extend(invoicing);
+
+        assert_eq!(diagnostics.count("dst_ambiguous"), 6 * MAX_ISSUE_SAMPLES);
+        assert_eq!(diagnostics.count("summary_row"), 2);
+        assert_eq!(diagnostics.total(), 6 * MAX_ISSUE_SAMPLES + 2);
+        assert_eq!(
+            diagnostics
+                .issues
+                .iter()
+                .filter(|issue| issue.kind == "dst_ambiguous")
+                .count(),
+            MAX_ISSUE_SAMPLES
+        );
+        assert!(diagnostics
+            .issues
+            .iter()
+            .all(|issue| issue.kind != "dst_ambiguous" || issue.file == "dialogue-1.csv"));
+        assert_eq!(
+            diagnostics
+                .issues
+                .iter()
+                .filter(|issue| issue.kind == "summary_row")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    pub(crate) fn unwraps_excel_quoted_rows_without_losing_field_quoting() {
+        let contents = [
+            r#""Start,""Finish"",""Resource: Resource Name"""#,
+            r#""5/2/2026 9:00 AM,""5/2/2026 11:00 AM"",""Magongo, Babalwa"""#,
+            r#""5/2/2026 1:00 PM,""5/2/2026 2:00 PM"",""Nomsa """"Noms"""" Dube""""#,
+            r#"5/2/2026 3:00 PM,5/2/2026 4:00 PM,"Plain, Row""#,
+        ]
+        .join("\n");
+
+        let unwrapped = unwrap_excel_quoted_rows(&contents, b',');
+        let mut reader = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(unwrapped.as_bytes());
+
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["Start", "Finish", "Resource: Resource Name"]
+        );
+
+        let names = reader
+            .records()
+            .map(|record| record.unwrap()[2].to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec!["Magongo, Babalwa", "Nomsa \"Noms\" Dube", "Plain, Row"]
+        );
+    }
+
+    #[test]
+    pub(crate) fn detects_xlsx_summary_rows_by_leading_word_and_missing_dates() {
+        let row = |cells: &[&str]| ImportRecord {
+            row: 2,
+            cells: cells
+                .iter()
+                .map(|cell| ImportCell::Text(cell.to_string()))
+                .collect(),
+        };
+        // Start and finish are the last two columns.
+        let dates = [2, 3];
+
+        assert!(is_xlsx_summary_row(&row(&["", "Total", "12"]), &dates));
+        assert!(is_xlsx_summary_row(&row(&["Record Count: 40"]), &dates));
+        assert!(is_xlsx_summary_row(&row(&["Generated By Admin"]), &dates));
+        assert!(!is_xlsx_summary_row(
+            &row(&["Totale Group", "Teacher"]),
+            &dates
+        ));
+        assert!(!is_xlsx_summary_row(&row(&["Sumaya Khan"]), &dates));
+        assert!(!is_xlsx_summary_row(&row(&["", ""]), &dates));
+        assert!(!is_xlsx_summary_row(
+            &row(&["Sum Yee Lin", "Total Learning", "5/1/2026 9:00 AM"]),
+            &dates
+        ));
+
+        let excel_dates = ImportRecord {
+            row: 2,
+            cells: vec![
+                ImportCell::Text("Total Learning".to_string()),
+                ImportCell::Text("T-1".to_string()),
+                ImportCell::DateTime(
+                    NaiveDate::from_ymd_opt(2026, 5, 1)
+                        .unwrap()
+                        .and_hms_opt(9, 0, 0)
+                        .unwrap(),
+                ),
+            ],
+        };
+        assert!(!is_xlsx_summary_row(&excel_dates, &dates));
+    }
+}
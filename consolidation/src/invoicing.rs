@@ -0,0 +1,592 @@
+use std::ops::ControlFlow;
+
+use anyhow::Error;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    column_mappings::{ColumnMappings, INVOICING_FILE_TYPE},
+    datetime_profiles::DateTimeProfile,
+    import::{
+        find_header_index, find_mapped_header_index, is_unambiguous_in_source, is_xlsx_path,
+        normalize_csv_header, resolve_source_datetime, resolve_source_profile, zoned_from_source,
+        ImportOptions, ImportSource, IngestionDiagnostics,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InvoicingRow {
+    pub teacher_name: String,
+    pub eligible: bool,
+    pub activity_start: DateTime<Utc>,
+    pub activity_end: DateTime<Utc>,
+    pub shift: String,
+}
+
+pub(crate) struct InvoicingCsvColumns {
+    teacher_name: usize,
+    eligible: Option<usize>,
+    activity_start: usize,
+    activity_end: usize,
+    shift: Vec<usize>,
+}
+
+pub(crate) fn parse_invoicing_datetime(
+    value: &str,
+    profile: Option<&DateTimeProfile>,
+) -> Result<NaiveDateTime, Error> {
+    let value = value.trim().trim_matches('"');
+
+    if let Some(profile) = profile {
+        return profile.parse(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invoicing datetime {:?} does not match the {} profile",
+                value,
+                profile.name
+            )
+        });
+    }
+
+    let supported_formats = [
+        "%m/%d/%Y %I:%M:%S %p",
+        "%m/%d/%Y %I:%M %p",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ];
+
+    for format in supported_formats {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(parsed);
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Unsupported invoicing datetime format: {}",
+        value
+    ))
+}
+
+pub(crate) fn parse_eligible_status(value: &str) -> bool {
+    normalize_csv_header(value) == "eligible"
+}
+
+pub(crate) fn build_invoicing_csv_columns(
+    headers: &StringRecord,
+    mappings: &ColumnMappings,
+) -> Result<InvoicingCsvColumns, Error> {
+    let find =
+        |field: &str| find_mapped_header_index(headers, mappings, INVOICING_FILE_TYPE, field);
+
+    let teacher_name = find("teacher_name").ok_or_else(|| {
+        let cols: Vec<&str> = headers.iter().collect();
+        anyhow::anyhow!(
+            "Invoicing CSV is missing a Teacher Name column. Found columns: {:?}",
+            cols
+        )
+    })?;
+    let eligible = find("eligible");
+    let activity_start = find("activity_start")
+        .ok_or_else(|| anyhow::anyhow!("Invoicing CSV is missing an Activity Start column"))?;
+    let activity_end = find("activity_end")
+        .ok_or_else(|| anyhow::anyhow!("Invoicing CSV is missing an Activity End column"))?;
+
+    // Every matching shift column is kept, in alias order, and the first non-empty one wins.
+    let shift = mappings
+        .aliases(INVOICING_FILE_TYPE, "shift")
+        .iter()
+        .filter_map(|alias| find_header_index(headers, &[*alias]))
+        .collect::<Vec<_>>();
+
+    if shift.is_empty() {
+        return Err(anyhow::anyhow!("Invoicing CSV is missing a Shift column"));
+    }
+
+    Ok(InvoicingCsvColumns {
+        teacher_name,
+        eligible,
+        activity_start,
+        activity_end,
+        shift,
+    })
+}
+
+/// Opens an invoicing report, reading it as XLSX or CSV by its extension.
+pub fn open_invoicing_source(
+    file_path: &str,
+    mappings: &ColumnMappings,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Result<ImportSource, Error> {
+    if is_xlsx_path(file_path) {
+        tracing::info!("📄 Parsing invoicing XLSX {}", file_path);
+
+        ImportSource::open_xlsx(file_path, |headers| {
+            build_invoicing_csv_columns(headers, mappings).is_ok()
+        })
+    } else {
+        // Invoicing exports can use the same whole-row quoting as Excel dialogue exports.
+        ImportSource::open_csv(file_path, diagnostics, |_, _| true)
+    }
+}
+
+/// Streams the invoicing rows on the process date to `emit`, which can stop the read early by
+/// breaking.
+pub fn stream_invoicing_rows<F>(
+    source: &ImportSource,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+    mut emit: F,
+) -> Result<(), Error>
+where
+    F: FnMut(InvoicingRow) -> ControlFlow<()>,
+{
+    let file_path = source.file_path.as_str();
+    let columns = build_invoicing_csv_columns(&source.headers, &options.column_mappings)?;
+    let profile = resolve_source_profile(
+        source,
+        &[columns.activity_start, columns.activity_end],
+        options,
+    )?;
+
+    let mut previous_invoicing_start: Option<DateTime<Utc>> = None;
+
+    source.for_each_record(|record| {
+        if source.skip_summary_row(
+            &record,
+            &[columns.activity_start, columns.activity_end],
+            diagnostics,
+        ) {
+            return ControlFlow::Continue(());
+        }
+
+        // Issues are only reported for rows on the process date; the rest are ignored anyway.
+        let mut row_diagnostics = IngestionDiagnostics::default();
+        source.flag_undecodable(&record, &mut row_diagnostics);
+
+        let teacher_name = record.text(columns.teacher_name);
+        let eligible = columns
+            .eligible
+            .map(|column| parse_eligible_status(&record.text(column)))
+            .unwrap_or(true);
+        let shift = record.first_non_empty_text(&columns.shift);
+
+        let (Some(activity_start), Some(activity_end)) = (
+            record.cell(columns.activity_start),
+            record.cell(columns.activity_end),
+        ) else {
+            tracing::warn!(
+                "Skipping invoicing row {} in {} due to missing required columns",
+                record.row,
+                file_path
+            );
+            return ControlFlow::Continue(());
+        };
+
+        if teacher_name.is_empty() {
+            tracing::warn!(
+                "Skipping invoicing row {} in {} due to missing required columns",
+                record.row,
+                file_path
+            );
+            return ControlFlow::Continue(());
+        }
+
+        let activity_start_date = match activity_start.invoicing_datetime(profile.as_ref()) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                tracing::warn!(
+                    "Skipping invoicing row {} in {} due to invalid activity start datetime: {:?}",
+                    record.row,
+                    file_path,
+                    error
+                );
+                return ControlFlow::Continue(());
+            }
+        };
+
+        let activity_end_date = match activity_end.invoicing_datetime(profile.as_ref()) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                tracing::warn!(
+                    "Skipping invoicing row {} in {} due to invalid activity end datetime: {:?}",
+                    record.row,
+                    file_path,
+                    error
+                );
+                return ControlFlow::Continue(());
+            }
+        };
+
+        let on_process_date = |datetime: DateTime<Utc>| {
+            datetime.with_timezone(&options.app_timezone).date_naive() == process_calendar
+        };
+        // A rejected time has no instant, so the lenient placement decides its date.
+        let leniently_on_process_date = on_process_date(
+            zoned_from_source(activity_start_date, options.source_timezone).with_timezone(&Utc),
+        );
+
+        let location = (file_path, record.row);
+        let Some(activity_start) = resolve_source_datetime(
+            activity_start_date,
+            options,
+            previous_invoicing_start,
+            location,
+            &mut row_diagnostics,
+        ) else {
+            if leniently_on_process_date {
+                diagnostics.extend(row_diagnostics);
+            }
+            return ControlFlow::Continue(());
+        };
+        let Some(activity_end) = resolve_source_datetime(
+            activity_end_date,
+            options,
+            Some(activity_start),
+            location,
+            &mut row_diagnostics,
+        ) else {
+            if on_process_date(activity_start) {
+                diagnostics.extend(row_diagnostics);
+            }
+            return ControlFlow::Continue(());
+        };
+
+        if is_unambiguous_in_source(activity_start_date, options) {
+            previous_invoicing_start = Some(activity_start);
+        }
+
+        let invoicing_row = InvoicingRow {
+            teacher_name,
+            eligible,
+            activity_start,
+            activity_end,
+            shift,
+        };
+
+        //  Check that the day, month and year are the same as the process date
+        if on_process_date(invoicing_row.activity_start) {
+            diagnostics.extend(row_diagnostics);
+            emit(invoicing_row)
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use chrono::NaiveDate;
+    use chrono_tz::{Africa::Johannesburg, America::New_York};
+    use xlsx_writer::{write_xlsx, XlsxCell};
+
+    use super::{open_invoicing_source, stream_invoicing_rows};
+    use crate::{
+        datetime_profiles::{DateTimeProfile, DateTimeProfileSelection},
+        import::{parse_process_calendar_date, ImportOptions, IngestionDiagnostics},
+        pipeline::INGEST_BATCH_SIZE,
+        test_support::{import_options, load_invoicing},
+        timezones::DstPolicy,
+    };
+
+    #[test]
+    fn invoicing_csv_and_xlsx_produce_identical_rows() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-invoicing-xlsx-test-{}",
+            std::process::id()
+        ));
+        let csv_dir = dir.join("csv");
+        let xlsx_dir = dir.join("xlsx");
+        std::fs::create_dir_all(&csv_dir).unwrap();
+        std::fs::create_dir_all(&xlsx_dir).unwrap();
+
+        std::fs::write(
+            csv_dir.join("invoicing-report.csv"),
+            "Teacher_Name,Eligible_Status,Activity_Start_Time,Activity_End_Time,shift_name_tsm\n\
+             Teacher One,Eligible,05/01/2026 09:00:00 AM,05/01/2026 11:00:00 AM,T-1\n\
+             Teacher Two,Not Eligible,05/01/2026 01:00:00 PM,05/01/2026 02:00:00 PM,T-2\n\
+             Teacher Three,Eligible,05/02/2026 09:00:00 AM,05/02/2026 10:00:00 AM,T-3\n",
+        )
+        .unwrap();
+
+        let at = |day: u32, hour: u32| {
+            XlsxCell::DateTime(
+                NaiveDate::from_ymd_opt(2026, 5, day)
+                    .unwrap()
+                    .and_hms_opt(hour, 0, 0)
+                    .unwrap(),
+            )
+        };
+        let text = XlsxCell::text;
+        let workbook = write_xlsx(
+            "Invoicing",
+            &[
+                vec![text("Invoicing Report")],
+                vec![],
+                vec![
+                    text("Teacher Name"),
+                    text("Eligible Status"),
+                    text("Activity Start Time"),
+                    text("Activity End Time"),
+                    text("Shift Name"),
+                ],
+                vec![
+                    text("Teacher One"),
+                    text("Eligible"),
+                    at(1, 9),
+                    at(1, 11),
+                    text("T-1"),
+                ],
+                vec![
+                    text("Teacher Two"),
+                    text("Not Eligible"),
+                    text("05/01/2026 01:00:00 PM"),
+                    text("05/01/2026 02:00:00 PM"),
+                    text("T-2"),
+                ],
+                vec![
+                    text("Teacher Three"),
+                    text("Eligible"),
+                    at(2, 9),
+                    at(2, 10),
+                    text("T-3"),
+                ],
+                vec![text("Grand Total"), XlsxCell::Number(3.0)],
+            ],
+        )
+        .unwrap();
+        std::fs::write(xlsx_dir.join("invoicing-report.xlsx"), workbook).unwrap();
+
+        let options = ImportOptions {
+            source_timezone: Johannesburg,
+            ..import_options(DateTimeProfileSelection::Flexible)
+        };
+        let process_calendar = parse_process_calendar_date("2026-05-01").unwrap();
+        let load = |dir: &std::path::Path| {
+            load_invoicing(
+                dir,
+                process_calendar,
+                &options,
+                &mut IngestionDiagnostics::default(),
+            )
+        };
+
+        let csv_rows = load(&csv_dir);
+        let xlsx_rows = load(&xlsx_dir);
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(csv_rows.len(), 2);
+        assert_eq!(csv_rows, xlsx_rows);
+        assert!(!csv_rows[1].eligible);
+        assert_eq!(
+            csv_rows[0].activity_start.to_rfc3339(),
+            "2026-05-01T07:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn loads_invoicing_names_containing_commas_from_excel_and_plain_csv() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-invoicing-comma-test-{}",
+            std::process::id()
+        ));
+        let excel_dir = dir.join("excel");
+        let plain_dir = dir.join("plain");
+        std::fs::create_dir_all(&excel_dir).unwrap();
+        std::fs::create_dir_all(&plain_dir).unwrap();
+
+        std::fs::write(
+            excel_dir.join("invoicing-report.csv"),
+            [
+                r#""Teacher_Name,""Eligible_Status"",""Activity_Start_Time"",""Activity_End_Time"",""shift_name_tsm"""#,
+                r#""""Magongo, Babalwa"",""Eligible"",""05/02/2026 09:00:00 AM"",""05/02/2026 11:00:00 AM"",""T-1, Morning"""#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            plain_dir.join("invoicing-report.csv"),
+            "Teacher_Name,Eligible_Status,Activity_Start_Time,Activity_End_Time,shift_name_tsm\n\
+             \"Magongo, Babalwa\",Eligible,05/02/2026 09:00:00 AM,05/02/2026 11:00:00 AM,\"T-1, Morning\"\n",
+        )
+        .unwrap();
+
+        let options = import_options(DateTimeProfileSelection::Flexible);
+        let load = |dir: &std::path::Path| {
+            load_invoicing(
+                dir,
+                parse_process_calendar_date("2026-05-02").unwrap(),
+                &options,
+                &mut IngestionDiagnostics::default(),
+            )
+        };
+
+        let excel_rows = load(&excel_dir);
+        let plain_rows = load(&plain_dir);
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(plain_rows.len(), 1);
+        assert_eq!(plain_rows[0].teacher_name, "Magongo, Babalwa");
+        assert_eq!(plain_rows[0].shift, "T-1, Morning");
+        assert_eq!(excel_rows, plain_rows);
+    }
+
+    #[test]
+    fn flags_dst_ambiguous_invoicing_rows_only_on_the_process_date() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-invoicing-dst-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("invoicing-report.csv"),
+            "Teacher_Name,Eligible_Status,Activity_Start_Time,Activity_End_Time,shift_name_tsm\n\
+             Teacher One,Eligible,11/02/2025 01:30:00 AM,11/02/2025 03:00:00 AM,T-1\n\
+             Teacher One,Eligible,11/01/2026 01:30:00 AM,11/01/2026 03:00:00 AM,T-2\n",
+        )
+        .unwrap();
+
+        let mut diagnostics = IngestionDiagnostics::default();
+        let rows = load_invoicing(
+            &dir,
+            parse_process_calendar_date("2026-11-01").unwrap(),
+            &ImportOptions {
+                source_timezone: New_York,
+                dst_policy: DstPolicy::Latest,
+                ..import_options(DateTimeProfileSelection::Flexible)
+            },
+            &mut diagnostics,
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        // Both rows fall in the repeated hour, but only the 2026 one is on the process date.
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].shift, "T-2");
+        assert_eq!(diagnostics.count("dst_ambiguous"), 1);
+        assert_eq!(diagnostics.issues[0].row, 3);
+    }
+
+    /// Writes an invoicing CSV with `rows` shifts spread over 2026-05-01 and 2026-05-02.
+    fn write_invoicing_fixture(path: &std::path::Path, rows: usize) {
+        use std::io::Write;
+
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+        writeln!(
+            file,
+            "Teacher_Name,Eligible_Status,Activity_Start_Time,Activity_End_Time,shift_name_tsm"
+        )
+        .unwrap();
+
+        for index in 0..rows {
+            let day = 1 + index % 2;
+            let hour = 1 + index % 11;
+            let eligible = if index % 3 == 0 {
+                "Not Eligible"
+            } else {
+                "Eligible"
+            };
+
+            writeln!(
+                file,
+                "\"Teacher, {}\",{},05/0{}/2026 {:02}:00:00 AM,05/0{}/2026 {:02}:30:00 AM,T-{}",
+                index % 5_000,
+                eligible,
+                day,
+                hour,
+                day,
+                hour,
+                index
+            )
+            .unwrap();
+        }
+
+        file.flush().unwrap();
+    }
+
+    fn peak_resident_kib() -> Option<u64> {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("VmHWM:"))?
+            .trim()
+            .trim_end_matches(" kB")
+            .parse()
+            .ok()
+    }
+
+    /// Run with `cargo test --release -- --ignored streams_a_million_invoicing_rows --nocapture`.
+    /// `INVOICING_BENCH_ROWS` overrides the row count.
+    #[test]
+    #[ignore]
+    fn streams_a_million_invoicing_rows_in_bounded_batches() {
+        let rows = std::env::var("INVOICING_BENCH_ROWS")
+            .ok()
+            .and_then(|rows| rows.parse().ok())
+            .unwrap_or(1_000_000);
+
+        let dir =
+            std::env::temp_dir().join(format!("sergio-ar-invoicing-bench-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_invoicing_fixture(&dir.join("invoicing-report.csv"), rows);
+
+        let options = import_options(DateTimeProfileSelection::Named(DateTimeProfile::us()));
+        let mut diagnostics = IngestionDiagnostics::default();
+        let started = std::time::Instant::now();
+
+        let source = open_invoicing_source(
+            dir.join("invoicing-report.csv").to_str().unwrap(),
+            &options.column_mappings,
+            &mut diagnostics,
+        )
+        .unwrap();
+
+        let mut batch = Vec::with_capacity(INGEST_BATCH_SIZE);
+        let mut batches = 0usize;
+        let mut streamed = 0usize;
+
+        stream_invoicing_rows(
+            &source,
+            parse_process_calendar_date("2026-05-01").unwrap(),
+            &options,
+            &mut diagnostics,
+            |row| {
+                batch.push(row);
+
+                if batch.len() == INGEST_BATCH_SIZE {
+                    streamed += batch.len();
+                    batches += 1;
+                    batch.clear();
+                }
+
+                ControlFlow::Continue(())
+            },
+        )
+        .unwrap();
+
+        streamed += batch.len();
+        let elapsed = started.elapsed();
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        println!(
+            "streamed {} of {} invoicing rows in {} full batches in {:?}, peak RSS {:?} KiB",
+            streamed,
+            rows,
+            batches,
+            elapsed,
+            peak_resident_kib()
+        );
+
+        // Odd rows fall on 2026-05-02 in Johannesburg and are filtered out.
+        assert_eq!(streamed, rows.div_ceil(2));
+        assert_eq!(batches, streamed / INGEST_BATCH_SIZE);
+        assert!(diagnostics.issues.is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+//! Parsing and classification of Dialogue snapshots and invoicing reports.
+//!
+//! The engine reads CSV or XLSX exports, classifies each shift by comparing two Dialogue
+//! snapshots, and hands the results to a [ConsolidationRepository] in bounded batches. It has no
+//! web or database dependency; the API supplies a Postgres repository and tests use
+//! [InMemoryRepository].
+
+pub mod column_mappings;
+pub mod datetime_profiles;
+pub mod dialogue;
+pub mod encoding;
+pub mod import;
+pub mod invoicing;
+pub mod pipeline;
+pub mod repository;
+pub mod timezones;
+
+#[cfg(test)]
+mod dialogue_properties;
+#[cfg(test)]
+mod test_support;
+
+pub use dialogue::{
+    consolidate_dialogue_rows, load_dialogue_rows, DialogueConsolidatedRow, DialogueRow,
+};
+pub use import::{
+    parse_process_calendar_date, ConsolidationSettings, ImportOptions, IngestionDiagnostics,
+    IngestionIssue,
+};
+pub use invoicing::{open_invoicing_source, stream_invoicing_rows, InvoicingRow};
+pub use pipeline::{
+    consolidate_files, preview_consolidation, ConsolidationInputs, ConsolidationPreview,
+    ConsolidationSummary,
+};
+pub use repository::{ConsolidationRepository, InMemoryRepository, ScheduleRecord, StoredInvoices};
@@ -0,0 +1,966 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::ControlFlow,
+};
+
+use anyhow::{Context, Error};
+use chrono::NaiveDate;
+use serde::Serialize;
+use tokio::{sync::mpsc, task::spawn_blocking};
+
+use crate::{
+    column_mappings::ColumnMappings,
+    datetime_profiles::DateTimeProfileSelection,
+    dialogue::{
+        consolidate_dialogue_rows, load_dialogue_rows, log_dialogue_file_date_diagnosis,
+        parse_dialogue_datetime, DialogueConsolidatedRow,
+    },
+    import::{
+        app_local_to_utc, parse_process_calendar_date, ConsolidationSettings, ImportOptions,
+        ImportSource, IngestionDiagnostics, MAX_ISSUE_SAMPLES,
+    },
+    invoicing::{
+        build_invoicing_csv_columns, open_invoicing_source, stream_invoicing_rows, InvoicingRow,
+    },
+    repository::{ConsolidationRepository, InMemoryRepository, ScheduleRecord},
+};
+
+/// Rows held in memory before they are written to Postgres in one statement.
+pub(crate) const INGEST_BATCH_SIZE: usize = 5_000;
+
+/// Batches the invoicing reader may run ahead of the database writer.
+pub(crate) const INGEST_CHANNEL_BATCHES: usize = 2;
+
+/// Finds `{name}.csv` or `{name}.xlsx` in an upload directory, preferring CSV.
+pub(crate) fn find_upload_file(base_path: &str, name: &str) -> Result<String, Error> {
+    let csv_path = format!("{}/{}.csv", base_path, name);
+    let xlsx_path = format!("{}/{}.xlsx", base_path, name);
+
+    if std::path::Path::new(&csv_path).exists() {
+        Ok(csv_path)
+    } else if std::path::Path::new(&xlsx_path).exists() {
+        Ok(xlsx_path)
+    } else {
+        Err(anyhow::anyhow!(
+            "{} file not found (tried .csv and .xlsx)",
+            name
+        ))
+    }
+}
+
+/// The three files one consolidation run reads. Each can be CSV or XLSX.
+#[derive(Debug, Clone)]
+pub struct ConsolidationInputs {
+    pub dialogue_1: String,
+    pub dialogue_2: String,
+    pub invoicing: String,
+}
+
+impl ConsolidationInputs {
+    /// Locates `dialogue-1`, `dialogue-2` and `invoicing-report` in an upload directory.
+    pub fn from_upload_dir(base_path: &str) -> Result<ConsolidationInputs, Error> {
+        Ok(ConsolidationInputs {
+            dialogue_1: find_upload_file(base_path, "dialogue-1")?,
+            dialogue_2: find_upload_file(base_path, "dialogue-2")?,
+            invoicing: find_upload_file(base_path, "invoicing-report")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsolidationSummary {
+    /// Classified shifts read from the Dialogue snapshots.
+    pub parsed_shifts: usize,
+    /// Invoicing rows read for the process date.
+    pub parsed_invoices: usize,
+    pub new_teachers: usize,
+    pub skipped_teachers: usize,
+    pub new_shifts: usize,
+    pub skipped_shifts: usize,
+    pub inserted_invoices: usize,
+    /// Invoicing rows dropped because a later row in the same batch has the same key.
+    pub skipped_invoices: usize,
+    pub updated_invoices: usize,
+    /// Shift groups that had shifts on the process date, sorted.
+    pub shift_groups: Vec<String>,
+    /// Classified shifts by `shift_type`.
+    pub shift_types: BTreeMap<String, usize>,
+    pub diagnostics: IngestionDiagnostics,
+}
+
+/// What a consolidation run would store, computed without touching the database.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsolidationPreview {
+    pub schedules: Vec<DialogueConsolidatedRow>,
+    pub invoicing_rows: usize,
+    pub diagnostics: IngestionDiagnostics,
+}
+
+pub(crate) fn log_import_settings(
+    dialogue_options: &ImportOptions,
+    invoicing_options: &ImportOptions,
+    profile_selection: &DateTimeProfileSelection,
+) {
+    tracing::info!(
+        "🕐 Dialogue files (dialogue-1, dialogue-2): {} (naive in CSV) → {} (stored/filtered)",
+        dialogue_options.source_timezone,
+        dialogue_options.app_timezone
+    );
+    tracing::info!(
+        "🕐 Invoicing report: {} (naive in CSV/XLSX) → {} (filtered)",
+        invoicing_options.source_timezone,
+        invoicing_options.app_timezone
+    );
+    tracing::info!("🕐 Datetime profile: {}", profile_selection.name());
+}
+
+pub(crate) fn log_ingestion_diagnostics(diagnostics: &IngestionDiagnostics) {
+    if diagnostics.counts.is_empty() {
+        return;
+    }
+
+    tracing::warn!(
+        "⚠️ Ingestion diagnostics: {} issues ({}), the first {} of each kind follow",
+        diagnostics.total(),
+        diagnostics
+            .counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", "),
+        MAX_ISSUE_SAMPLES
+    );
+
+    for issue in &diagnostics.issues {
+        tracing::warn!(
+            "⚠️ {} row {} [{}]: {}",
+            issue.file,
+            issue.row,
+            issue.kind,
+            issue.message
+        );
+    }
+}
+
+/// Opens the invoicing report and checks its headers before any rows are read.
+pub(crate) fn open_checked_invoicing_source(
+    inputs: &ConsolidationInputs,
+    options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Result<ImportSource, Error> {
+    let invoicing_source =
+        open_invoicing_source(&inputs.invoicing, &options.column_mappings, diagnostics)?;
+    build_invoicing_csv_columns(&invoicing_source.headers, &options.column_mappings)?;
+
+    tracing::info!("✅ Successfully opened invoicing file.");
+
+    Ok(invoicing_source)
+}
+
+/// Loads both dialogue snapshots and returns the classified shifts that start on the process
+/// date, sorted by shift group, teacher and start.
+pub(crate) fn classify_dialogue_files(
+    inputs: &ConsolidationInputs,
+    process_calendar: NaiveDate,
+    dialogue_options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Result<Vec<DialogueConsolidatedRow>, Error> {
+    // Consolidate dialogue snapshots (UTC export times → local timezone)
+    tracing::info!("❕ Mapping first dialogue file (dialogue-1)...");
+    let first_dialogue_rows = load_dialogue_rows(
+        &inputs.dialogue_1,
+        process_calendar,
+        dialogue_options,
+        diagnostics,
+    )?;
+
+    tracing::info!("✅ Successfully mapped first dialogue file.");
+
+    tracing::info!("❕ Mapping second dialogue file (dialogue-2)...");
+    let second_dialogue_rows = load_dialogue_rows(
+        &inputs.dialogue_2,
+        process_calendar,
+        dialogue_options,
+        diagnostics,
+    )?;
+
+    tracing::info!("✅ Successfully mapped second dialogue file.");
+
+    if second_dialogue_rows.is_empty() && !first_dialogue_rows.is_empty() {
+        log_dialogue_file_date_diagnosis(
+            &inputs.dialogue_2,
+            process_calendar,
+            &dialogue_options.column_mappings,
+        );
+
+        return Err(anyhow::anyhow!(
+            "dialogue-2 has no shifts on process date {} (dialogue-1 has {}). \
+             ASM exports are multi-week schedules: the filename export timestamp is not the shift date. \
+             For example, ASM - DL Scheduled Shifts 2026-05-01-15-00-19.csv is exported on May 1 but contains May 2+ shifts only. \
+             Use a dialogue-2 file that includes shifts dated {}, or run consolidation for the date that dialogue-2 actually contains.",
+            process_calendar,
+            first_dialogue_rows.len(),
+            process_calendar
+        ));
+    }
+
+    tracing::info!("❕ Consolidating dialogues...");
+
+    tracing::info!("❕ First dialogue rows: {}", first_dialogue_rows.len());
+    tracing::info!("❕ Second dialogue rows: {}", second_dialogue_rows.len());
+
+    let mut consolidated_rows =
+        consolidate_dialogue_rows(&first_dialogue_rows, &second_dialogue_rows);
+
+    // let mut first_dialogue_rows_split: HashMap<String, Vec<DialogueRow>> = HashMap::new();
+
+    // for row in first_dialogue_rows {
+    //     let shift_group = row.shift_group.clone();
+
+    //     match first_dialogue_rows_split.get_mut(&shift_group) {
+    //         Some(shift_group_rows) => {
+    //             shift_group_rows.push(row);
+    //         }
+    //         None => {
+    //             let mut shift_group_rows: Vec<DialogueRow> = Vec::new();
+
+    //             shift_group_rows.push(row);
+
+    //             first_dialogue_rows_split.insert(shift_group, shift_group_rows);
+    //         }
+    //     }
+    // }
+
+    // let mut second_dialogue_rows_split: HashMap<String, Vec<DialogueRow>> = HashMap::new();
+
+    // for row in second_dialogue_rows {
+    //     let shift_group = row.shift_group.clone();
+
+    //     match second_dialogue_rows_split.get_mut(&shift_group) {
+    //         Some(shift_group_rows) => {
+    //             shift_group_rows.push(row);
+    //         }
+    //         None => {
+    //             let mut shift_group_rows: Vec<DialogueRow> = Vec::new();
+
+    //             shift_group_rows.push(row);
+
+    //             second_dialogue_rows_split.insert(shift_group, shift_group_rows);
+    //         }
+    //     }
+    // }
+
+    // for (shift_group, first_dialogue_rows) in first_dialogue_rows_split {
+    //     let second_dialogue_rows = second_dialogue_rows_split.get(&shift_group);
+
+    //     match second_dialogue_rows {
+    //         Some(second_dialogue_rows) => {
+    //             let first_shifts = first_dialogue_rows
+    //                 .iter()
+    //                 .map(|row| row.shift.clone())
+    //                 .collect::<Vec<String>>();
+    //             let second_shifts = second_dialogue_rows
+    //                 .iter()
+    //                 .map(|row| row.shift.clone())
+    //                 .collect::<Vec<String>>();
+
+    //             let lost_shifts = first_shifts
+    //                 .iter()
+    //                 .filter(|shift| !second_shifts.contains(shift))
+    //                 .collect::<Vec<&String>>();
+    //             let new_shifts = second_shifts
+    //                 .iter()
+    //                 .filter(|shift| !first_shifts.contains(shift))
+    //                 .collect::<Vec<&String>>();
+
+    //             let previous_shift_teachers = first_dialogue_rows
+    //                 .iter()
+    //                 .map(|row| (row.shift.clone(), row.teacher_name.clone()))
+    //                 .collect::<HashMap<String, String>>();
+
+    //             let picked_up_shifts = second_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| {
+    //                     let previous_shift_teacher = previous_shift_teachers.get(&row.shift);
+
+    //                     match previous_shift_teacher {
+    //                         Some(previous_shift_teacher) => {
+    //                             if previous_shift_teacher != &row.teacher_name {
+    //                                 true
+    //                             } else {
+    //                                 false
+    //                             }
+    //                         }
+    //                         None => false,
+    //                     }
+    //                 })
+    //                 .map(|row| row.shift.clone())
+    //                 .collect::<Vec<String>>();
+
+    //             let lost_but_picked_up_shifts = first_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| {
+    //                     let second_shift = second_shifts.contains(&row.shift);
+
+    //                     if second_shift {
+    //                         let second_dialogue_row = second_dialogue_rows
+    //                             .iter()
+    //                             .find(|second_dialogue_row| second_dialogue_row.shift == row.shift);
+
+    //                         match second_dialogue_row {
+    //                             Some(second_dialogue_row) => {
+    //                                 if second_dialogue_row.teacher_name != row.teacher_name {
+    //                                     true
+    //                                 } else {
+    //                                     false
+    //                                 }
+    //                             }
+    //                             None => false,
+    //                         }
+    //                     } else {
+    //                         false
+    //                     }
+    //                 })
+    //                 .map(|row| row.shift.clone())
+    //                 .collect::<Vec<String>>();
+
+    //             for current_shift in second_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| new_shifts.contains(&&row.shift))
+    //                 .collect::<Vec<&DialogueRow>>()
+    //             {
+    //                 let consolidated_row = DialogueConsolidatedRow {
+    //                     shift_group: shift_group.clone(),
+    //                     shift: current_shift.shift.clone(),
+    //                     shift_type: "Pickup".to_string(),
+    //                     teacher_name: current_shift.teacher_name.clone(),
+    //                     start_date: current_shift.start_date.clone(),
+    //                     end_date: current_shift.end_date.clone(),
+    //                 };
+
+    //                 consolidated_rows.push(consolidated_row);
+    //             }
+
+    //             for current_shift in second_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| picked_up_shifts.contains(&&row.shift))
+    //                 .collect::<Vec<&DialogueRow>>()
+    //             {
+    //                 let consolidated_row = DialogueConsolidatedRow {
+    //                     shift_group: shift_group.clone(),
+    //                     shift: current_shift.shift.clone(),
+    //                     shift_type: "Internal Pickup".to_string(),
+    //                     teacher_name: current_shift.teacher_name.clone(),
+    //                     start_date: current_shift.start_date.clone(),
+    //                     end_date: current_shift.end_date.clone(),
+    //                 };
+
+    //                 consolidated_rows.push(consolidated_row);
+    //             }
+
+    //             for current_shift in first_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| lost_shifts.contains(&&row.shift))
+    //                 .collect::<Vec<&DialogueRow>>()
+    //             {
+    //                 let consolidated_row = DialogueConsolidatedRow {
+    //                     shift_group: shift_group.clone(),
+    //                     shift: current_shift.shift.clone(),
+    //                     shift_type: "Dropped".to_string(),
+    //                     teacher_name: current_shift.teacher_name.clone(),
+    //                     start_date: current_shift.start_date.clone(),
+    //                     end_date: current_shift.end_date.clone(),
+    //                 };
+
+    //                 consolidated_rows.push(consolidated_row);
+    //             }
+
+    //             for current_shift in first_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| lost_but_picked_up_shifts.contains(&&row.shift))
+    //                 .collect::<Vec<&DialogueRow>>()
+    //             {
+    //                 let consolidated_row = DialogueConsolidatedRow {
+    //                     shift_group: shift_group.clone(),
+    //                     shift: current_shift.shift.clone(),
+    //                     shift_type: "Dropped & Picked Up".to_string(),
+    //                     teacher_name: current_shift.teacher_name.clone(),
+    //                     start_date: current_shift.start_date.clone(),
+    //                     end_date: current_shift.end_date.clone(),
+    //                 };
+
+    //                 consolidated_rows.push(consolidated_row);
+    //             }
+
+    //             for current_shift in second_dialogue_rows
+    //                 .iter()
+    //                 .filter(|row| {
+    //                     !picked_up_shifts.contains(&&row.shift)
+    //                         && !new_shifts.contains(&&row.shift)
+    //                         && !lost_shifts.contains(&&row.shift)
+    //                         && !lost_but_picked_up_shifts.contains(&&row.shift)
+    //                 })
+    //                 .collect::<Vec<&DialogueRow>>()
+    //             {
+    //                 let consolidated_row = DialogueConsolidatedRow {
+    //                     shift_group: shift_group.clone(),
+    //                     shift: current_shift.shift.clone(),
+    //                     shift_type: "-".to_string(),
+    //                     teacher_name: current_shift.teacher_name.clone(),
+    //                     start_date: current_shift.start_date.clone(),
+    //                     end_date: current_shift.end_date.clone(),
+    //                 };
+
+    //                 consolidated_rows.push(consolidated_row);
+    //             }
+    //         }
+    //         None => {}
+    //     }
+    // }
+
+    tracing::info!("❕ Consolidated rows: {}", consolidated_rows.len());
+
+    tracing::info!("✅ Successfully consolidated dialogues.");
+
+    consolidated_rows.sort_by(|a, b| {
+        match (
+            parse_dialogue_datetime(&a.start_date),
+            parse_dialogue_datetime(&b.start_date),
+        ) {
+            (Ok(a_date), Ok(b_date)) => a_date.cmp(&b_date),
+            _ => a.start_date.cmp(&b.start_date),
+        }
+    });
+
+    consolidated_rows.sort_by(|a, b| a.teacher_name.cmp(&b.teacher_name));
+    consolidated_rows.sort_by(|a, b| a.shift_group.cmp(&b.shift_group));
+
+    // Use the invoicing rows to determine which consolidated rows are eligible
+    let consolidated_rows: Vec<DialogueConsolidatedRow> = consolidated_rows
+        .into_iter()
+        .filter(|row| {
+            match parse_dialogue_datetime(&row.start_date) {
+                Ok(row_start_date) => row_start_date.date() == process_calendar,
+                Err(error) => {
+                    tracing::warn!(
+                        "Skipping consolidated row for teacher {} due to invalid start datetime {:?}: {:?}",
+                        row.teacher_name,
+                        row.start_date,
+                        error
+                    );
+                    false
+                }
+            }
+        })
+        .collect::<Vec<DialogueConsolidatedRow>>();
+
+    tracing::info!("❕ Consolidated rows: {}", consolidated_rows.len());
+
+    Ok(consolidated_rows)
+}
+
+/// Runs the whole pipeline against an [InMemoryRepository], so nothing is written anywhere.
+pub async fn preview_consolidation(
+    settings: &ConsolidationSettings,
+    inputs: &ConsolidationInputs,
+    process_date: &str,
+    profile_selection: DateTimeProfileSelection,
+    column_mappings: ColumnMappings,
+) -> Result<ConsolidationPreview, Error> {
+    let repository = InMemoryRepository::default();
+    let summary = consolidate_files(
+        &repository,
+        settings,
+        inputs,
+        process_date,
+        profile_selection,
+        column_mappings,
+    )
+    .await?;
+
+    Ok(ConsolidationPreview {
+        schedules: repository
+            .schedules()
+            .into_iter()
+            .map(|schedule| schedule.row)
+            .collect(),
+        invoicing_rows: summary.inserted_invoices + summary.updated_invoices,
+        diagnostics: summary.diagnostics,
+    })
+}
+
+/// Keeps the last occurrence of each invoice key in a batch, as row-by-row updates used to.
+fn dedupe_invoice_batch(batch: Vec<InvoicingRow>) -> Vec<InvoicingRow> {
+    let mut positions = HashMap::new();
+    let mut invoicing_rows: Vec<InvoicingRow> = Vec::with_capacity(batch.len());
+
+    for invoicing_row in batch {
+        let key = (
+            invoicing_row.teacher_name.clone(),
+            invoicing_row.shift.clone(),
+            invoicing_row.activity_start,
+            invoicing_row.activity_end,
+        );
+
+        match positions.get(&key) {
+            Some(&position) => invoicing_rows[position] = invoicing_row,
+            None => {
+                positions.insert(key, invoicing_rows.len());
+                invoicing_rows.push(invoicing_row);
+            }
+        }
+    }
+
+    invoicing_rows
+}
+
+/// Consolidates the given files for `process_date` and writes teachers, schedules and
+/// invoices to `repository` in bounded batches.
+pub async fn consolidate_files<R: ConsolidationRepository>(
+    repository: &R,
+    settings: &ConsolidationSettings,
+    inputs: &ConsolidationInputs,
+    process_date: &str,
+    profile_selection: DateTimeProfileSelection,
+    column_mappings: ColumnMappings,
+) -> Result<ConsolidationSummary, Error> {
+    let dialogue_options =
+        ImportOptions::dialogue(settings, profile_selection.clone(), column_mappings.clone());
+    let invoicing_options =
+        ImportOptions::invoicing(settings, profile_selection.clone(), column_mappings);
+    let mut diagnostics = IngestionDiagnostics::default();
+
+    let process_calendar = parse_process_calendar_date(process_date)?;
+
+    log_import_settings(&dialogue_options, &invoicing_options, &profile_selection);
+
+    let invoicing_source =
+        open_checked_invoicing_source(inputs, &invoicing_options, &mut diagnostics)?;
+
+    tracing::info!("❕ Consolidating files...");
+
+    // Reading and parsing both snapshots is blocking file and CSV work.
+    let (consolidated_rows, dialogue_diagnostics) = spawn_blocking({
+        let inputs = inputs.clone();
+        let dialogue_options = dialogue_options.clone();
+
+        move || -> Result<_, Error> {
+            let mut diagnostics = IngestionDiagnostics::default();
+            let rows = classify_dialogue_files(
+                &inputs,
+                process_calendar,
+                &dialogue_options,
+                &mut diagnostics,
+            )?;
+
+            Ok((rows, diagnostics))
+        }
+    })
+    .await??;
+
+    diagnostics.extend(dialogue_diagnostics);
+
+    // Consolidate invoicing file. The reader runs on a blocking thread and hands bounded
+    // batches to the database writer, so neither side holds more than a few batches.
+    tracing::info!("❕ Mapping and storing invoicing file...");
+
+    let (batch_sender, mut batch_receiver) = mpsc::channel(INGEST_CHANNEL_BATCHES);
+    let invoicing_reader = spawn_blocking({
+        let invoicing_options = invoicing_options.clone();
+
+        move || -> Result<IngestionDiagnostics, Error> {
+            let mut diagnostics = IngestionDiagnostics::default();
+            let mut batch = Vec::with_capacity(INGEST_BATCH_SIZE);
+
+            stream_invoicing_rows(
+                &invoicing_source,
+                process_calendar,
+                &invoicing_options,
+                &mut diagnostics,
+                |invoicing_row| {
+                    batch.push(invoicing_row);
+
+                    if batch.len() < INGEST_BATCH_SIZE {
+                        return ControlFlow::Continue(());
+                    }
+
+                    let full_batch =
+                        std::mem::replace(&mut batch, Vec::with_capacity(INGEST_BATCH_SIZE));
+
+                    match batch_sender.blocking_send(full_batch) {
+                        Ok(()) => ControlFlow::Continue(()),
+                        Err(_) => ControlFlow::Break(()),
+                    }
+                },
+            )?;
+
+            if !batch.is_empty() {
+                let _ = batch_sender.blocking_send(batch);
+            }
+
+            Ok(diagnostics)
+        }
+    });
+
+    let mut parsed_invoices = 0;
+    let mut inserted_invoices = 0;
+    let mut skipped_invoices = 0;
+    let mut updated_invoices = 0;
+
+    while let Some(batch) = batch_receiver.recv().await {
+        let batch_size = batch.len();
+        parsed_invoices += batch_size;
+
+        let invoices = dedupe_invoice_batch(batch);
+        skipped_invoices += batch_size - invoices.len();
+
+        // Dropping the receiver on the way out stops the reader at its next full batch.
+        let stored = repository
+            .store_invoices(&invoices)
+            .await
+            .with_context(|| format!("Failed to store a batch of {} invoices", invoices.len()))?;
+
+        inserted_invoices += stored.inserted;
+        updated_invoices += stored.updated;
+    }
+
+    diagnostics.extend(invoicing_reader.await??);
+
+    tracing::info!(
+        "✅ Successfully stored invoicing file ({} inserted, {} updated, {} skipped).",
+        inserted_invoices,
+        updated_invoices,
+        skipped_invoices
+    );
+
+    tracing::info!("❕ Storing consolidated rows to the database...");
+
+    let mut new_teachers = 0;
+    let mut skipped_teachers = 0;
+    let mut new_shifts = 0;
+    let mut skipped_shifts = 0;
+
+    for chunk in consolidated_rows.chunks(INGEST_BATCH_SIZE) {
+        let mut schedules = Vec::with_capacity(chunk.len());
+
+        for consolidated_row in chunk {
+            let start_date =
+                parse_dialogue_datetime(&consolidated_row.start_date).map_err(|error| {
+                    tracing::error!(
+                        "🔥 Failed to parse consolidated row start datetime {:?}: {:?}",
+                        consolidated_row.start_date,
+                        error
+                    );
+
+                    Error::msg("Failed to parse consolidated row start datetime.")
+                })?;
+            let end_date =
+                parse_dialogue_datetime(&consolidated_row.end_date).map_err(|error| {
+                    tracing::error!(
+                        "🔥 Failed to parse consolidated row end datetime {:?}: {:?}",
+                        consolidated_row.end_date,
+                        error
+                    );
+
+                    Error::msg("Failed to parse consolidated row end datetime.")
+                })?;
+
+            schedules.push(ScheduleRecord {
+                row: consolidated_row,
+                start_date: app_local_to_utc(start_date, dialogue_options.app_timezone),
+                end_date: app_local_to_utc(end_date, dialogue_options.app_timezone),
+            });
+        }
+
+        let inserted_teachers = repository
+            .store_teachers(&schedules)
+            .await
+            .map_err(|error| {
+                tracing::error!("🔥 Failed to store teachers: {:?}", error);
+
+                // Keeps the database error in the chain so the queue can tell if it is transient.
+                error.context("Failed to store teachers.")
+            })?;
+
+        new_teachers += inserted_teachers;
+        skipped_teachers += schedules.len() - inserted_teachers;
+
+        let inserted_shifts = repository
+            .store_schedules(&schedules)
+            .await
+            .map_err(|error| {
+                tracing::error!("🔥 Failed to store schedules: {:?}", error);
+
+                error.context("Failed to store schedules.")
+            })?;
+
+        new_shifts += inserted_shifts;
+        skipped_shifts += schedules.len() - inserted_shifts;
+    }
+
+    tracing::info!("✅ Invoicing consolidation complete.");
+
+    log_ingestion_diagnostics(&diagnostics);
+
+    let shift_groups = consolidated_rows
+        .iter()
+        .map(|row| row.shift_group.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut shift_types = BTreeMap::new();
+
+    for row in &consolidated_rows {
+        *shift_types.entry(row.shift_type.clone()).or_insert(0) += 1;
+    }
+
+    Ok(ConsolidationSummary {
+        parsed_shifts: consolidated_rows.len(),
+        parsed_invoices,
+        new_teachers,
+        skipped_teachers,
+        new_shifts,
+        skipped_shifts,
+        inserted_invoices,
+        skipped_invoices,
+        updated_invoices,
+        shift_groups,
+        shift_types,
+        diagnostics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use anyhow::{anyhow, Error};
+    use chrono_tz::{Africa::Johannesburg, UTC};
+
+    use super::{consolidate_files, preview_consolidation, ConsolidationInputs};
+    use crate::{
+        column_mappings::ColumnMappings,
+        datetime_profiles::{DateTimeProfile, DateTimeProfileSelection},
+        import::ConsolidationSettings,
+        invoicing::InvoicingRow,
+        repository::{ConsolidationRepository, InMemoryRepository, ScheduleRecord, StoredInvoices},
+        timezones::DstPolicy,
+    };
+
+    fn settings() -> ConsolidationSettings {
+        ConsolidationSettings {
+            app_timezone: Johannesburg,
+            dialogue_timezone: UTC,
+            invoicing_timezone: Johannesburg,
+            dialogue_dst_policy: DstPolicy::Earliest,
+            invoicing_dst_policy: DstPolicy::Earliest,
+            datetime_profiles: DateTimeProfile::builtin(),
+        }
+    }
+
+    fn write_inputs(dir: &Path) -> ConsolidationInputs {
+        std::fs::create_dir_all(dir).unwrap();
+
+        let header =
+            "Start,Finish,Shift: Shift Number,Resource: Shift Group,Resource: Resource Name\n";
+        std::fs::write(
+            dir.join("first.csv"),
+            format!(
+                "{}05/01/2026 9:00 AM,05/01/2026 11:00 AM,T-1,JEN 4 - PM,Babalwa Magongo\n",
+                header
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("second.csv"),
+            format!(
+                "{}05/01/2026 9:00 AM,05/01/2026 11:00 AM,T-1,JEN 4 - PM,Babalwa Magongo\n\
+                 05/01/2026 9:00 AM,05/01/2026 11:00 AM,T-2,JEN 4 - PM,Ann Smith\n",
+                header
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("invoicing.csv"),
+            "Teacher_Name,Eligible_Status,Activity_Start_Time,Activity_End_Time,shift_name_tsm\n\
+             Ann Smith,Eligible,05/01/2026 11:00:00 AM,05/01/2026 01:00:00 PM,T-2\n",
+        )
+        .unwrap();
+
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        ConsolidationInputs {
+            dialogue_1: path("first.csv"),
+            dialogue_2: path("second.csv"),
+            invoicing: path("invoicing.csv"),
+        }
+    }
+
+    #[tokio::test]
+    async fn previews_consolidation_from_local_files_without_a_database() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-pipeline-preview-test-{}",
+            std::process::id()
+        ));
+        let inputs = write_inputs(&dir);
+
+        let preview = preview_consolidation(
+            &settings(),
+            &inputs,
+            "2026-05-01",
+            DateTimeProfileSelection::Named(DateTimeProfile::us()),
+            ColumnMappings::defaults(),
+        )
+        .await;
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let preview = preview.expect("preview");
+        let mut classified = preview
+            .schedules
+            .iter()
+            .map(|row| (row.teacher_name.as_str(), row.shift_type.as_str()))
+            .collect::<Vec<_>>();
+        classified.sort();
+
+        assert_eq!(
+            classified,
+            vec![("Ann Smith", "Pickup"), ("Babalwa Magongo", "-")]
+        );
+        assert!(preview
+            .schedules
+            .iter()
+            .all(|row| row.start_date == "2026-05-01 11:00:00"));
+        assert_eq!(preview.invoicing_rows, 1);
+    }
+
+    #[tokio::test]
+    async fn rerunning_into_the_same_repository_only_updates_invoices() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-pipeline-rerun-test-{}",
+            std::process::id()
+        ));
+        let inputs = write_inputs(&dir);
+        let settings = settings();
+        let repository = InMemoryRepository::default();
+        let run = || {
+            consolidate_files(
+                &repository,
+                &settings,
+                &inputs,
+                "2026-05-01",
+                DateTimeProfileSelection::Named(DateTimeProfile::us()),
+                ColumnMappings::defaults(),
+            )
+        };
+
+        let first = run().await;
+        let second = run().await;
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let first = first.expect("first run");
+        let second = second.expect("second run");
+
+        assert_eq!((first.parsed_shifts, first.parsed_invoices), (2, 1));
+        assert_eq!(first.shift_groups, vec!["JEN 4 - PM".to_string()]);
+        assert_eq!(
+            first.shift_types.values().sum::<usize>(),
+            first.parsed_shifts
+        );
+        assert_eq!((first.new_teachers, first.new_shifts), (2, 2));
+        assert_eq!(first.inserted_invoices, 1);
+        assert_eq!((second.new_teachers, second.skipped_teachers), (0, 2));
+        assert_eq!((second.new_shifts, second.skipped_shifts), (0, 2));
+        assert_eq!((second.inserted_invoices, second.updated_invoices), (0, 1));
+        assert_eq!(repository.schedules().len(), 2);
+        assert_eq!(repository.invoices().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn counts_repeated_invoice_rows_as_skipped_rather_than_updated() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-pipeline-repeated-invoice-test-{}",
+            std::process::id()
+        ));
+        let inputs = write_inputs(&dir);
+        std::fs::write(
+            &inputs.invoicing,
+            "Teacher_Name,Eligible_Status,Activity_Start_Time,Activity_End_Time,shift_name_tsm\n\
+             Ann Smith,Not Eligible,05/01/2026 11:00:00 AM,05/01/2026 01:00:00 PM,T-2\n\
+             Ann Smith,Eligible,05/01/2026 11:00:00 AM,05/01/2026 01:00:00 PM,T-2\n",
+        )
+        .unwrap();
+        let repository = InMemoryRepository::default();
+
+        let summary = consolidate_files(
+            &repository,
+            &settings(),
+            &inputs,
+            "2026-05-01",
+            DateTimeProfileSelection::Named(DateTimeProfile::us()),
+            ColumnMappings::defaults(),
+        )
+        .await;
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let summary = summary.expect("summary");
+
+        assert_eq!(summary.parsed_invoices, 2);
+        assert_eq!(
+            (
+                summary.inserted_invoices,
+                summary.updated_invoices,
+                summary.skipped_invoices
+            ),
+            (1, 0, 1)
+        );
+        assert!(repository.invoices()[0].eligible);
+    }
+
+    /// Stores shifts in memory but fails every invoice batch.
+    #[derive(Default)]
+    struct FailingInvoices(InMemoryRepository);
+
+    impl ConsolidationRepository for FailingInvoices {
+        async fn store_invoices(&self, _: &[InvoicingRow]) -> Result<StoredInvoices, Error> {
+            Err(anyhow!("connection reset"))
+        }
+
+        async fn store_teachers(&self, schedules: &[ScheduleRecord<'_>]) -> Result<usize, Error> {
+            self.0.store_teachers(schedules).await
+        }
+
+        async fn store_schedules(&self, schedules: &[ScheduleRecord<'_>]) -> Result<usize, Error> {
+            self.0.store_schedules(schedules).await
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_the_run_when_an_invoice_batch_cannot_be_stored() {
+        let dir = std::env::temp_dir().join(format!(
+            "sergio-ar-pipeline-failing-invoices-test-{}",
+            std::process::id()
+        ));
+        let inputs = write_inputs(&dir);
+
+        let result = consolidate_files(
+            &FailingInvoices::default(),
+            &settings(),
+            &inputs,
+            "2026-05-01",
+            DateTimeProfileSelection::Named(DateTimeProfile::us()),
+            ColumnMappings::defaults(),
+        )
+        .await;
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let error = result.expect_err("the invoice batch failed");
+
+        assert!(format!("{:#}", error).contains("Failed to store a batch of 1 invoices"));
+        assert_eq!(error.root_cause().to_string(), "connection reset");
+    }
+}
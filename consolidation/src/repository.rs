@@ -0,0 +1,168 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Mutex,
+};
+
+use anyhow::Error;
+use chrono::{DateTime, Utc};
+
+use crate::{dialogue::DialogueConsolidatedRow, invoicing::InvoicingRow};
+
+/// A classified shift with its start and end converted to UTC for storage.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleRecord<'a> {
+    pub row: &'a DialogueConsolidatedRow,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+}
+
+/// How many invoices of a batch were added and how many matched a stored one and were updated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoredInvoices {
+    pub inserted: usize,
+    pub updated: usize,
+}
+
+/// Where a consolidation run writes its results. Every method takes a whole batch and returns
+/// how many rows were new, so the engine can report inserted and skipped counts.
+pub trait ConsolidationRepository: Sync {
+    /// Upserts invoices on (teacher, shift, start, end), updating eligibility on a match. The
+    /// batch holds no duplicate keys.
+    fn store_invoices(
+        &self,
+        invoices: &[InvoicingRow],
+    ) -> impl Future<Output = Result<StoredInvoices, Error>> + Send;
+
+    /// Adds the teachers named in `schedules` that are not stored yet.
+    fn store_teachers(
+        &self,
+        schedules: &[ScheduleRecord<'_>],
+    ) -> impl Future<Output = Result<usize, Error>> + Send;
+
+    /// Adds the schedules that are not stored yet. Runs after [Self::store_teachers] for the
+    /// same batch.
+    fn store_schedules(
+        &self,
+        schedules: &[ScheduleRecord<'_>],
+    ) -> impl Future<Output = Result<usize, Error>> + Send;
+}
+
+type InvoiceKey = (String, String, DateTime<Utc>, DateTime<Utc>);
+
+/// Teacher, shift, shift type, shift group, start and end: the columns a stored schedule is
+/// matched on.
+type ScheduleKey = (String, String, String, String, DateTime<Utc>, DateTime<Utc>);
+
+#[derive(Debug, Clone)]
+pub struct StoredSchedule {
+    pub row: DialogueConsolidatedRow,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct InMemoryState {
+    invoices: Vec<InvoicingRow>,
+    invoice_positions: HashMap<InvoiceKey, usize>,
+    teachers: Vec<String>,
+    teacher_names: HashSet<String>,
+    schedules: Vec<StoredSchedule>,
+    schedule_keys: HashSet<ScheduleKey>,
+}
+
+/// Keeps everything in memory with the same de-duplication rules as the Postgres tables. Used
+/// for dry runs and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryRepository {
+    state: Mutex<InMemoryState>,
+}
+
+impl InMemoryRepository {
+    pub fn invoices(&self) -> Vec<InvoicingRow> {
+        self.state.lock().unwrap().invoices.clone()
+    }
+
+    pub fn teachers(&self) -> Vec<String> {
+        self.state.lock().unwrap().teachers.clone()
+    }
+
+    /// Stored schedules in insertion order.
+    pub fn schedules(&self) -> Vec<StoredSchedule> {
+        self.state.lock().unwrap().schedules.clone()
+    }
+}
+
+impl ConsolidationRepository for InMemoryRepository {
+    async fn store_invoices(&self, invoices: &[InvoicingRow]) -> Result<StoredInvoices, Error> {
+        let mut state = self.state.lock().unwrap();
+        let mut stored = StoredInvoices::default();
+
+        for invoice in invoices {
+            let key = (
+                invoice.teacher_name.clone(),
+                invoice.shift.clone(),
+                invoice.activity_start,
+                invoice.activity_end,
+            );
+
+            match state.invoice_positions.get(&key) {
+                Some(&position) => {
+                    state.invoices[position].eligible = invoice.eligible;
+                    stored.updated += 1;
+                }
+                None => {
+                    let position = state.invoices.len();
+                    state.invoice_positions.insert(key, position);
+                    state.invoices.push(invoice.clone());
+                    stored.inserted += 1;
+                }
+            }
+        }
+
+        Ok(stored)
+    }
+
+    async fn store_teachers(&self, schedules: &[ScheduleRecord<'_>]) -> Result<usize, Error> {
+        let mut state = self.state.lock().unwrap();
+        let mut inserted = 0;
+
+        for schedule in schedules {
+            let name = &schedule.row.teacher_name;
+
+            if state.teacher_names.insert(name.clone()) {
+                state.teachers.push(name.clone());
+                inserted += 1;
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    async fn store_schedules(&self, schedules: &[ScheduleRecord<'_>]) -> Result<usize, Error> {
+        let mut state = self.state.lock().unwrap();
+        let mut inserted = 0;
+
+        for schedule in schedules {
+            let key = (
+                schedule.row.teacher_name.clone(),
+                schedule.row.shift.clone(),
+                schedule.row.shift_type.clone(),
+                schedule.row.shift_group.clone(),
+                schedule.start_date,
+                schedule.end_date,
+            );
+
+            if state.schedule_keys.insert(key) {
+                state.schedules.push(StoredSchedule {
+                    row: schedule.row.clone(),
+                    start_date: schedule.start_date,
+                    end_date: schedule.end_date,
+                });
+                inserted += 1;
+            }
+        }
+
+        Ok(inserted)
+    }
+}
@@ -0,0 +1,45 @@
+use std::{ops::ControlFlow, path::Path};
+
+use chrono::NaiveDate;
+use chrono_tz::{Africa::Johannesburg, UTC};
+
+use crate::{
+    column_mappings::ColumnMappings,
+    datetime_profiles::{DateTimeProfile, DateTimeProfileSelection},
+    import::{ImportOptions, IngestionDiagnostics},
+    invoicing::{open_invoicing_source, stream_invoicing_rows, InvoicingRow},
+    pipeline::find_upload_file,
+    timezones::DstPolicy,
+};
+
+/// UTC sources shown in Johannesburg, matching the production defaults for Dialogue exports.
+pub(crate) fn import_options(profile_selection: DateTimeProfileSelection) -> ImportOptions {
+    ImportOptions {
+        profile_selection,
+        profiles: DateTimeProfile::builtin(),
+        source_timezone: UTC,
+        app_timezone: Johannesburg,
+        dst_policy: DstPolicy::Earliest,
+        column_mappings: ColumnMappings::defaults(),
+    }
+}
+
+pub(crate) fn load_invoicing(
+    dir: &Path,
+    process_calendar: NaiveDate,
+    options: &ImportOptions,
+    diagnostics: &mut IngestionDiagnostics,
+) -> Vec<InvoicingRow> {
+    let file_path = find_upload_file(dir.to_str().unwrap(), "invoicing-report").unwrap();
+    let source = open_invoicing_source(&file_path, &options.column_mappings, diagnostics)
+        .expect("invoicing source");
+    let mut rows = Vec::new();
+
+    stream_invoicing_rows(&source, process_calendar, options, diagnostics, |row| {
+        rows.push(row);
+        ControlFlow::Continue(())
+    })
+    .expect("invoicing rows");
+
+    rows
+}
@@ -0,0 +1,241 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::Error;
+use chrono::{DateTime, Duration, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Resolves the optional `tz` query parameter, falling back to the configured app timezone.
+pub fn resolve_timezone(requested: Option<&str>, default: Tz) -> Result<Tz, Error> {
+    match requested.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(name) => name
+            .parse::<Tz>()
+            .map_err(|error| anyhow::anyhow!("Unknown timezone {:?}: {}", name, error)),
+        None => Ok(default),
+    }
+}
+
+/// What to do with a local time that a DST transition makes ambiguous (fall back) or
+/// nonexistent (spring forward).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DstPolicy {
+    /// Use the earlier of the two possible instants.
+    Earliest,
+    /// Use the later of the two possible instants.
+    Latest,
+    /// Skip the row.
+    Reject,
+    /// Use the instant closest to a neighbouring, unambiguous timestamp; earliest without one.
+    Infer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalTimeIssue {
+    Ambiguous,
+    Nonexistent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedLocalTime {
+    pub datetime: DateTime<Tz>,
+    pub issue: Option<LocalTimeIssue>,
+}
+
+impl FromStr for DstPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<DstPolicy, Error> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "earliest" => Ok(DstPolicy::Earliest),
+            "latest" => Ok(DstPolicy::Latest),
+            "reject" => Ok(DstPolicy::Reject),
+            "infer" => Ok(DstPolicy::Infer),
+            other => Err(anyhow::anyhow!(
+                "Unknown DST policy {:?}. Expected one of: earliest, latest, reject, infer",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DstPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DstPolicy::Earliest => "earliest",
+            DstPolicy::Latest => "latest",
+            DstPolicy::Reject => "reject",
+            DstPolicy::Infer => "infer",
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for LocalTimeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalTimeIssue::Ambiguous => f.write_str("ambiguous"),
+            LocalTimeIssue::Nonexistent => f.write_str("nonexistent"),
+        }
+    }
+}
+
+/// Places a naive local time in `timezone`, applying `policy` when it falls inside a DST
+/// transition. `neighbour` is the closest unambiguous instant from the same file and is only
+/// used by [DstPolicy::Infer]. Returns the issue as an error when the policy rejects the value.
+pub fn resolve_local_datetime(
+    naive: NaiveDateTime,
+    timezone: Tz,
+    policy: DstPolicy,
+    neighbour: Option<DateTime<Utc>>,
+) -> Result<ResolvedLocalTime, LocalTimeIssue> {
+    let (earliest, latest, issue) = match timezone.from_local_datetime(&naive) {
+        chrono::LocalResult::Single(datetime) => {
+            return Ok(ResolvedLocalTime {
+                datetime,
+                issue: None,
+            })
+        }
+        chrono::LocalResult::Ambiguous(earliest, latest) => {
+            (earliest, latest, LocalTimeIssue::Ambiguous)
+        }
+        chrono::LocalResult::None => {
+            // The wall clock skipped this time, so read it with the offsets in force on either
+            // side of the gap.
+            let before = timezone
+                .offset_from_utc_datetime(&(naive - Duration::days(1)))
+                .fix();
+            let after = timezone
+                .offset_from_utc_datetime(&(naive + Duration::days(1)))
+                .fix();
+            let first = timezone.from_utc_datetime(&(naive - before));
+            let second = timezone.from_utc_datetime(&(naive - after));
+
+            (
+                first.min(second),
+                first.max(second),
+                LocalTimeIssue::Nonexistent,
+            )
+        }
+    };
+
+    let datetime = match policy {
+        DstPolicy::Earliest => earliest,
+        DstPolicy::Latest => latest,
+        DstPolicy::Reject => return Err(issue),
+        DstPolicy::Infer => match neighbour {
+            Some(neighbour) => {
+                let distance = |candidate: &DateTime<Tz>| {
+                    (candidate.with_timezone(&Utc) - neighbour)
+                        .num_seconds()
+                        .abs()
+                };
+
+                if distance(&latest) < distance(&earliest) {
+                    latest
+                } else {
+                    earliest
+                }
+            }
+            None => earliest,
+        },
+    };
+
+    Ok(ResolvedLocalTime {
+        datetime,
+        issue: Some(issue),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use chrono_tz::{Africa::Johannesburg, America::New_York};
+
+    use super::{resolve_local_datetime, DstPolicy, LocalTimeIssue};
+
+    fn naive(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    /// Resolves a New York wall-clock time, where the DST transitions are, and reads the result
+    /// on Johannesburg's clock, which has none.
+    fn new_york_to_johannesburg(
+        value: NaiveDateTime,
+        policy: DstPolicy,
+        neighbour: Option<NaiveDateTime>,
+    ) -> Result<NaiveDateTime, LocalTimeIssue> {
+        resolve_local_datetime(
+            value,
+            New_York,
+            policy,
+            neighbour.map(|neighbour| Utc.from_utc_datetime(&neighbour)),
+        )
+        .map(|resolved| resolved.datetime.with_timezone(&Johannesburg).naive_local())
+    }
+
+    #[test]
+    fn us_spring_forward_gap_follows_policy() {
+        // 02:30 on 8 March 2026 does not exist in New York: 06:30Z (EDT) or 07:30Z (EST).
+        let skipped = naive(2026, 3, 8, 2, 30);
+
+        assert_eq!(
+            new_york_to_johannesburg(skipped, DstPolicy::Earliest, None),
+            Ok(naive(2026, 3, 8, 8, 30))
+        );
+        assert_eq!(
+            new_york_to_johannesburg(skipped, DstPolicy::Latest, None),
+            Ok(naive(2026, 3, 8, 9, 30))
+        );
+        assert_eq!(
+            new_york_to_johannesburg(skipped, DstPolicy::Reject, None),
+            Err(LocalTimeIssue::Nonexistent)
+        );
+        assert_eq!(
+            new_york_to_johannesburg(skipped, DstPolicy::Infer, Some(naive(2026, 3, 8, 7, 15))),
+            Ok(naive(2026, 3, 8, 9, 30))
+        );
+    }
+
+    #[test]
+    fn us_fall_back_overlap_follows_policy() {
+        // 01:30 on 1 November 2026 happens twice in New York: 05:30Z (EDT) and 06:30Z (EST).
+        let repeated = naive(2026, 11, 1, 1, 30);
+
+        assert_eq!(
+            new_york_to_johannesburg(repeated, DstPolicy::Earliest, None),
+            Ok(naive(2026, 11, 1, 7, 30))
+        );
+        assert_eq!(
+            new_york_to_johannesburg(repeated, DstPolicy::Latest, None),
+            Ok(naive(2026, 11, 1, 8, 30))
+        );
+        assert_eq!(
+            new_york_to_johannesburg(repeated, DstPolicy::Reject, None),
+            Err(LocalTimeIssue::Ambiguous)
+        );
+        assert_eq!(
+            new_york_to_johannesburg(repeated, DstPolicy::Infer, Some(naive(2026, 11, 1, 6, 15))),
+            Ok(naive(2026, 11, 1, 8, 30))
+        );
+        assert_eq!(
+            new_york_to_johannesburg(repeated, DstPolicy::Infer, Some(naive(2026, 11, 1, 5, 0))),
+            Ok(naive(2026, 11, 1, 7, 30))
+        );
+    }
+
+    #[test]
+    fn unaffected_times_are_not_flagged() {
+        let resolved =
+            resolve_local_datetime(naive(2026, 7, 1, 9, 0), New_York, DstPolicy::Reject, None)
+                .expect("single");
+
+        assert_eq!(resolved.issue, None);
+        assert_eq!(
+            resolved.datetime.with_timezone(&Johannesburg).naive_local(),
+            naive(2026, 7, 1, 15, 0)
+        );
+    }
+}
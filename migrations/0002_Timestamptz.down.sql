@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE invoices
+    ALTER COLUMN activity_start TYPE TIMESTAMP USING activity_start AT TIME ZONE 'Africa/Johannesburg',
+    ALTER COLUMN activity_end TYPE TIMESTAMP USING activity_end AT TIME ZONE 'Africa/Johannesburg';
+
+ALTER TABLE schedules
+    ALTER COLUMN start_date TYPE TIMESTAMP USING start_date AT TIME ZONE 'Africa/Johannesburg',
+    ALTER COLUMN end_date TYPE TIMESTAMP USING end_date AT TIME ZONE 'Africa/Johannesburg';
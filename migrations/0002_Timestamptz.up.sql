@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- Existing values were written as naive Africa/Johannesburg (APP_TIMEZONE default) local time.
+ALTER TABLE schedules
+    ALTER COLUMN start_date TYPE TIMESTAMPTZ USING start_date AT TIME ZONE 'Africa/Johannesburg',
+    ALTER COLUMN end_date TYPE TIMESTAMPTZ USING end_date AT TIME ZONE 'Africa/Johannesburg';
+
+ALTER TABLE invoices
+    ALTER COLUMN activity_start TYPE TIMESTAMPTZ USING activity_start AT TIME ZONE 'Africa/Johannesburg',
+    ALTER COLUMN activity_end TYPE TIMESTAMPTZ USING activity_end AT TIME ZONE 'Africa/Johannesburg';
@@ -0,0 +1,52 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS column_mappings (
+        id SERIAL PRIMARY KEY NOT NULL,
+        file_type VARCHAR(32) NOT NULL,
+        field VARCHAR(64) NOT NULL,
+        alias VARCHAR(255) NOT NULL,
+        position INT NOT NULL DEFAULT 0,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE UNIQUE INDEX IF NOT EXISTS column_mappings_alias_idx
+    ON column_mappings (file_type, field, LOWER(alias));
+
+INSERT INTO
+    column_mappings (file_type, field, alias, position)
+VALUES
+        ('dialogue', 'start', 'Start', 0),
+        ('dialogue', 'start', 'Activity Start', 10),
+        ('dialogue', 'finish', 'Finish', 0),
+        ('dialogue', 'finish', 'End', 10),
+        ('dialogue', 'finish', 'Activity End', 20),
+        ('dialogue', 'shift', 'Shift: Shift Number', 0),
+        ('dialogue', 'shift', 'Shift Number', 10),
+        ('dialogue', 'shift', 'Shift', 20),
+        ('dialogue', 'shift_group', 'Resource: Shift Group', 0),
+        ('dialogue', 'shift_group', 'Shift Group', 10),
+        ('dialogue', 'teacher_name', 'Resource: Resource Name', 0),
+        ('dialogue', 'teacher_name', 'Resource Name', 10),
+        ('dialogue', 'teacher_name', 'Teacher Name', 20),
+        ('invoicing', 'teacher_name', 'Resource: Resource Name', 0),
+        ('invoicing', 'teacher_name', 'Resource Name', 10),
+        ('invoicing', 'teacher_name', 'Teacher_Name', 20),
+        ('invoicing', 'teacher_name', 'Teacher Name', 30),
+        ('invoicing', 'eligible', 'Eligible_Status', 0),
+        ('invoicing', 'eligible', 'Eligible Status', 10),
+        ('invoicing', 'eligible', 'Eligible', 20),
+        ('invoicing', 'activity_start', 'Activity_Start_Time', 0),
+        ('invoicing', 'activity_start', 'Activity Start Time', 10),
+        ('invoicing', 'activity_start', 'Activity Start', 20),
+        ('invoicing', 'activity_start', 'Start', 30),
+        ('invoicing', 'activity_end', 'Activity_End_Time', 0),
+        ('invoicing', 'activity_end', 'Activity End Time', 10),
+        ('invoicing', 'activity_end', 'Activity End', 20),
+        ('invoicing', 'activity_end', 'Finish', 30),
+        ('invoicing', 'shift', 'shift_name_tsm', 0),
+        ('invoicing', 'shift', 'Shift_Name', 10),
+        ('invoicing', 'shift', 'Shift Name', 20),
+        ('invoicing', 'shift', 'Shift: Shift Number', 30),
+        ('invoicing', 'shift', 'Shift Number', 40),
+        ('invoicing', 'shift', 'Shift', 50)
+ON CONFLICT DO NOTHING;
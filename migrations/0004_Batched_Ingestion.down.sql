@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS schedules_teacher_start_idx;
+
+DROP INDEX IF EXISTS teachers_name_idx;
+
+DROP INDEX IF EXISTS invoices_natural_key_idx;
@@ -0,0 +1,34 @@
+-- Add up migration script here
+-- Invoices are upserted in batches on their natural key, so earlier duplicates must go first.
+DELETE FROM invoices
+WHERE id IN (
+    SELECT id
+    FROM (
+        SELECT
+            id,
+            ROW_NUMBER() OVER (
+                PARTITION BY teacher_name, shift, activity_start, activity_end
+                ORDER BY id DESC
+            ) AS duplicate_number
+        FROM invoices
+    ) AS ranked
+    WHERE duplicate_number > 1
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS invoices_natural_key_idx
+    ON invoices (teacher_name, shift, activity_start, activity_end);
+
+-- Teachers are inserted with ON CONFLICT (name) so concurrent runs share one row per name.
+-- Duplicates stored before then are merged by `sergio-ar-api merge-teachers`, which logs what
+-- it changes, rather than deleted here.
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM teachers GROUP BY name HAVING COUNT(*) > 1) THEN
+        RAISE EXCEPTION 'teachers has duplicate names. Run `sergio-ar-api merge-teachers` and migrate again.';
+    END IF;
+END
+$$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS teachers_name_idx ON teachers (name);
+
+CREATE INDEX IF NOT EXISTS schedules_teacher_start_idx ON schedules (teacher_id, start_date);
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS consolidation_runs (
+        id SERIAL PRIMARY KEY NOT NULL,
+        process_date DATE NOT NULL,
+        status VARCHAR(16) NOT NULL DEFAULT 'running',
+        error TEXT,
+        started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        finished_at TIMESTAMPTZ
+    );
+
+CREATE INDEX IF NOT EXISTS consolidation_runs_status_idx ON consolidation_runs (status);
@@ -0,0 +1,28 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS consolidation_jobs_running_date_idx;
+
+DROP INDEX IF EXISTS consolidation_jobs_status_idx;
+
+DROP INDEX IF EXISTS consolidation_jobs_queue_idx;
+
+DELETE FROM consolidation_jobs WHERE started_at IS NULL;
+
+UPDATE consolidation_jobs SET status = 'failed' WHERE status IN ('queued', 'cancelled');
+
+ALTER TABLE consolidation_jobs
+    DROP COLUMN datetime_profile,
+    DROP COLUMN upload_directory,
+    DROP COLUMN attempts,
+    DROP COLUMN max_attempts,
+    DROP COLUMN cancel_requested,
+    DROP COLUMN run_at,
+    DROP COLUMN created_at,
+    ALTER COLUMN status SET DEFAULT 'running',
+    ALTER COLUMN started_at SET DEFAULT NOW(),
+    ALTER COLUMN started_at SET NOT NULL;
+
+ALTER SEQUENCE consolidation_jobs_id_seq RENAME TO consolidation_runs_id_seq;
+
+ALTER TABLE consolidation_jobs RENAME TO consolidation_runs;
+
+CREATE INDEX IF NOT EXISTS consolidation_runs_status_idx ON consolidation_runs (status);
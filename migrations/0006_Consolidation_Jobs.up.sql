@@ -0,0 +1,43 @@
+-- Add up migration script here
+-- Consolidation runs become jobs that workers claim from a queue.
+ALTER TABLE consolidation_runs RENAME TO consolidation_jobs;
+
+ALTER SEQUENCE consolidation_runs_id_seq RENAME TO consolidation_jobs_id_seq;
+
+DROP INDEX IF EXISTS consolidation_runs_status_idx;
+
+ALTER TABLE consolidation_jobs
+    -- Profile names come from the config and have no length limit.
+    ADD COLUMN datetime_profile TEXT,
+    -- Each upload gets its own directory. Runs from before this keep NULL and read temp/<date>.
+    ADD COLUMN upload_directory TEXT,
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN max_attempts INT NOT NULL DEFAULT 3,
+    ADD COLUMN cancel_requested BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ALTER COLUMN status SET DEFAULT 'queued',
+    ALTER COLUMN started_at DROP NOT NULL,
+    ALTER COLUMN started_at DROP DEFAULT;
+
+UPDATE consolidation_jobs
+SET created_at = started_at, run_at = started_at, attempts = 1;
+
+CREATE INDEX IF NOT EXISTS consolidation_jobs_queue_idx
+    ON consolidation_jobs (run_at, id)
+    WHERE status = 'queued';
+
+CREATE INDEX IF NOT EXISTS consolidation_jobs_status_idx ON consolidation_jobs (status);
+
+-- Only one job per process date runs at a time. Runs a stopped server left running are
+-- requeued at startup anyway, so all but one per date can go back to the queue now.
+UPDATE consolidation_jobs
+SET status = 'queued', run_at = NOW()
+WHERE status = 'running'
+AND id NOT IN (
+    SELECT MIN(id) FROM consolidation_jobs WHERE status = 'running' GROUP BY process_date
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS consolidation_jobs_running_date_idx
+    ON consolidation_jobs (process_date)
+    WHERE status = 'running';
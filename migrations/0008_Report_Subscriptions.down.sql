@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS report_deliveries;
+
+DROP TABLE IF EXISTS report_subscriptions;
@@ -0,0 +1,31 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS report_subscriptions (
+        id SERIAL PRIMARY KEY NOT NULL,
+        recipient VARCHAR(320) NOT NULL,
+        -- Empty for every shift group with schedules in the period.
+        shift_groups TEXT[] NOT NULL DEFAULT '{}',
+        cadence VARCHAR(16) NOT NULL,
+        formats TEXT[] NOT NULL DEFAULT '{csv}',
+        -- The last day of the most recent period sent.
+        last_period_end DATE,
+        next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE TABLE
+    IF NOT EXISTS report_deliveries (
+        id SERIAL PRIMARY KEY NOT NULL,
+        subscription_id INT NOT NULL REFERENCES report_subscriptions (id) ON DELETE CASCADE,
+        recipient VARCHAR(320) NOT NULL,
+        start_date DATE NOT NULL,
+        end_date DATE NOT NULL,
+        shift_groups TEXT[] NOT NULL,
+        attachments TEXT[] NOT NULL,
+        status VARCHAR(16) NOT NULL,
+        error TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX IF NOT EXISTS report_deliveries_subscription_idx
+    ON report_deliveries (subscription_id, id);
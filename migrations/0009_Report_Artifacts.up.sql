@@ -0,0 +1,35 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS report_schedules (
+        id SERIAL PRIMARY KEY NOT NULL,
+        name VARCHAR(255) NOT NULL,
+        -- Six fields, seconds first, evaluated in APP_TIMEZONE.
+        cron VARCHAR(255) NOT NULL,
+        period VARCHAR(16) NOT NULL,
+        -- Empty for every shift group with schedules in the period.
+        shift_groups TEXT[] NOT NULL DEFAULT '{}',
+        formats TEXT[] NOT NULL DEFAULT '{csv}',
+        last_run_at TIMESTAMPTZ,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE TABLE
+    IF NOT EXISTS report_artifacts (
+        id SERIAL PRIMARY KEY NOT NULL,
+        schedule_id INT REFERENCES report_schedules (id) ON DELETE SET NULL,
+        shift_group TEXT NOT NULL,
+        start_date DATE NOT NULL,
+        end_date DATE NOT NULL,
+        format VARCHAR(16) NOT NULL,
+        filename TEXT NOT NULL,
+        content_type VARCHAR(255) NOT NULL,
+        content BYTEA NOT NULL,
+        size_bytes INT NOT NULL,
+        sha256 CHAR(64) NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX IF NOT EXISTS report_artifacts_shift_group_idx
+    ON report_artifacts (shift_group, start_date);
+
+CREATE INDEX IF NOT EXISTS report_artifacts_schedule_idx ON report_artifacts (schedule_id, id);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS schedules_shift_group_start_idx;
+
+DROP TABLE IF EXISTS shift_groups;
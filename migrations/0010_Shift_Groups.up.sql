@@ -0,0 +1,29 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS shift_groups (
+        id SERIAL PRIMARY KEY NOT NULL,
+        -- Matches schedules.shift_group.
+        name VARCHAR(255) NOT NULL UNIQUE,
+        display_name VARCHAR(255),
+        region VARCHAR(255),
+        manager VARCHAR(255),
+        active BOOLEAN NOT NULL DEFAULT TRUE,
+        -- IANA name. NULL for APP_TIMEZONE.
+        timezone VARCHAR(64),
+        -- The latest upload date that had schedules for the group. Groups added from existing
+        -- schedules get one at startup, once APP_TIMEZONE is known.
+        last_processed_date DATE,
+        -- Kept up to date by ingestion, so listing shift groups does not count schedules each time.
+        schedule_count BIGINT NOT NULL DEFAULT 0,
+        teacher_count BIGINT NOT NULL DEFAULT 0,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    );
+
+INSERT INTO shift_groups (name, schedule_count, teacher_count)
+SELECT shift_group, COUNT(*), COUNT(DISTINCT teacher_id)
+FROM schedules
+GROUP BY shift_group
+ON CONFLICT (name) DO NOTHING;
+
+CREATE INDEX IF NOT EXISTS schedules_shift_group_start_idx ON schedules (shift_group, start_date);
@@ -0,0 +1,625 @@
+use std::{collections::HashMap, io::Write};
+
+use anyhow::Error;
+use chrono::NaiveDate;
+use consolidation::{
+    column_mappings::ColumnMappings, datetime_profiles::DateTimeProfileSelection,
+    preview_consolidation, timezones::resolve_timezone, ConsolidationInputs, ConsolidationPreview,
+};
+use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+
+use crate::{
+    config::Config,
+    date_range::DateRange,
+    routes::{
+        consolidator::upload_and_process::consolidate_into_database,
+        efficiency::generate_consolidated_report::build_consolidated_report,
+    },
+    utils::{shift_groups::shift_group_timezone, teachers::merge_duplicate_teachers},
+    MIGRATOR,
+};
+
+pub const USAGE: &str = "\
+Usage: sergio-ar-api [COMMAND]
+
+Commands:
+  serve        Run the HTTP API (default)
+  consolidate  Run the consolidation pipeline on local files
+  report       Print the consolidated report CSV for a shift group
+  migrate      Apply pending database migrations
+  merge-teachers
+               Merge teachers stored more than once under one name
+  help         Show this message
+
+consolidate:
+  --date <YYYY-MM-DD>          Process date
+  --dialogue-1 <FILE>          First Dialogue snapshot (.csv or .xlsx)
+  --dialogue-2 <FILE>          Second Dialogue snapshot (.csv or .xlsx)
+  --invoicing <FILE>           Invoicing report (.csv or .xlsx)
+  --datetime-profile <NAME>    auto, flexible or a configured profile such as us (default: DATETIME_PROFILE)
+  --format <table|csv|json>    Output format for the classified rows (default: table)
+  --write                      Store the results in DATABASE_URL instead of printing them
+
+  Without --write no database is needed and the default column aliases are used.
+
+report:
+  --start-date <YYYY-MM-DD>    First day of the report
+  --end-date <YYYY-MM-DD>      Last day of the report
+  --shift-group <NAME>         Shift group to report on
+  --tz <ZONE>                  IANA timezone (default: the group's, then APP_TIMEZONE)
+  --output <FILE>              Write the CSV to a file instead of stdout
+
+merge-teachers:
+  --apply                      Commit the merge. Without it the changes are only listed.
+
+  Needed once before migrating a database whose teachers table has duplicate names.
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    Consolidate(ConsolidateArgs),
+    Report(ReportArgs),
+    Migrate,
+    MergeTeachers { apply: bool },
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsolidateArgs {
+    pub date: String,
+    pub dialogue_1: String,
+    pub dialogue_2: String,
+    pub invoicing: String,
+    pub datetime_profile: Option<String>,
+    pub format: OutputFormat,
+    pub write: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportArgs {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub shift_group: String,
+    pub tz: Option<String>,
+    pub output: Option<String>,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<OutputFormat, Error> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(anyhow::anyhow!(
+                "Unknown format {:?}. Expected one of: table, csv, json",
+                value
+            )),
+        }
+    }
+}
+
+/// Parses the arguments after the program name. No arguments means `serve`.
+pub fn parse(args: &[String]) -> Result<Command, Error> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::Serve);
+    };
+
+    match command.as_str() {
+        "serve" => {
+            parse_flags(rest, &[], &[])?;
+            Ok(Command::Serve)
+        }
+        "migrate" => {
+            parse_flags(rest, &[], &[])?;
+            Ok(Command::Migrate)
+        }
+        "merge-teachers" => {
+            let flags = parse_flags(rest, &[], &["apply"])?;
+            Ok(Command::MergeTeachers {
+                apply: flags.contains_key("apply"),
+            })
+        }
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "consolidate" => {
+            let mut flags = parse_flags(
+                rest,
+                &[
+                    "date",
+                    "dialogue-1",
+                    "dialogue-2",
+                    "invoicing",
+                    "datetime-profile",
+                    "format",
+                ],
+                &["write"],
+            )?;
+
+            Ok(Command::Consolidate(ConsolidateArgs {
+                date: required(&mut flags, "date")?,
+                dialogue_1: required(&mut flags, "dialogue-1")?,
+                dialogue_2: required(&mut flags, "dialogue-2")?,
+                invoicing: required(&mut flags, "invoicing")?,
+                datetime_profile: flags.remove("datetime-profile"),
+                format: match flags.remove("format") {
+                    Some(format) => format.parse()?,
+                    None => OutputFormat::Table,
+                },
+                write: flags.remove("write").is_some(),
+            }))
+        }
+        "report" => {
+            let mut flags = parse_flags(
+                rest,
+                &["start-date", "end-date", "shift-group", "tz", "output"],
+                &[],
+            )?;
+
+            Ok(Command::Report(ReportArgs {
+                start_date: required_date(&mut flags, "start-date")?,
+                end_date: required_date(&mut flags, "end-date")?,
+                shift_group: required(&mut flags, "shift-group")?,
+                tz: flags.remove("tz"),
+                output: flags.remove("output"),
+            }))
+        }
+        _ => Err(anyhow::anyhow!("Unknown command {:?}", command)),
+    }
+}
+
+/// Accepts `--name value` and `--name=value` for `values`, and bare `--name` for `switches`.
+fn parse_flags(
+    args: &[String],
+    values: &[&str],
+    switches: &[&str],
+) -> Result<HashMap<String, String>, Error> {
+    let mut flags = HashMap::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            return Err(anyhow::anyhow!("Unexpected argument {:?}", arg));
+        };
+
+        let (name, inline_value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+
+        let value = if values.contains(&name) {
+            match inline_value.or_else(|| args.next().cloned()) {
+                Some(value) => value,
+                None => return Err(anyhow::anyhow!("--{} needs a value", name)),
+            }
+        } else if switches.contains(&name) && inline_value.is_none() {
+            "true".to_string()
+        } else {
+            return Err(anyhow::anyhow!("Unknown option --{}", name));
+        };
+
+        if flags.insert(name.to_string(), value).is_some() {
+            return Err(anyhow::anyhow!("--{} was given more than once", name));
+        }
+    }
+
+    Ok(flags)
+}
+
+fn required(flags: &mut HashMap<String, String>, name: &str) -> Result<String, Error> {
+    flags
+        .remove(name)
+        .ok_or_else(|| anyhow::anyhow!("Missing required option --{}", name))
+}
+
+fn required_date(flags: &mut HashMap<String, String>, name: &str) -> Result<NaiveDate, Error> {
+    let value = required(flags, name)?;
+
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+        .map_err(|error| anyhow::anyhow!("Invalid --{} {:?}: {}", name, value, error))
+}
+
+/// Logs go to stderr so stdout only carries command output.
+pub fn init_logging() {
+    tracing_subscriber::fmt()
+        .compact()
+        .with_writer(std::io::stderr)
+        .with_env_filter(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG")
+                .unwrap_or_else(|_| "sergio_ar_api=info,consolidation=info".into()),
+        ))
+        .init();
+}
+
+pub async fn run(command: Command) -> Result<(), Error> {
+    match command {
+        Command::Serve => Err(anyhow::anyhow!("serve is handled by main")),
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Command::Migrate => {
+            let db = connect(&Config::without_database()).await?;
+
+            MIGRATOR.run(&db).await?;
+
+            tracing::info!("✅ Database migration successful!");
+
+            Ok(())
+        }
+        Command::MergeTeachers { apply } => merge_teachers(apply).await,
+        Command::Consolidate(args) => consolidate(args).await,
+        Command::Report(args) => report(args).await,
+    }
+}
+
+async fn connect(config: &Config) -> Result<Pool<Postgres>, Error> {
+    if config.database_url.is_empty() {
+        return Err(anyhow::anyhow!(
+            "DATABASE_URL must be set in the environment, .env or the config file"
+        ));
+    }
+
+    let pool = PgPoolOptions::new()
+        .max_connections(4)
+        .connect(&config.database_url)
+        .await?;
+
+    tracing::info!("✅ Connection to the database is successful!");
+
+    Ok(pool)
+}
+
+async fn consolidate(args: ConsolidateArgs) -> Result<(), Error> {
+    let config = Config::without_database();
+
+    let profile_selection = match &args.datetime_profile {
+        Some(name) => DateTimeProfileSelection::resolve(name, &config.datetime_profiles)?,
+        None => config.default_datetime_profile.clone(),
+    };
+
+    let inputs = ConsolidationInputs {
+        dialogue_1: args.dialogue_1,
+        dialogue_2: args.dialogue_2,
+        invoicing: args.invoicing,
+    };
+
+    if args.write {
+        let db = connect(&config).await?;
+        let summary = consolidate_into_database(
+            &db,
+            &config,
+            &inputs,
+            &args.date,
+            profile_selection,
+            Default::default(),
+        )
+        .await?;
+
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+
+        return Ok(());
+    }
+
+    let preview = preview_consolidation(
+        &config.consolidation_settings(),
+        &inputs,
+        &args.date,
+        profile_selection,
+        ColumnMappings::defaults(),
+    )
+    .await?;
+
+    let stdout = std::io::stdout();
+    render_preview(&preview, args.format, &mut stdout.lock())?;
+
+    if args.format != OutputFormat::Json {
+        eprintln!(
+            "{} schedules, {} invoicing rows, {} diagnostics",
+            preview.schedules.len(),
+            preview.invoicing_rows,
+            preview.diagnostics.total()
+        );
+    }
+
+    Ok(())
+}
+
+async fn merge_teachers(apply: bool) -> Result<(), Error> {
+    let db = connect(&Config::without_database()).await?;
+    let merge = merge_duplicate_teachers(&db, apply).await?;
+
+    for teacher in &merge.teachers {
+        println!(
+            "teacher {:?}: keeping id {}, merging ids {:?}",
+            teacher.name, teacher.kept_id, teacher.merged_ids
+        );
+    }
+
+    for schedule in &merge.removed_schedules {
+        println!(
+            "schedule {} (teacher {}, {}, {}, {}, {} - {}): duplicate of schedule {}",
+            schedule.id,
+            schedule.teacher_id,
+            schedule.shift_group,
+            schedule.shift,
+            schedule.shift_type,
+            schedule.start_date,
+            schedule.end_date,
+            schedule.kept_id
+        );
+    }
+
+    if merge.teachers.is_empty() {
+        tracing::info!("✅ No teacher is stored more than once.");
+    } else if apply {
+        tracing::info!(
+            "✅ Merged {} teacher name(s) and removed {} duplicate schedule(s).",
+            merge.teachers.len(),
+            merge.removed_schedules.len()
+        );
+    } else {
+        tracing::warn!(
+            "❕ Nothing was changed. Run again with --apply to merge {} teacher name(s) and remove {} duplicate schedule(s).",
+            merge.teachers.len(),
+            merge.removed_schedules.len()
+        );
+    }
+
+    Ok(())
+}
+
+async fn report(args: ReportArgs) -> Result<(), Error> {
+    let config = Config::without_database();
+    let db = connect(&config).await?;
+    let group_timezone = shift_group_timezone(&db, &args.shift_group).await?;
+    let timezone = resolve_timezone(
+        args.tz.as_deref(),
+        group_timezone.unwrap_or(config.app_timezone),
+    )?;
+
+    let range = DateRange::from_dates(args.start_date, args.end_date, timezone);
+    let report = build_consolidated_report(&db, &range, &args.shift_group)
+        .await?
+        .to_csv()?;
+
+    match args.output {
+        Some(path) => {
+            std::fs::write(&path, report)?;
+            tracing::info!("✅ Report written to {}", path);
+        }
+        None => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+const TABLE_HEADERS: [&str; 6] = [
+    "Shift Group",
+    "Shift",
+    "Shift Type",
+    "Teacher",
+    "Start",
+    "End",
+];
+
+/// Writes the classified rows. JSON also carries the invoicing count and diagnostics.
+pub fn render_preview<W: Write>(
+    preview: &ConsolidationPreview,
+    format: OutputFormat,
+    out: &mut W,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, preview)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+
+            for row in &preview.schedules {
+                writer.serialize(row)?;
+            }
+
+            writer.flush()?;
+        }
+        OutputFormat::Table => {
+            let rows = preview
+                .schedules
+                .iter()
+                .map(|row| {
+                    [
+                        row.shift_group.as_str(),
+                        row.shift.as_str(),
+                        row.shift_type.as_str(),
+                        row.teacher_name.as_str(),
+                        row.start_date.as_str(),
+                        row.end_date.as_str(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+
+            let mut widths = TABLE_HEADERS.map(|header| header.chars().count());
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+
+            write_table_row(out, &TABLE_HEADERS, &widths)?;
+            write_table_row(out, &widths.map(|width| "-".repeat(width)), &widths)?;
+
+            for row in &rows {
+                write_table_row(out, row, &widths)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_table_row<W: Write, S: AsRef<str>>(
+    out: &mut W,
+    cells: &[S],
+    widths: &[usize],
+) -> Result<(), Error> {
+    let line = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell.as_ref(), width = width))
+        .collect::<Vec<_>>()
+        .join("  ");
+
+    writeln!(out, "{}", line.trim_end())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use consolidation::{ConsolidationPreview, DialogueConsolidatedRow};
+
+    use super::{parse, render_preview, Command, ConsolidateArgs, OutputFormat, ReportArgs};
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn row(shift: &str, shift_type: &str, teacher_name: &str) -> DialogueConsolidatedRow {
+        DialogueConsolidatedRow {
+            shift_group: "JEN 4 - PM".to_string(),
+            shift: shift.to_string(),
+            shift_type: shift_type.to_string(),
+            teacher_name: teacher_name.to_string(),
+            start_date: "2026-05-01 11:00:00".to_string(),
+            end_date: "2026-05-01 13:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_consolidate_flags_in_both_styles() {
+        let command = parse(&args(&[
+            "consolidate",
+            "--date=2026-05-01",
+            "--dialogue-1",
+            "a.csv",
+            "--dialogue-2",
+            "b.xlsx",
+            "--invoicing",
+            "inv.csv",
+            "--format=json",
+            "--write",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            command,
+            Command::Consolidate(ConsolidateArgs {
+                date: "2026-05-01".to_string(),
+                dialogue_1: "a.csv".to_string(),
+                dialogue_2: "b.xlsx".to_string(),
+                invoicing: "inv.csv".to_string(),
+                datetime_profile: None,
+                format: OutputFormat::Json,
+                write: true,
+            })
+        );
+        assert_eq!(parse(&[]).unwrap(), Command::Serve);
+        assert_eq!(
+            parse(&args(&["merge-teachers"])).unwrap(),
+            Command::MergeTeachers { apply: false }
+        );
+        assert_eq!(
+            parse(&args(&["merge-teachers", "--apply"])).unwrap(),
+            Command::MergeTeachers { apply: true }
+        );
+    }
+
+    #[test]
+    fn parses_report_dates() {
+        let command = parse(&args(&[
+            "report",
+            "--start-date",
+            "2026-05-01",
+            "--end-date",
+            "2026-05-07",
+            "--shift-group",
+            "JEN 4 - PM",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            command,
+            Command::Report(ReportArgs {
+                start_date: NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(),
+                end_date: NaiveDate::from_ymd_opt(2026, 5, 7).unwrap(),
+                shift_group: "JEN 4 - PM".to_string(),
+                tz: None,
+                output: None,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_missing_and_malformed_options() {
+        let missing = parse(&args(&["consolidate", "--date", "2026-05-01"])).unwrap_err();
+        assert!(missing.to_string().contains("--dialogue-1"));
+
+        let unknown = parse(&args(&["migrate", "--force"])).unwrap_err();
+        assert!(unknown.to_string().contains("--force"));
+
+        let no_value = parse(&args(&["report", "--start-date"])).unwrap_err();
+        assert!(no_value.to_string().contains("needs a value"));
+
+        let bad_date = parse(&args(&[
+            "report",
+            "--start-date",
+            "01/05/2026",
+            "--end-date",
+            "2026-05-07",
+            "--shift-group",
+            "A",
+        ]))
+        .unwrap_err();
+        assert!(bad_date.to_string().contains("Invalid --start-date"));
+
+        assert!(parse(&args(&["frobnicate"])).is_err());
+    }
+
+    #[test]
+    fn renders_rows_as_aligned_table_and_csv() {
+        let preview = ConsolidationPreview {
+            schedules: vec![
+                row("T-1", "-", "Babalwa Magongo"),
+                row("T-22", "Pickup", "Ann"),
+            ],
+            ..ConsolidationPreview::default()
+        };
+
+        let mut table = Vec::new();
+        render_preview(&preview, OutputFormat::Table, &mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Shift Group  Shift  Shift Type  Teacher"));
+        assert!(lines[3].starts_with("JEN 4 - PM   T-22   Pickup      Ann      "));
+
+        let mut csv = Vec::new();
+        render_preview(&preview, OutputFormat::Csv, &mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap().lines().next(),
+            Some("shift_group,shift,shift_type,teacher_name,start_date,end_date")
+        );
+    }
+}
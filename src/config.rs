@@ -1,24 +1,877 @@
-use std::env;
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use dotenv::dotenv;
+use anyhow::{anyhow, Context, Error};
+use axum::http::HeaderValue;
+use chrono::format::{Item as FormatItem, StrftimeItems};
+use chrono_tz::{Africa::Johannesburg, Tz, UTC};
+use toml_edit::{Document, Item, TableLike, Value};
+
+use consolidation::{
+    datetime_profiles::{
+        DateTimeProfile, DateTimeProfileSelection, AUTO_PROFILE, FLEXIBLE_PROFILE,
+    },
+    timezones::DstPolicy,
+    ConsolidationSettings,
+};
+
+use crate::utils::smtp::{validate_address, SmtpSecurity, SmtpServer};
+
+/// Read when `CONFIG_FILE` is not set, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// The config file table that defines datetime profiles, one sub-table per profile.
+const DATETIME_PROFILES_TABLE: &str = "datetime_profiles";
+
+/// Every setting, by environment variable name. The config file uses the same names in lower
+/// case.
+const KEYS: &[&str] = &[
+    "DATABASE_URL",
+    "DATABASE_MAX_CONNECTIONS",
+    "BIND_ADDRESS",
+    "CORS_ALLOWED_ORIGINS",
+    "BODY_LIMIT_BYTES",
+    "LOGS_DIRECTORY",
+    "UPLOAD_DIRECTORY",
+    "SHUTDOWN_TIMEOUT_SECONDS",
+    "WORKER_CONCURRENCY",
+    "JOB_MAX_ATTEMPTS",
+    "JOB_RETRY_DELAY_SECONDS",
+    "WEBHOOK_MAX_ATTEMPTS",
+    "WEBHOOK_RETRY_DELAY_SECONDS",
+    "WEBHOOK_TIMEOUT_SECONDS",
+    "SMTP_HOST",
+    "SMTP_PORT",
+    "SMTP_SECURITY",
+    "SMTP_USERNAME",
+    "SMTP_PASSWORD",
+    "SMTP_TIMEOUT_SECONDS",
+    "REPORT_EMAIL_FROM",
+    "REPORT_EMAIL_HOUR",
+    "REPORT_EMAIL_RETRY_DELAY_SECONDS",
+    "APP_TIMEZONE",
+    "DIALOGUE_SOURCE_TIMEZONE",
+    "INVOICING_SOURCE_TIMEZONE",
+    "DIALOGUE_DST_POLICY",
+    "INVOICING_DST_POLICY",
+    "DATETIME_PROFILE",
+];
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
+    /// Timezone schedules are filtered and reported in.
+    pub app_timezone: Tz,
+    /// Timezone naive Dialogue export timestamps are written in.
+    pub dialogue_timezone: Tz,
+    /// Timezone naive invoicing report timestamps are written in.
+    pub invoicing_timezone: Tz,
+    /// How Dialogue times that fall inside a DST transition are placed.
+    pub dialogue_dst_policy: DstPolicy,
+    /// How invoicing times that fall inside a DST transition are placed.
+    pub invoicing_dst_policy: DstPolicy,
+    pub datetime_profiles: Vec<DateTimeProfile>,
+    pub default_datetime_profile: DateTimeProfileSelection,
+    pub server: ServerConfig,
+    pub queue: QueueConfig,
+    pub webhooks: WebhookConfig,
+    pub email: EmailConfig,
+}
+
+/// How the HTTP server listens and what it accepts.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_address: SocketAddr,
+    /// Browser origins allowed to call the API with credentials. `*` is not accepted.
+    pub cors_allowed_origins: Vec<HeaderValue>,
+    /// Largest request body accepted, uploads included.
+    pub body_limit_bytes: usize,
+    pub database_max_connections: u32,
+    /// Where the daily JSON log files are written.
+    pub logs_directory: PathBuf,
+    /// Where uploads wait for their consolidation job, one sub-directory per job.
+    pub upload_directory: PathBuf,
+    /// How long a shutdown waits for running consolidations before requeueing them.
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 4000)),
+            cors_allowed_origins: vec![
+                HeaderValue::from_static("http://localhost:3001"),
+                HeaderValue::from_static("http://localhost:5173"),
+                HeaderValue::from_static("https://autoreports-app.corecapital.co.za"),
+            ],
+            body_limit_bytes: 100_000_000,
+            database_max_connections: 32,
+            logs_directory: PathBuf::from("./logs"),
+            upload_directory: PathBuf::from("temp"),
+            shutdown_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// How uploads are consolidated in the background.
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// Consolidations that run at the same time.
+    pub worker_concurrency: usize,
+    /// Attempts per job, counting the first, before a transient failure is final.
+    pub max_attempts: i32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub retry_delay: Duration,
+}
+
+impl Default for QueueConfig {
+    fn default() -> QueueConfig {
+        QueueConfig {
+            worker_concurrency: 2,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+/// How consolidation webhooks are delivered.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Attempts per delivery, counting the first.
+    pub max_attempts: i32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub retry_delay: Duration,
+    /// Applies to connecting, sending and waiting for the response status.
+    pub timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> WebhookConfig {
+        WebhookConfig {
+            max_attempts: 6,
+            retry_delay: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// How subscribed reports are emailed. Nothing is sent while `smtp` is `None`.
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    pub smtp: Option<SmtpServer>,
+    /// The sender of report emails.
+    pub from: String,
+    /// Hour of the day in `APP_TIMEZONE` from which the previous day's or week's reports go out,
+    /// leaving time for that day's upload.
+    pub send_hour: u32,
+    /// Delay before trying again after a failed send.
+    pub retry_delay: Duration,
+}
+
+impl Default for EmailConfig {
+    fn default() -> EmailConfig {
+        EmailConfig {
+            smtp: None,
+            from: String::new(),
+            send_hour: 7,
+            retry_delay: Duration::from_secs(900),
+        }
+    }
 }
 
 impl Config {
+    /// Loads `.env` if there is one, then the config file, then the environment, which wins.
+    /// Exits with the offending setting named if one is invalid or `DATABASE_URL` is unset.
     pub fn init() -> Config {
-        let environment_result = dotenv();
+        Config::load(true)
+    }
+
+    /// Used by CLI commands that may run without a database, so `DATABASE_URL` is not required.
+    pub fn without_database() -> Config {
+        Config::load(false)
+    }
+
+    fn load(require_database: bool) -> Config {
+        match dotenv::dotenv() {
+            Ok(_) => {}
+            Err(dotenv::Error::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                println!("🔥 Failed to load .env file: {}", error);
+                std::process::exit(1);
+            }
+        }
+
+        ConfigValues::load()
+            .and_then(|values| Config::from_values(&values, require_database))
+            .unwrap_or_else(|error| {
+                println!("🔥 Invalid configuration: {:#}", error);
+                std::process::exit(1);
+            })
+    }
+
+    fn from_values(values: &ConfigValues, require_database: bool) -> Result<Config, Error> {
+        let database_url = values.get("DATABASE_URL").unwrap_or_default().to_string();
+
+        if require_database && database_url.is_empty() {
+            return Err(anyhow!(
+                "DATABASE_URL must be set in the environment, .env or the config file"
+            ));
+        }
+
+        let app_timezone = values.parse_or("APP_TIMEZONE", Johannesburg)?;
+        let dialogue_timezone = values.parse_or("DIALOGUE_SOURCE_TIMEZONE", UTC)?;
+        let invoicing_timezone = values.parse_or("INVOICING_SOURCE_TIMEZONE", app_timezone)?;
+        let dialogue_dst_policy = values.parse_or("DIALOGUE_DST_POLICY", DstPolicy::Earliest)?;
+        let invoicing_dst_policy = values.parse_or("INVOICING_DST_POLICY", DstPolicy::Earliest)?;
+
+        let datetime_profiles = merge_datetime_profiles(values.datetime_profiles.clone());
+
+        let default_datetime_profile = match values.get("DATETIME_PROFILE") {
+            Some(name) => DateTimeProfileSelection::resolve(name, &datetime_profiles)
+                .map_err(|error| anyhow!("Invalid DATETIME_PROFILE: {}", error))?,
+            None => DateTimeProfileSelection::Flexible,
+        };
+
+        Ok(Config {
+            database_url,
+            app_timezone,
+            dialogue_timezone,
+            invoicing_timezone,
+            dialogue_dst_policy,
+            invoicing_dst_policy,
+            datetime_profiles,
+            default_datetime_profile,
+            server: ServerConfig::from_values(values)?,
+            queue: QueueConfig::from_values(values)?,
+            webhooks: WebhookConfig::from_values(values)?,
+            email: EmailConfig::from_values(values)?,
+        })
+    }
+
+    pub fn consolidation_settings(&self) -> ConsolidationSettings {
+        ConsolidationSettings {
+            app_timezone: self.app_timezone,
+            dialogue_timezone: self.dialogue_timezone,
+            invoicing_timezone: self.invoicing_timezone,
+            dialogue_dst_policy: self.dialogue_dst_policy,
+            invoicing_dst_policy: self.invoicing_dst_policy,
+            datetime_profiles: self.datetime_profiles.clone(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Holds each job's upload in a directory named by the job ID.
+    pub fn jobs_directory(&self) -> PathBuf {
+        self.upload_directory.join("jobs")
+    }
+
+    fn from_values(values: &ConfigValues) -> Result<ServerConfig, Error> {
+        let defaults = ServerConfig::default();
+
+        let cors_allowed_origins = match values.get("CORS_ALLOWED_ORIGINS") {
+            Some(origins) => origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(parse_origin)
+                .collect::<Result<Vec<_>, _>>()
+                .context("Invalid CORS_ALLOWED_ORIGINS")?,
+            None => defaults.cors_allowed_origins,
+        };
+
+        let body_limit_bytes = values.parse_or("BODY_LIMIT_BYTES", defaults.body_limit_bytes)?;
+        let database_max_connections = values.parse_or(
+            "DATABASE_MAX_CONNECTIONS",
+            defaults.database_max_connections,
+        )?;
+
+        if body_limit_bytes == 0 {
+            return Err(anyhow!("Invalid BODY_LIMIT_BYTES: must be greater than 0"));
+        }
+
+        if database_max_connections == 0 {
+            return Err(anyhow!(
+                "Invalid DATABASE_MAX_CONNECTIONS: must be greater than 0"
+            ));
+        }
+
+        Ok(ServerConfig {
+            bind_address: values.parse_or("BIND_ADDRESS", defaults.bind_address)?,
+            cors_allowed_origins,
+            body_limit_bytes,
+            database_max_connections,
+            logs_directory: values
+                .get("LOGS_DIRECTORY")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.logs_directory),
+            upload_directory: values
+                .get("UPLOAD_DIRECTORY")
+                .map(PathBuf::from)
+                .unwrap_or(defaults.upload_directory),
+            shutdown_timeout: Duration::from_secs(values.parse_or(
+                "SHUTDOWN_TIMEOUT_SECONDS",
+                defaults.shutdown_timeout.as_secs(),
+            )?),
+        })
+    }
+}
+
+impl QueueConfig {
+    fn from_values(values: &ConfigValues) -> Result<QueueConfig, Error> {
+        let defaults = QueueConfig::default();
+
+        let worker_concurrency =
+            values.parse_or("WORKER_CONCURRENCY", defaults.worker_concurrency)?;
+        let max_attempts = values.parse_or("JOB_MAX_ATTEMPTS", defaults.max_attempts)?;
+
+        if worker_concurrency == 0 {
+            return Err(anyhow!(
+                "Invalid WORKER_CONCURRENCY: must be greater than 0"
+            ));
+        }
+
+        if max_attempts < 1 {
+            return Err(anyhow!("Invalid JOB_MAX_ATTEMPTS: must be at least 1"));
+        }
+
+        Ok(QueueConfig {
+            worker_concurrency,
+            max_attempts,
+            retry_delay: Duration::from_secs(
+                values.parse_or("JOB_RETRY_DELAY_SECONDS", defaults.retry_delay.as_secs())?,
+            ),
+        })
+    }
+}
+
+impl WebhookConfig {
+    fn from_values(values: &ConfigValues) -> Result<WebhookConfig, Error> {
+        let defaults = WebhookConfig::default();
+
+        let max_attempts = values.parse_or("WEBHOOK_MAX_ATTEMPTS", defaults.max_attempts)?;
+        let timeout = values.parse_or("WEBHOOK_TIMEOUT_SECONDS", defaults.timeout.as_secs())?;
+
+        if max_attempts < 1 {
+            return Err(anyhow!("Invalid WEBHOOK_MAX_ATTEMPTS: must be at least 1"));
+        }
+
+        if timeout == 0 {
+            return Err(anyhow!(
+                "Invalid WEBHOOK_TIMEOUT_SECONDS: must be greater than 0"
+            ));
+        }
+
+        Ok(WebhookConfig {
+            max_attempts,
+            retry_delay: Duration::from_secs(values.parse_or(
+                "WEBHOOK_RETRY_DELAY_SECONDS",
+                defaults.retry_delay.as_secs(),
+            )?),
+            timeout: Duration::from_secs(timeout),
+        })
+    }
+}
+
+impl EmailConfig {
+    fn from_values(values: &ConfigValues) -> Result<EmailConfig, Error> {
+        let defaults = EmailConfig::default();
+
+        let send_hour = values.parse_or("REPORT_EMAIL_HOUR", defaults.send_hour)?;
+        let retry_delay = Duration::from_secs(values.parse_or(
+            "REPORT_EMAIL_RETRY_DELAY_SECONDS",
+            defaults.retry_delay.as_secs(),
+        )?);
+
+        if send_hour > 23 {
+            return Err(anyhow!(
+                "Invalid REPORT_EMAIL_HOUR: must be between 0 and 23"
+            ));
+        }
+
+        let Some(host) = values.get("SMTP_HOST") else {
+            return Ok(EmailConfig {
+                send_hour,
+                retry_delay,
+                ..defaults
+            });
+        };
+
+        let from = values
+            .get("REPORT_EMAIL_FROM")
+            .ok_or_else(|| anyhow!("REPORT_EMAIL_FROM must be set when SMTP_HOST is"))?;
+
+        validate_address(from).context("Invalid REPORT_EMAIL_FROM")?;
+
+        let security = values.parse_or("SMTP_SECURITY", SmtpSecurity::StartTls)?;
+        let timeout = values.parse_or("SMTP_TIMEOUT_SECONDS", 30)?;
 
-        if environment_result.is_err() {
-            println!("🔥 Failed to load .env file.");
-            std::process::exit(1);
+        if timeout == 0 {
+            return Err(anyhow!(
+                "Invalid SMTP_TIMEOUT_SECONDS: must be greater than 0"
+            ));
         }
 
-        let database_url =
-            env::var("DATABASE_URL").expect("Failed to find DATABASE_URL environment variable.");
+        let smtp = SmtpServer {
+            host: host.to_string(),
+            port: values.parse_or("SMTP_PORT", security.default_port())?,
+            security,
+            username: values.get("SMTP_USERNAME").map(str::to_string),
+            password: values.get("SMTP_PASSWORD").map(str::to_string),
+            timeout: Duration::from_secs(timeout),
+        };
+
+        smtp.validate().context("Invalid SMTP_SECURITY")?;
+
+        Ok(EmailConfig {
+            smtp: Some(smtp),
+            from: from.to_string(),
+            send_hour,
+            retry_delay,
+        })
+    }
+}
+
+/// An origin as browsers send it: scheme and host, with an optional port and nothing else.
+fn parse_origin(origin: &str) -> Result<HeaderValue, Error> {
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .ok_or_else(|| anyhow!("{:?} must start with http:// or https://", origin))?;
+
+    if host.is_empty() || host.contains(['/', '*', '?', '#']) {
+        return Err(anyhow!(
+            "{:?} must be a scheme and host without a path or wildcard",
+            origin
+        ));
+    }
+
+    HeaderValue::from_str(origin).map_err(|error| anyhow!("{:?}: {}", origin, error))
+}
+
+/// The built-in profiles, with any the config file defines added or, for the same name, swapped
+/// in.
+fn merge_datetime_profiles(configured: Vec<DateTimeProfile>) -> Vec<DateTimeProfile> {
+    let mut profiles = DateTimeProfile::builtin();
+
+    for profile in configured {
+        match profiles
+            .iter_mut()
+            .find(|existing| existing.name == profile.name)
+        {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+    }
+
+    profiles
+}
+
+/// Reads `[datetime_profiles.<name>]` tables, each with a `formats` list of chrono formats tried
+/// in order and an optional `day_first` flag for loose slash dates such as `3/4/2026 9:00 AM`.
+fn parse_datetime_profiles(item: &Item) -> Result<Vec<DateTimeProfile>, Error> {
+    let table = item
+        .as_table_like()
+        .ok_or_else(|| anyhow!("{} must be a table", DATETIME_PROFILES_TABLE))?;
+
+    table
+        .iter()
+        .map(|(name, profile)| {
+            let profile = profile
+                .as_table_like()
+                .ok_or_else(|| anyhow!("{}.{} must be a table", DATETIME_PROFILES_TABLE, name))?;
+
+            parse_datetime_profile(name, profile)
+                .with_context(|| format!("Invalid {}.{}", DATETIME_PROFILES_TABLE, name))
+        })
+        .collect()
+}
+
+fn parse_datetime_profile(name: &str, table: &dyn TableLike) -> Result<DateTimeProfile, Error> {
+    let name = name.trim().to_ascii_lowercase();
+
+    if [FLEXIBLE_PROFILE, AUTO_PROFILE].contains(&name.as_str()) {
+        return Err(anyhow!("{:?} is reserved", name));
+    }
+
+    for (key, _) in table.iter() {
+        if !["formats", "day_first"].contains(&key) {
+            return Err(anyhow!(
+                "Unknown setting {:?}. Expected formats or day_first",
+                key
+            ));
+        }
+    }
+
+    let formats = table
+        .get("formats")
+        .and_then(Item::as_array)
+        .ok_or_else(|| anyhow!("formats must be a list of strings"))?
+        .iter()
+        .map(|format| {
+            let format = format
+                .as_str()
+                .ok_or_else(|| anyhow!("formats must only contain strings"))?;
+
+            if StrftimeItems::new(format).any(|item| item == FormatItem::Error) {
+                return Err(anyhow!("{:?} is not a valid datetime format", format));
+            }
+
+            Ok(format.to_string())
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let day_first = match table.get("day_first") {
+        Some(item) => Some(
+            item.as_bool()
+                .ok_or_else(|| anyhow!("day_first must be true or false"))?,
+        ),
+        None => None,
+    };
+
+    if formats.is_empty() && day_first.is_none() {
+        return Err(anyhow!("formats must not be empty"));
+    }
+
+    Ok(DateTimeProfile {
+        name,
+        formats,
+        day_first,
+    })
+}
+
+/// Raw settings from the config file, overridden by the environment. Blank values count as
+/// unset.
+#[derive(Debug, Default)]
+struct ConfigValues {
+    values: HashMap<String, String>,
+    /// Profiles defined under `[datetime_profiles.<name>]`, which only the config file can set.
+    datetime_profiles: Vec<DateTimeProfile>,
+}
+
+impl ConfigValues {
+    fn load() -> Result<ConfigValues, Error> {
+        let mut config_values = match env::var("CONFIG_FILE") {
+            Ok(path) => ConfigValues::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                ConfigValues::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            Err(_) => ConfigValues::default(),
+        };
+
+        for key in KEYS {
+            if let Ok(value) = env::var(key) {
+                config_values.values.insert(key.to_string(), value);
+            }
+        }
+
+        Ok(config_values)
+    }
+
+    fn from_file(path: &Path) -> Result<ConfigValues, Error> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read config file {}", path.display()))?;
+
+        ConfigValues::from_toml(&source)
+            .with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Top-level keys, plus the `datetime_profiles` table. Lists of strings, used for CORS
+    /// origins, are joined with commas.
+    fn from_toml(source: &str) -> Result<ConfigValues, Error> {
+        let document = source.parse::<Document>()?;
+        let mut values = HashMap::new();
+        let mut datetime_profiles = Vec::new();
+
+        for (key, item) in document.iter() {
+            if key == DATETIME_PROFILES_TABLE {
+                datetime_profiles = parse_datetime_profiles(item)?;
+                continue;
+            }
+
+            let name = key.to_ascii_uppercase();
+
+            if !KEYS.contains(&name.as_str()) {
+                return Err(anyhow!(
+                    "Unknown setting {:?}. Expected one of: {}",
+                    key,
+                    KEYS.iter()
+                        .map(|key| key.to_ascii_lowercase())
+                        .chain([DATETIME_PROFILES_TABLE.to_string()])
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+
+            let value = match item {
+                Item::Value(Value::String(value)) => value.value().to_string(),
+                Item::Value(Value::Integer(value)) => value.value().to_string(),
+                Item::Value(Value::Array(array)) => array
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map(str::to_string)
+                            .ok_or_else(|| anyhow!("{} must only contain strings", key))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(","),
+                _ => return Err(anyhow!("{} must be a string, an integer or a list", key)),
+            };
+
+            values.insert(name, value);
+        }
+
+        Ok(ConfigValues {
+            values,
+            datetime_profiles,
+        })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .get(key)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn parse_or<T>(&self, key: &str, default: T) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.get(key) {
+            Some(value) => value
+                .parse::<T>()
+                .map_err(|error| anyhow!("Invalid {} {:?}: {}", key, value, error)),
+            None => Ok(default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono_tz::{Africa::Johannesburg, America::New_York};
+    use consolidation::datetime_profiles::DateTimeProfile;
+
+    use super::{Config, ConfigValues};
+
+    fn values(pairs: &[(&str, &str)]) -> ConfigValues {
+        ConfigValues {
+            values: pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..ConfigValues::default()
+        }
+    }
+
+    #[test]
+    fn defaults_everything_but_the_database_url() {
+        let config =
+            Config::from_values(&values(&[("DATABASE_URL", "postgres://db")]), true).unwrap();
+
+        assert_eq!(config.app_timezone, Johannesburg);
+        assert_eq!(config.server.bind_address.to_string(), "0.0.0.0:4000");
+        assert_eq!(config.server.body_limit_bytes, 100_000_000);
+        assert_eq!(config.server.database_max_connections, 32);
+        assert_eq!(
+            config.server.cors_allowed_origins,
+            [
+                "http://localhost:3001",
+                "http://localhost:5173",
+                "https://autoreports-app.corecapital.co.za"
+            ]
+        );
+
+        let example = ConfigValues::from_toml(include_str!("../config.example.toml")).unwrap();
+        assert_eq!(
+            Config::from_values(&example, true)
+                .unwrap()
+                .server
+                .cors_allowed_origins,
+            config.server.cors_allowed_origins
+        );
+
+        let error = Config::from_values(&values(&[("DATABASE_URL", " ")]), true).unwrap_err();
+        assert!(error.to_string().contains("DATABASE_URL must be set"));
+        assert!(Config::from_values(&values(&[]), false).is_ok());
+    }
+
+    #[test]
+    fn reads_the_toml_file() {
+        let file = ConfigValues::from_toml(
+            r#"
+            database_url = "postgres://db"
+            app_timezone = "America/New_York"
+            bind_address = "127.0.0.1:8080"
+            cors_allowed_origins = ["https://reports.example.com", "http://localhost:5173"]
+            body_limit_bytes = 1048576
+            database_max_connections = 8
+            logs_directory = "/var/log/sergio-ar-api"
+            upload_directory = "/var/lib/sergio-ar-api/uploads"
+            shutdown_timeout_seconds = 120
+            worker_concurrency = 4
+            "#,
+        )
+        .unwrap();
+        let config = Config::from_values(&file, true).unwrap();
+
+        assert_eq!(config.app_timezone, New_York);
+        assert_eq!(config.invoicing_timezone, New_York);
+        assert_eq!(config.server.bind_address.to_string(), "127.0.0.1:8080");
+        assert_eq!(
+            config.server.cors_allowed_origins,
+            ["https://reports.example.com", "http://localhost:5173"]
+        );
+        assert_eq!(config.server.body_limit_bytes, 1_048_576);
+        assert_eq!(config.server.database_max_connections, 8);
+        assert_eq!(
+            config.server.logs_directory.to_str(),
+            Some("/var/log/sergio-ar-api")
+        );
+        assert_eq!(
+            config.server.jobs_directory().to_str(),
+            Some("/var/lib/sergio-ar-api/uploads/jobs")
+        );
+        assert_eq!(config.server.shutdown_timeout.as_secs(), 120);
+        assert_eq!(config.queue.worker_concurrency, 4);
+        assert_eq!(config.queue.max_attempts, 3);
+        assert!(config.email.smtp.is_none());
+    }
+
+    #[test]
+    fn reads_datetime_profiles_from_the_toml_file() {
+        let file = ConfigValues::from_toml(
+            r#"
+            datetime_profile = "za"
+
+            [datetime_profiles.za]
+            formats = ["%Y/%m/%d %H:%M"]
+
+            [datetime_profiles.uk]
+            formats = ["%d.%m.%Y %H:%M"]
+            day_first = true
+            "#,
+        )
+        .unwrap();
+        let config = Config::from_values(&file, false).unwrap();
+        let names = config
+            .datetime_profiles
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["us", "uk", "iso", "za"]);
+        assert_eq!(config.datetime_profiles[1].formats, ["%d.%m.%Y %H:%M"]);
+        assert_eq!(config.default_datetime_profile.name(), "za");
+
+        let example = ConfigValues::from_toml(include_str!("../config.example.toml")).unwrap();
+        assert_eq!(example.datetime_profiles, DateTimeProfile::builtin());
+
+        for (source, expected) in [
+            ("[datetime_profiles.auto]\nformats = [\"%Y\"]", "reserved"),
+            ("[datetime_profiles.za]\nformats = []", "must not be empty"),
+            ("[datetime_profiles.za]\nformats = [\"%Q\"]", "not a valid"),
+            (
+                "[datetime_profiles.za]\nformat = [\"%Y\"]",
+                "Unknown setting",
+            ),
+        ] {
+            let error = ConfigValues::from_toml(source).unwrap_err();
+
+            assert!(format!("{:#}", error).contains(expected), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn reads_the_smtp_server() {
+        let config = Config::from_values(
+            &values(&[
+                ("SMTP_HOST", "smtp.example.com"),
+                ("SMTP_SECURITY", "tls"),
+                ("SMTP_USERNAME", "reports"),
+                ("REPORT_EMAIL_FROM", "reports@example.com"),
+            ]),
+            false,
+        )
+        .unwrap();
+        let smtp = config.email.smtp.unwrap();
+
+        assert_eq!(smtp.host, "smtp.example.com");
+        assert_eq!(smtp.port, 465);
+        assert_eq!(smtp.username.as_deref(), Some("reports"));
+        assert_eq!(smtp.password, None);
+        assert_eq!(config.email.from, "reports@example.com");
+        assert_eq!(config.email.send_hour, 7);
+
+        let error = Config::from_values(
+            &values(&[
+                ("SMTP_HOST", "localhost"),
+                ("SMTP_SECURITY", "none"),
+                ("SMTP_USERNAME", "reports"),
+                ("REPORT_EMAIL_FROM", "reports@example.com"),
+            ]),
+            false,
+        )
+        .unwrap_err();
+
+        assert!(
+            format!("{:#}", error).contains("without TLS"),
+            "{:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        for (key, value, expected) in [
+            ("BIND_ADDRESS", "localhost", "Invalid BIND_ADDRESS"),
+            ("BODY_LIMIT_BYTES", "100MB", "Invalid BODY_LIMIT_BYTES"),
+            ("DATABASE_MAX_CONNECTIONS", "0", "must be greater than 0"),
+            ("WORKER_CONCURRENCY", "0", "must be greater than 0"),
+            ("JOB_MAX_ATTEMPTS", "-1", "must be at least 1"),
+            ("WEBHOOK_TIMEOUT_SECONDS", "0", "must be greater than 0"),
+            ("REPORT_EMAIL_HOUR", "24", "must be between 0 and 23"),
+            (
+                "SMTP_HOST",
+                "smtp.example.com",
+                "REPORT_EMAIL_FROM must be set",
+            ),
+            ("CORS_ALLOWED_ORIGINS", "*", "must start with http"),
+            (
+                "CORS_ALLOWED_ORIGINS",
+                "https://a.example.com/app",
+                "without a path",
+            ),
+            ("APP_TIMEZONE", "Mars/Olympus", "Invalid APP_TIMEZONE"),
+            (
+                "DIALOGUE_DST_POLICY",
+                "sometimes",
+                "Invalid DIALOGUE_DST_POLICY",
+            ),
+        ] {
+            let error = Config::from_values(&values(&[(key, value)]), false).unwrap_err();
+
+            assert!(
+                format!("{:#}", error).contains(expected),
+                "{}={}: {:#}",
+                key,
+                value,
+                error
+            );
+        }
 
-        Config { database_url }
+        assert!(ConfigValues::from_toml("port = 4000").is_err());
+        assert!(ConfigValues::from_toml("[server]\nbind_address = \"0.0.0.0:1\"").is_err());
     }
 }
@@ -0,0 +1,206 @@
+//! Date ranges taken from query strings, and the one rule every schedule query filters by.
+//!
+//! `start_date` and `end_date` each take an ISO date (`2026-05-04`) or date and time
+//! (`2026-05-04T08:00`, `2026-05-04 08:00:00`), optionally with an offset (`Z`, `+02:00`).
+//! Values without an offset are read in `tz`, which defaults to `APP_TIMEZONE`. A date starts
+//! the range at the beginning of that day, or ends it at the end of that day.
+//!
+//! The range is half-open, and a shift is in it when the two overlap: it starts before the
+//! range ends and ends after the range starts. A shift crossing midnight is therefore in both
+//! days.
+
+use anyhow::{anyhow, Error};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use consolidation::timezones::{resolve_local_datetime, resolve_timezone, DstPolicy};
+use serde::Deserialize;
+
+use crate::{
+    error::{ApiError, Query},
+    AppState,
+};
+
+const DATETIME_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"];
+
+const OFFSET_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f%:z", "%Y-%m-%dT%H:%M%:z"];
+
+const EXPECTED: &str = "expected YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS], optionally with an offset";
+
+#[derive(Debug, Deserialize)]
+struct DateRangeParams {
+    start_date: String,
+    end_date: String,
+    /// IANA timezone the range is given in and results are rendered in.
+    tz: Option<String>,
+}
+
+/// The instants from `start` up to, but not including, `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Where dates without an offset were read, and the one results are shown in.
+    pub timezone: Tz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Start,
+    End,
+}
+
+impl DateRange {
+    /// The whole days from `start_date` to `end_date` in `timezone`.
+    pub fn from_dates(start_date: NaiveDate, end_date: NaiveDate, timezone: Tz) -> DateRange {
+        DateRange {
+            start: start_of_day(start_date, timezone),
+            end: start_of_day(end_date + Days::new(1), timezone),
+            timezone,
+        }
+    }
+
+    pub fn parse(start_date: &str, end_date: &str, timezone: Tz) -> Result<DateRange, ApiError> {
+        let start = parse_bound(start_date, Bound::Start, timezone)
+            .map_err(|error| ApiError::invalid_field("start_date", error.to_string()))?;
+        let end = parse_bound(end_date, Bound::End, timezone)
+            .map_err(|error| ApiError::invalid_field("end_date", error.to_string()))?;
+
+        if end <= start {
+            return Err(ApiError::invalid_field(
+                "end_date",
+                "must be after start_date",
+            ));
+        }
+
+        Ok(DateRange {
+            start,
+            end,
+            timezone,
+        })
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for DateRange {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(params) = Query::<DateRangeParams>::from_request_parts(parts, state).await?;
+        let timezone = resolve_timezone(params.tz.as_deref(), state.env.app_timezone)
+            .map_err(|error| ApiError::invalid_field("tz", error.to_string()))?;
+
+        DateRange::parse(&params.start_date, &params.end_date, timezone)
+    }
+}
+
+/// The SQL condition for schedules overlapping the range bound to `$start` and `$end`.
+pub fn overlaps(start: usize, end: usize) -> String {
+    format!(
+        "schedules.start_date < ${} AND schedules.end_date > ${}",
+        end, start
+    )
+}
+
+fn parse_bound(original: &str, bound: Bound, timezone: Tz) -> Result<DateTime<Utc>, Error> {
+    let value = original.trim().replacen(' ', "T", 1);
+
+    if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+        return Ok(match bound {
+            Bound::Start => start_of_day(date, timezone),
+            Bound::End => start_of_day(date + Days::new(1), timezone),
+        });
+    }
+
+    let with_offset = match value.strip_suffix(['Z', 'z']) {
+        Some(utc) => format!("{}+00:00", utc),
+        None => value.clone(),
+    };
+
+    for format in OFFSET_FORMATS {
+        if let Ok(datetime) = DateTime::parse_from_str(&with_offset, format) {
+            return Ok(datetime.with_timezone(&Utc));
+        }
+    }
+
+    for format in DATETIME_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(&value, format) {
+            return Ok(local_to_utc(naive, timezone));
+        }
+    }
+
+    Err(anyhow!(
+        "{:?} is not a date or time ({})",
+        original,
+        EXPECTED
+    ))
+}
+
+fn start_of_day(date: NaiveDate, timezone: Tz) -> DateTime<Utc> {
+    local_to_utc(NaiveDateTime::new(date, NaiveTime::MIN), timezone)
+}
+
+/// Times a DST transition makes ambiguous or skips are read as the earlier instant.
+fn local_to_utc(naive: NaiveDateTime, timezone: Tz) -> DateTime<Utc> {
+    match resolve_local_datetime(naive, timezone, DstPolicy::Earliest, None) {
+        Ok(resolved) => resolved.datetime.with_timezone(&Utc),
+        Err(_) => unreachable!("the earliest policy always resolves"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use chrono_tz::{Africa::Johannesburg, America::New_York};
+
+    use super::DateRange;
+
+    fn instant(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn dates_cover_whole_days_in_the_timezone() {
+        let range = DateRange::parse("2026-05-04", "2026-05-05", Johannesburg).unwrap();
+
+        assert_eq!(range.start, instant("2026-05-03T22:00:00Z"));
+        assert_eq!(range.end, instant("2026-05-05T22:00:00Z"));
+    }
+
+    #[test]
+    fn reads_datetimes_with_and_without_offsets() {
+        let range =
+            DateRange::parse("2026-05-04 08:00:00", "2026-05-04T10:30Z", Johannesburg).unwrap();
+
+        assert_eq!(range.start, instant("2026-05-04T06:00:00Z"));
+        assert_eq!(range.end, instant("2026-05-04T10:30:00Z"));
+
+        let range =
+            DateRange::parse("2026-05-04T08:00", "2026-05-04T14:00:00.5+01:00", New_York).unwrap();
+
+        assert_eq!(range.start, instant("2026-05-04T12:00:00Z"));
+        assert_eq!(range.end, instant("2026-05-04T13:00:00.5Z"));
+    }
+
+    #[test]
+    fn a_day_across_a_dst_change_is_not_24_hours() {
+        let range = DateRange::parse("2026-03-08", "2026-03-08", New_York).unwrap();
+
+        assert_eq!((range.end - range.start).num_hours(), 23);
+    }
+
+    #[test]
+    fn names_the_field_that_is_wrong() {
+        let error = DateRange::parse("2026-05-04", "05/04/2026", Johannesburg).unwrap_err();
+        assert_eq!(error.message(), "Invalid end_date: \"05/04/2026\" is not a date or time (expected YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS], optionally with an offset)");
+
+        let error = DateRange::parse("2026-05-05", "2026-05-04", Johannesburg).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Invalid end_date: must be after start_date"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use axum::{
+    async_trait,
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Request,
+    },
+    http::{request::Parts, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// One invalid input, named the way the client sent it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every error a handler returns. Rendered as
+/// `{"status", "code", "message", "request_id", "details"?}` with a matching HTTP status.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request is malformed or fails validation. `details` lists the offending fields.
+    Validation {
+        message: String,
+        details: Vec<FieldError>,
+    },
+    NotFound(String),
+    RouteNotFound,
+    Conflict(String),
+    /// The body is over `BODY_LIMIT_BYTES`.
+    PayloadTooLarge(String),
+    /// Something failed on our side. The cause is logged where it happens, not returned.
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn validation(message: impl Into<String>) -> ApiError {
+        ApiError::Validation {
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> ApiError {
+        let message = message.into();
+
+        ApiError::Validation {
+            message: format!("Invalid {}: {}", field, message),
+            details: vec![FieldError {
+                field: field.to_string(),
+                message,
+            }],
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> ApiError {
+        ApiError::Internal(message.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Validation { .. } => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) | ApiError::RouteNotFound => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable, machine-readable identifier. Clients should branch on this, not on `message`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation { .. } => "validation_failed",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::RouteNotFound => "route_not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::Validation { message, .. }
+            | ApiError::NotFound(message)
+            | ApiError::Conflict(message)
+            | ApiError::PayloadTooLarge(message)
+            | ApiError::Internal(message) => message,
+            ApiError::RouteNotFound => "Route not found. Please contact the developer.",
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let mut body = json!({
+            "status": status.as_u16(),
+            "code": self.code(),
+            "message": self.message(),
+            "request_id": current_request_id(),
+        });
+
+        if let ApiError::Validation { details, .. } = &self {
+            if !details.is_empty() {
+                body["details"] = json!(details);
+            }
+        }
+
+        (status, Json(body)).into_response()
+    }
+}
+
+/// The ID of the request being handled, if it came through [request_id].
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Reuses the caller's `x-request-id` or generates one, makes it available to [ApiError] and
+/// echoes it on the response.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+/// Pulls the field name out of serde messages such as ``missing field `alias` ``.
+fn field_from_serde_message(message: &str) -> Option<String> {
+    ["missing field `", "unknown field `", "duplicate field `"]
+        .iter()
+        .find_map(|prefix| {
+            let start = message.find(prefix)? + prefix.len();
+            let end = message[start..].find('`')?;
+
+            Some(message[start..start + end].to_string())
+        })
+}
+
+fn rejection_error(message: String) -> ApiError {
+    match field_from_serde_message(&message) {
+        Some(field) => ApiError::Validation {
+            details: vec![FieldError {
+                field,
+                message: message.clone(),
+            }],
+            message,
+        },
+        None => ApiError::validation(message),
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> ApiError {
+        rejection_error(rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> ApiError {
+        rejection_error(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> ApiError {
+        ApiError::validation(rejection.body_text())
+    }
+}
+
+/// Keeps the status axum chose, so a body over the limit is a 413 rather than a 400.
+fn multipart_error(status: StatusCode, message: String) -> ApiError {
+    match status {
+        StatusCode::PAYLOAD_TOO_LARGE => ApiError::PayloadTooLarge(message),
+        _ => ApiError::validation(message),
+    }
+}
+
+impl From<MultipartRejection> for ApiError {
+    fn from(rejection: MultipartRejection) -> ApiError {
+        multipart_error(rejection.status(), rejection.body_text())
+    }
+}
+
+/// A multipart body that breaks off, is malformed or grows past the limit part way through.
+impl From<MultipartError> for ApiError {
+    fn from(error: MultipartError) -> ApiError {
+        multipart_error(error.status(), error.body_text())
+    }
+}
+
+/// [axum::extract::Query] that rejects with an [ApiError].
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+
+        Ok(Query(value))
+    }
+}
+
+/// [axum::extract::Path] that rejects with an [ApiError].
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) =
+            axum::extract::Path::<T>::from_request_parts(parts, state).await?;
+
+        Ok(Path(value))
+    }
+}
+
+/// [axum::Json] body that rejects with an [ApiError]. Responses keep using [axum::Json].
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+
+        Ok(JsonBody(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, response::IntoResponse};
+    use serde_json::Value;
+
+    use axum::http::StatusCode;
+
+    use super::{field_from_serde_message, multipart_error, ApiError, FieldError, REQUEST_ID};
+
+    async fn render(error: ApiError) -> (u16, Value) {
+        let response = REQUEST_ID
+            .scope("req-1".to_string(), async { error.into_response() })
+            .await;
+        let status = response.status().as_u16();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn renders_field_details_with_the_request_id() {
+        let (status, body) = render(ApiError::invalid_field("start_date", "expected a date")).await;
+
+        assert_eq!(status, 400);
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["message"], "Invalid start_date: expected a date");
+        assert_eq!(body["request_id"], "req-1");
+        assert_eq!(
+            body["details"],
+            serde_json::json!([FieldError {
+                field: "start_date".to_string(),
+                message: "expected a date".to_string(),
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn omits_details_for_other_errors() {
+        let (status, body) = render(ApiError::RouteNotFound).await;
+
+        assert_eq!(status, 404);
+        assert_eq!(body["code"], "route_not_found");
+        assert_eq!(body["status"], 404);
+        assert!(body.get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn keeps_the_status_of_multipart_errors() {
+        let (status, body) = render(multipart_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "length limit exceeded".to_string(),
+        ))
+        .await;
+
+        assert_eq!(status, 413);
+        assert_eq!(body["code"], "payload_too_large");
+
+        let (status, _) = render(multipart_error(
+            StatusCode::BAD_REQUEST,
+            "incomplete stream".to_string(),
+        ))
+        .await;
+
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn finds_field_names_in_serde_messages() {
+        assert_eq!(
+            field_from_serde_message(
+                "Failed to deserialize query string: missing field `start_date`"
+            )
+            .as_deref(),
+            Some("start_date")
+        );
+        assert_eq!(field_from_serde_message("expected value at line 1"), None);
+    }
+}
@@ -0,0 +1,1492 @@
+//! End-to-end runs of the router against a throwaway Postgres database.
+//!
+//! `sqlx::test` creates a fresh database from `DATABASE_URL` for every test and applies the
+//! migrations. Each scenario uploads the exports in `tests/fixtures/<scenario>`, waits for the
+//! background consolidation and compares `/shift-groups`, `/schedules`, the consolidated report
+//! and the stored invoices with `expected.json` in the same directory.
+//!
+//! Run them with `cargo test -- --ignored`. Set `UPDATE_GOLDEN=1` to rewrite `expected.json`
+//! after an intended change.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    sync::atomic::AtomicBool,
+    time::Duration,
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::DefaultBodyLimit,
+    http::{header::CONTENT_TYPE, Method, Request, StatusCode},
+    Router,
+};
+use chrono::NaiveDate;
+use chrono_tz::{Africa::Johannesburg, UTC};
+use consolidation::{
+    datetime_profiles::{DateTimeProfile, DateTimeProfileSelection},
+    timezones::DstPolicy,
+    ConsolidationRepository, DialogueConsolidatedRow, ScheduleRecord,
+};
+use serde_json::{json, Map, Value};
+use sqlx::PgPool;
+use tempfile::TempDir;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, EmailConfig, QueueConfig, ServerConfig, WebhookConfig},
+    metrics::Metrics,
+    router::create_router,
+    routes::consolidator::repository::PgConsolidationRepository,
+    scheduler::ReportScheduler,
+    utils::{
+        consolidation_jobs::{
+            claim_job, enqueue_job, requeue_interrupted_job, requeue_interrupted_jobs,
+        },
+        reports::{render_reports, CSV_FORMAT},
+        shift_groups::backfill_last_processed_dates,
+        smtp::{tests::smtp_sink, SmtpSecurity, SmtpServer},
+        teachers::{merge_duplicate_teachers, DuplicateTeacher, TeacherMerge},
+        webhooks::{sign, tests::header_value},
+    },
+    worker::{spawn_workers, wait_for_cancellation},
+    AppState,
+};
+
+const BOUNDARY: &str = "sergio-ar-integration-test";
+
+const UPLOAD_FILES: [&str; 3] = ["dialogue-1", "dialogue-2", "invoicing-report"];
+
+fn config() -> Config {
+    Config {
+        database_url: String::new(),
+        app_timezone: Johannesburg,
+        dialogue_timezone: UTC,
+        invoicing_timezone: Johannesburg,
+        dialogue_dst_policy: DstPolicy::Earliest,
+        invoicing_dst_policy: DstPolicy::Earliest,
+        datetime_profiles: DateTimeProfile::builtin(),
+        default_datetime_profile: DateTimeProfileSelection::Flexible,
+        // Every test database numbers its jobs from 1, so each test needs its own uploads.
+        server: ServerConfig {
+            upload_directory: std::env::temp_dir()
+                .join(format!("sergio-ar-integration-{}", Uuid::new_v4())),
+            ..ServerConfig::default()
+        },
+        queue: QueueConfig::default(),
+        webhooks: WebhookConfig::default(),
+        email: EmailConfig::default(),
+    }
+}
+
+fn app_state(db: &PgPool) -> AppState {
+    AppState {
+        db: db.clone(),
+        env: config(),
+        metrics: Metrics::new(),
+        report_scheduler: ReportScheduler::default(),
+    }
+}
+
+/// An [app_state] whose uploads go in a directory that is removed when the guard drops, even
+/// if the test fails first.
+fn app_state_with_uploads(db: &PgPool) -> (AppState, TempDir) {
+    let uploads = TempDir::new().expect("upload directory");
+    let mut app_state = app_state(db);
+    app_state.env.server.upload_directory = uploads.path().to_path_buf();
+
+    (app_state, uploads)
+}
+
+fn fixture_dir(scenario: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(scenario)
+}
+
+/// Percent-encodes everything but unreserved characters, for shift groups in query strings.
+fn query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn multipart_body(scenario: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    for name in UPLOAD_FILES {
+        let file_name = format!("{}.csv", name);
+        let contents = std::fs::read(fixture_dir(scenario).join(&file_name))
+            .unwrap_or_else(|error| panic!("reading {}/{}: {}", scenario, file_name, error));
+
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: text/csv\r\n\r\n",
+                BOUNDARY, name, file_name
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&contents);
+        body.extend_from_slice(b"\r\n");
+    }
+
+    body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, String) {
+    let response = app.clone().oneshot(request).await.expect("response");
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("response body");
+
+    (
+        status,
+        String::from_utf8(body.to_vec()).expect("UTF-8 body"),
+    )
+}
+
+async fn get(app: &Router, uri: &str) -> String {
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let (status, body) = send(app, request).await;
+
+    assert_eq!(status, StatusCode::OK, "GET {} returned {}", uri, body);
+
+    body
+}
+
+async fn get_json(app: &Router, uri: &str) -> Value {
+    serde_json::from_str(&get(app, uri).await).expect("JSON body")
+}
+
+fn upload_request(date: &str, body: Vec<u8>) -> Request<Body> {
+    Request::builder()
+        .method(Method::POST)
+        .uri(format!("/upload-and-process?date={}", date))
+        .header(
+            CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Uploads a scenario and waits for a worker to consolidate it.
+async fn upload(app: &Router, db: &PgPool, scenario: &str, date: &str) {
+    let (status, body) = send(app, upload_request(date, multipart_body(scenario))).await;
+
+    assert_eq!(status, StatusCode::OK, "upload returned {}", body);
+
+    for _ in 0..100 {
+        let run: Option<(String, Option<String>)> = sqlx::query_as(
+            "SELECT status, error FROM consolidation_jobs \
+             WHERE status NOT IN ('queued', 'running') ORDER BY id",
+        )
+        .fetch_optional(db)
+        .await
+        .expect("consolidation run");
+
+        if let Some((status, error)) = run {
+            assert_eq!(status, "succeeded", "consolidation failed: {:?}", error);
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!("consolidation of {} did not finish", scenario);
+}
+
+/// Everything the API exposes for `date`, in a form that is stable across runs.
+async fn snapshot(app: &Router, db: &PgPool, date: &str) -> Value {
+    let mut shift_groups = get_json(app, "/shift-groups").await["shift_groups"]
+        .as_array()
+        .expect("shift groups")
+        .iter()
+        .map(|group| group["name"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    shift_groups.sort();
+
+    let mut schedules = Map::new();
+    let mut reports = Map::new();
+
+    for shift_group in &shift_groups {
+        let mut rows = get_json(
+            app,
+            &format!(
+                "/schedules?start_date={}&end_date={}&shift_group={}",
+                query_value(&format!("{} 00:00:00", date)),
+                query_value(&format!("{} 23:59:59", date)),
+                query_value(shift_group)
+            ),
+        )
+        .await["schedules"]
+            .take();
+
+        for row in rows.as_array_mut().expect("schedules") {
+            row.as_object_mut().unwrap().remove("id");
+        }
+
+        let report = get(
+            app,
+            &format!(
+                "/generate-consolidated-report?start_date={}&end_date={}&shift_group={}",
+                date,
+                date,
+                query_value(shift_group)
+            ),
+        )
+        .await;
+
+        schedules.insert(shift_group.clone(), rows);
+        reports.insert(
+            shift_group.clone(),
+            report.lines().map(Value::from).collect::<Vec<_>>().into(),
+        );
+    }
+
+    let invoices = sqlx::query_as::<_, (String, String, bool, String, String)>(
+        r#"
+        SELECT
+            teacher_name,
+            shift,
+            eligible,
+            TO_CHAR(activity_start AT TIME ZONE 'Africa/Johannesburg', 'YYYY-MM-DD HH24:MI:SS'),
+            TO_CHAR(activity_end AT TIME ZONE 'Africa/Johannesburg', 'YYYY-MM-DD HH24:MI:SS')
+        FROM invoices
+        ORDER BY activity_start, teacher_name, shift
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .expect("invoices")
+    .into_iter()
+    .map(
+        |(teacher_name, shift, eligible, activity_start, activity_end)| {
+            json!({
+                "teacher_name": teacher_name,
+                "shift": shift,
+                "eligible": eligible,
+                "activity_start": activity_start,
+                "activity_end": activity_end,
+            })
+        },
+    )
+    .collect::<Vec<_>>();
+
+    json!({
+        "shift_groups": shift_groups,
+        "schedules": schedules,
+        "reports": reports,
+        "invoices": invoices,
+    })
+}
+
+fn assert_golden(scenario: &str, actual: &Value) {
+    let path = fixture_dir(scenario).join("expected.json");
+    let rendered = format!("{}\n", serde_json::to_string_pretty(actual).unwrap());
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, rendered).expect("writing expected.json");
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "{} is missing; run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+
+    assert_eq!(
+        rendered,
+        expected,
+        "{} differs; run with UPDATE_GOLDEN=1 if the change is intended",
+        path.display()
+    );
+}
+
+async fn run_scenario(db: PgPool, scenario: &str, date: &str) {
+    let (app_state, _uploads) = app_state_with_uploads(&db);
+    let app = create_router(app_state.clone()).await;
+    let workers = TaskTracker::new();
+    let stop_workers = CancellationToken::new();
+
+    spawn_workers(
+        &app_state,
+        &workers,
+        stop_workers.clone(),
+        CancellationToken::new(),
+    );
+
+    upload(&app, &db, scenario, date).await;
+    let snapshot = snapshot(&app, &db, date).await;
+
+    for shift_group in get_json(&app, "/shift-groups").await["shift_groups"]
+        .as_array()
+        .expect("shift groups")
+    {
+        assert_eq!(shift_group["last_processed_date"], date, "{}", shift_group);
+        assert!(shift_group["schedule_count"].as_i64().unwrap() > 0);
+    }
+
+    stop_workers.cancel();
+    workers.close();
+    workers.wait().await;
+
+    assert_golden(scenario, &snapshot);
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn consolidates_salesforce_us_exports(db: PgPool) {
+    run_scenario(db, "salesforce-us", "2026-05-02").await;
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn consolidates_exports_resaved_by_excel(db: PgPool) {
+    run_scenario(db, "excel-resaved", "2026-05-09").await;
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn reports_ready_with_every_migration_applied(db: PgPool) {
+    let app = create_router(app_state(&db)).await;
+
+    let ready = get_json(&app, "/readyz").await;
+    let version = get_json(&app, "/version").await;
+
+    assert_eq!(ready["checks"]["database"]["ok"], true);
+    assert_eq!(ready["checks"]["migrations"]["ok"], true);
+    assert_eq!(ready["checks"]["storage"]["ok"], true);
+    assert_eq!(
+        version["migrations"]["applied"],
+        version["migrations"]["embedded"]
+    );
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn reports_not_ready_while_migrations_are_pending(db: PgPool) {
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 4")
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let app = create_router(app_state(&db)).await;
+    let request = Request::get("/readyz").body(Body::empty()).unwrap();
+    let (status, body) = send(&app, request).await;
+    let body: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["checks"]["migrations"]["ok"], false);
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn requeues_jobs_left_running_by_a_previous_process(db: PgPool) {
+    sqlx::query(
+        "INSERT INTO consolidation_jobs (process_date, status, attempts, cancel_requested) \
+         VALUES \
+             ('2026-05-01', 'running', 1, FALSE), \
+             ('2026-05-02', 'running', 3, FALSE), \
+             ('2026-05-03', 'running', 1, TRUE), \
+             ('2026-05-04', 'succeeded', 1, FALSE)",
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    assert_eq!(requeue_interrupted_jobs(&db).await.unwrap(), 3);
+
+    let statuses: Vec<(String, bool)> = sqlx::query_as(
+        "SELECT status, finished_at IS NOT NULL FROM consolidation_jobs ORDER BY process_date",
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap();
+
+    assert_eq!(
+        statuses,
+        [
+            ("queued".to_string(), false),
+            ("failed".to_string(), true),
+            ("cancelled".to_string(), true),
+            ("succeeded".to_string(), false),
+        ]
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn stops_cancelled_jobs_only_before_they_write(db: PgPool) {
+    sqlx::query(
+        "INSERT INTO consolidation_jobs (id, process_date, status, attempts, cancel_requested) \
+         VALUES (1, '2026-05-01', 'running', 1, TRUE)",
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    let state = app_state(&db);
+    let wait = |writes_started: bool| {
+        let state = state.clone();
+
+        async move {
+            let writes_started = AtomicBool::new(writes_started);
+
+            tokio::time::timeout(
+                Duration::from_secs(3),
+                wait_for_cancellation(&state, 1, &writes_started),
+            )
+            .await
+        }
+    };
+
+    assert!(wait(false).await.is_ok());
+    assert!(wait(true).await.is_err());
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn requeues_only_the_job_a_worker_let_go_of(db: PgPool) {
+    sqlx::query(
+        "INSERT INTO consolidation_jobs (id, process_date, status, attempts) \
+         VALUES (1, '2026-05-01', 'running', 1), (2, '2026-05-02', 'running', 1)",
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    requeue_interrupted_job(&db, 2).await.unwrap();
+
+    let statuses: Vec<(i32, String)> =
+        sqlx::query_as("SELECT id, status FROM consolidation_jobs ORDER BY id")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+
+    assert_eq!(
+        statuses,
+        [(1, "running".to_string()), (2, "queued".to_string())]
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn queues_jobs_with_long_profile_names(db: PgPool) {
+    let date = NaiveDate::from_ymd_opt(2026, 5, 2).unwrap();
+    let profile = "salesforce-us-exports-resaved-by-excel-in-the-cape-town-office";
+    let job = enqueue_job(&db, date, Some(profile), 3, "temp/jobs")
+        .await
+        .unwrap();
+
+    assert_eq!(job.datetime_profile.as_deref(), Some(profile));
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn cancels_queued_jobs_once(db: PgPool) {
+    let app = create_router(app_state(&db)).await;
+    let date = NaiveDate::from_ymd_opt(2026, 5, 2).unwrap();
+    let job = enqueue_job(&db, date, None, 3, "temp/jobs").await.unwrap();
+    let cancel = |id: i32| {
+        Request::post(format!("/consolidation-jobs/{}/cancel", id))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (status, body) = send(&app, cancel(job.id)).await;
+    let body: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["job"]["status"], "cancelled");
+    assert_eq!(body["job"]["cancel_requested"], true);
+
+    assert_eq!(send(&app, cancel(job.id)).await.0, StatusCode::CONFLICT);
+    assert_eq!(
+        send(&app, cancel(job.id + 1)).await.0,
+        StatusCode::NOT_FOUND
+    );
+
+    let cancelled = get_json(&app, "/consolidation-jobs?status=cancelled").await;
+    assert_eq!(cancelled["jobs"][0]["id"], job.id);
+    assert!(claim_job(&db).await.unwrap().is_none());
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn stores_each_teacher_once_across_concurrent_batches(db: PgPool) {
+    let date = NaiveDate::from_ymd_opt(2026, 5, 1).unwrap();
+    let repository = PgConsolidationRepository::new(db.clone(), date);
+    let rows: Vec<DialogueConsolidatedRow> = ["Magongo, Babalwa", "Ann Lee", "Ann Lee"]
+        .into_iter()
+        .map(|teacher_name| DialogueConsolidatedRow {
+            shift_group: "JEN 4".to_string(),
+            shift: "Morning".to_string(),
+            shift_type: "Regular".to_string(),
+            teacher_name: teacher_name.to_string(),
+            start_date: "2026-05-01 08:00".to_string(),
+            end_date: "2026-05-01 09:00".to_string(),
+        })
+        .collect();
+    let start = date.and_hms_opt(6, 0, 0).unwrap().and_utc();
+    let schedules: Vec<ScheduleRecord> = rows
+        .iter()
+        .map(|row| ScheduleRecord {
+            row,
+            start_date: start,
+            end_date: start + chrono::Duration::hours(1),
+        })
+        .collect();
+
+    let stored = tokio::join!(
+        repository.store_teachers(&schedules),
+        repository.store_teachers(&schedules),
+        repository.store_teachers(&schedules),
+    );
+
+    assert_eq!(stored.0.unwrap() + stored.1.unwrap() + stored.2.unwrap(), 2);
+    assert_eq!(repository.store_schedules(&schedules).await.unwrap(), 2);
+
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM teachers ORDER BY name")
+        .fetch_all(&db)
+        .await
+        .unwrap();
+
+    assert_eq!(names, ["Ann Lee", "Magongo, Babalwa"]);
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn merges_duplicate_teachers_only_when_applied(db: PgPool) {
+    // Databases from before teacher names were unique.
+    sqlx::query("DROP INDEX teachers_name_idx")
+        .execute(&db)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO teachers (id, name) VALUES (1, 'Ann Lee'), (2, 'Ann Lee'), (3, 'Bo')")
+        .execute(&db)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+            INSERT INTO schedules (teacher_id, shift_group, shift, shift_type, start_date, end_date)
+            VALUES
+                (1, 'JEN 4', 'T-1', 'Regular', '2026-05-01 08:00+02', '2026-05-01 09:00+02'),
+                (2, 'JEN 4', 'T-1', 'Regular', '2026-05-01 08:00+02', '2026-05-01 09:00+02'),
+                (2, 'JEN 4', 'T-2', 'Regular', '2026-05-01 10:00+02', '2026-05-01 11:00+02'),
+                (3, 'JEN 4', 'T-1', 'Regular', '2026-05-01 08:00+02', '2026-05-01 09:00+02'),
+                (3, 'JEN 4', 'T-1', 'Regular', '2026-05-01 08:00+02', '2026-05-01 09:00+02')
+        "#,
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    let teacher_ids = || async {
+        sqlx::query_scalar::<_, i32>("SELECT teacher_id FROM schedules ORDER BY id")
+            .fetch_all(&db)
+            .await
+            .unwrap()
+    };
+
+    let preview = merge_duplicate_teachers(&db, false).await.unwrap();
+
+    assert_eq!(
+        preview.teachers,
+        [DuplicateTeacher {
+            name: "Ann Lee".to_string(),
+            kept_id: 1,
+            merged_ids: vec![2],
+        }]
+    );
+    assert_eq!(preview.removed_schedules.len(), 1);
+    assert_eq!(teacher_ids().await, [1, 2, 2, 3, 3]);
+
+    let merge = merge_duplicate_teachers(&db, true).await.unwrap();
+
+    // Bo's own duplicate schedules were not caused by the merge and stay.
+    assert_eq!(merge, preview);
+    assert_eq!(merge.removed_schedules[0].teacher_id, 1);
+    assert_eq!(teacher_ids().await, [1, 1, 3, 3]);
+    assert_eq!(
+        merge_duplicate_teachers(&db, true).await.unwrap(),
+        TeacherMerge::default()
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn keeps_each_upload_in_its_own_directory(db: PgPool) {
+    let (app_state, _uploads) = app_state_with_uploads(&db);
+    let app = create_router(app_state.clone()).await;
+
+    for _ in 0..2 {
+        let (status, body) = send(
+            &app,
+            upload_request("2026-05-01", multipart_body("salesforce-us")),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK, "upload returned {}", body);
+    }
+
+    let directories: Vec<(i32, String)> =
+        sqlx::query_as("SELECT id, upload_directory FROM consolidation_jobs ORDER BY id")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+
+    assert_eq!(directories.len(), 2);
+
+    for (id, directory) in &directories {
+        let expected = app_state.env.server.jobs_directory().join(id.to_string());
+
+        assert_eq!(PathBuf::from(directory), expected);
+        assert!(expected.join("invoicing-report.csv").exists());
+    }
+
+    // Only one job per date runs at a time.
+    let claimed = claim_job(&db).await.unwrap().expect("first job");
+    assert_eq!(claimed.id, directories[0].0);
+    assert!(claim_job(&db).await.unwrap().is_none());
+
+    let mut truncated = multipart_body("salesforce-us");
+    truncated.truncate(truncated.len() / 2);
+    let (status, body) = send(&app, upload_request("2026-05-02", truncated)).await;
+    let body: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "validation_failed");
+
+    let unnamed = format!(
+        "--{0}\r\nContent-Disposition: form-data; name=\"../dialogue-1\"\r\n\r\nx\r\n--{0}--\r\n",
+        BOUNDARY
+    );
+    let (status, _) = send(&app, upload_request("2026-05-02", unnamed.into_bytes())).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let jobs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM consolidation_jobs")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+
+    assert_eq!(jobs, 2);
+
+    // Rejected uploads leave nothing staged behind.
+    let mut entries = std::fs::read_dir(app_state.env.server.jobs_directory())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    entries.sort();
+    assert_eq!(
+        entries,
+        directories
+            .iter()
+            .map(|(id, _)| id.to_string())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn rejects_uploads_over_the_body_limit(db: PgPool) {
+    let (app_state, _uploads) = app_state_with_uploads(&db);
+    let app = create_router(app_state.clone())
+        .await
+        .layer(DefaultBodyLimit::max(1024));
+
+    let (status, body) = send(
+        &app,
+        upload_request("2026-05-01", multipart_body("salesforce-us")),
+    )
+    .await;
+    let body: Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["code"], "payload_too_large");
+
+    let jobs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM consolidation_jobs")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+
+    assert_eq!(jobs, 0);
+}
+
+/// The header lines and body of a request to [webhook_receiver].
+type ReceivedRequest = (Vec<String>, Vec<u8>);
+
+/// Accepts one request per status in `statuses`, answers it with that status and returns the
+/// requests' headers and bodies.
+fn webhook_receiver(
+    statuses: &'static [u16],
+) -> (u16, std::thread::JoinHandle<Vec<ReceivedRequest>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let receiver = std::thread::spawn(move || {
+        statuses
+            .iter()
+            .map(|status| {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut headers = Vec::new();
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line.trim().is_empty() {
+                        break;
+                    }
+
+                    headers.push(line.trim().to_string());
+                }
+
+                let length = headers
+                    .iter()
+                    .find_map(|header| header_value(header, "Content-Length"))
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap();
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                            status
+                        )
+                        .as_bytes(),
+                    )
+                    .unwrap();
+
+                (headers, body)
+            })
+            .collect()
+    });
+
+    (port, receiver)
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn retries_signed_webhooks_for_finished_jobs(db: PgPool) {
+    let (mut app_state, _uploads) = app_state_with_uploads(&db);
+    app_state.env.webhooks.retry_delay = Duration::ZERO;
+
+    let app = create_router(app_state.clone()).await;
+    let (port, receiver) = webhook_receiver(&[500, 204]);
+    let request = Request::post("/admin/webhooks")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "url": format!("http://127.0.0.1:{}/hooks", port) }).to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(&app, request).await;
+    let created: Value = serde_json::from_str(&body).unwrap();
+    let secret = created["secret"].as_str().unwrap();
+
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert!(created["webhook"].get("secret").is_none());
+
+    let workers = TaskTracker::new();
+    let stop_workers = CancellationToken::new();
+
+    spawn_workers(
+        &app_state,
+        &workers,
+        stop_workers.clone(),
+        CancellationToken::new(),
+    );
+    upload(&app, &db, "salesforce-us", "2026-05-16").await;
+
+    let requests = tokio::task::spawn_blocking(move || receiver.join().unwrap())
+        .await
+        .unwrap();
+
+    stop_workers.cancel();
+    workers.close();
+    workers.wait().await;
+
+    let header = |headers: &[String], name: &str| {
+        headers
+            .iter()
+            .find_map(|header| header_value(header, name))
+            .unwrap()
+            .to_string()
+    };
+
+    for (headers, body) in &requests {
+        let timestamp = header(headers, "X-Webhook-Timestamp").parse().unwrap();
+
+        assert_eq!(
+            header(headers, "X-Webhook-Event"),
+            "consolidation.succeeded"
+        );
+        assert_eq!(
+            header(headers, "X-Webhook-Signature"),
+            sign(secret, timestamp, body)
+        );
+    }
+
+    let payload: Value = serde_json::from_slice(&requests[1].1).unwrap();
+
+    assert_eq!(requests[0].1, requests[1].1);
+    assert_eq!(payload["process_date"], "2026-05-16");
+    assert_eq!(payload["event"], "consolidation.succeeded");
+    assert!(payload["job_id"].is_i64());
+    assert!(payload["counts"]["parsed_shifts"].is_u64());
+
+    let deliveries = get_json(
+        &app,
+        &format!("/admin/webhooks/{}/deliveries", created["webhook"]["id"]),
+    )
+    .await;
+
+    for _ in 0..50 {
+        let delivered: Option<(i32, Option<i32>)> = sqlx::query_as(
+            "SELECT attempts, last_status_code FROM webhook_deliveries WHERE status = 'delivered'",
+        )
+        .fetch_optional(&db)
+        .await
+        .unwrap();
+
+        if let Some(delivered) = delivered {
+            assert_eq!(delivered, (2, Some(204)));
+            assert_eq!(deliveries["deliveries"].as_array().unwrap().len(), 1);
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!("the webhook delivery was not recorded");
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn emails_subscribed_reports_and_logs_the_delivery(db: PgPool) {
+    let (port, sink) = smtp_sink();
+    let mut app_state = app_state(&db);
+    app_state.env.email.send_hour = 0;
+    app_state.env.email.from = "reports@example.com".to_string();
+    app_state.env.email.smtp = Some(SmtpServer {
+        host: "127.0.0.1".to_string(),
+        port,
+        security: SmtpSecurity::None,
+        username: None,
+        password: None,
+        timeout: Duration::from_secs(5),
+    });
+
+    let app = create_router(app_state.clone()).await;
+    let request = Request::post("/admin/report-subscriptions")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "recipient": "manager@example.com",
+                "shift_groups": ["Group A", "Group A"],
+                "cadence": "daily",
+                "formats": ["csv", "xlsx"],
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(&app, request).await;
+    let created: Value = serde_json::from_str(&body).unwrap();
+    let id = &created["report_subscription"]["id"];
+
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!(
+        created["report_subscription"]["shift_groups"],
+        json!(["Group A"])
+    );
+
+    let workers = TaskTracker::new();
+    let stop_workers = CancellationToken::new();
+
+    spawn_workers(
+        &app_state,
+        &workers,
+        stop_workers.clone(),
+        CancellationToken::new(),
+    );
+
+    let (commands, message) = tokio::task::spawn_blocking(move || sink.join().unwrap())
+        .await
+        .unwrap();
+
+    let mut deliveries = Value::Null;
+
+    for _ in 0..50 {
+        deliveries = get_json(
+            &app,
+            &format!("/admin/report-subscriptions/{}/deliveries", id),
+        )
+        .await["deliveries"]
+            .take();
+
+        if deliveries.as_array().is_some_and(|rows| !rows.is_empty()) {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    stop_workers.cancel();
+    workers.close();
+    workers.wait().await;
+
+    let yesterday = (chrono::Utc::now().with_timezone(&Johannesburg).date_naive()
+        - chrono::Days::new(1))
+    .to_string();
+    let filename = format!("consolidated-report-Group A-{}-{}", yesterday, yesterday);
+
+    assert!(commands.contains(&"RCPT TO:<manager@example.com>".to_string()));
+    // Long names are split into RFC 2231 `filename*0=` parameters.
+    assert!(message.contains(&format!("=\"{}.csv\"", filename)));
+    assert!(message.contains(&format!("=\"{}.xlsx\"", filename)));
+    assert_eq!(deliveries[0]["status"], "sent");
+    assert_eq!(deliveries[0]["start_date"], yesterday);
+    assert_eq!(deliveries[0]["attachments"].as_array().unwrap().len(), 2);
+
+    let subscriptions = get_json(&app, "/admin/report-subscriptions").await;
+    assert_eq!(
+        subscriptions["report_subscriptions"][0]["last_period_end"],
+        yesterday
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn stores_scheduled_reports_as_artifacts(db: PgPool) {
+    let app_state = app_state(&db);
+    let app = create_router(app_state.clone()).await;
+    let workers = TaskTracker::new();
+
+    app_state
+        .report_scheduler
+        .start(&app_state, &workers)
+        .await
+        .unwrap();
+
+    let request = Request::post("/admin/report-schedules")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({
+                "name": "Every second",
+                "cron": "* * * * * *",
+                "period": "weekly",
+                "shift_groups": ["Group A"],
+                "formats": ["csv", "xlsx"],
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(&app, request).await;
+    let created: Value = serde_json::from_str(&body).unwrap();
+    let id = created["report_schedule"]["id"].as_i64().unwrap();
+
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let mut artifacts = Value::Null;
+
+    for _ in 0..50 {
+        artifacts = get_json(&app, &format!("/reports/artifacts?schedule_id={}", id)).await
+            ["artifacts"]
+            .take();
+
+        if artifacts.as_array().is_some_and(|rows| rows.len() >= 2) {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    app_state.report_scheduler.shutdown().await;
+    workers.close();
+    workers.wait().await;
+
+    let csv = artifacts
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|artifact| artifact["format"] == "csv")
+        .expect("a CSV artifact");
+    let live = get(
+        &app,
+        &format!(
+            "/generate-consolidated-report?start_date={}&end_date={}&shift_group=Group%20A",
+            csv["start_date"].as_str().unwrap(),
+            csv["end_date"].as_str().unwrap()
+        ),
+    )
+    .await;
+    let request = Request::get(format!("/reports/artifacts/{}/download", csv["id"]))
+        .body(Body::empty())
+        .unwrap();
+    let (status, downloaded) = send(&app, request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(downloaded, live);
+    assert_eq!(csv["size_bytes"], live.len());
+
+    let request = Request::delete(format!("/admin/report-schedules/{}", id))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::OK);
+
+    let kept = get_json(&app, "/reports/artifacts?shift_group=Group%20A&limit=1").await;
+    assert_eq!(kept["artifacts"][0]["schedule_id"], Value::Null);
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn runs_report_schedules_on_demand(db: PgPool) {
+    let app = create_router(app_state(&db)).await;
+    let create = |cron: &str| {
+        Request::post("/admin/report-schedules")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                json!({ "name": "Weekly", "cron": cron, "period": "weekly" }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    assert_eq!(
+        send(&app, create("every monday")).await.0,
+        StatusCode::BAD_REQUEST
+    );
+
+    let (status, body) = send(&app, create("0 0 6 * * Mon")).await;
+    let id = serde_json::from_str::<Value>(&body).unwrap()["report_schedule"]["id"].clone();
+
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let request = Request::post(format!("/admin/report-schedules/{}/run", id))
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, request).await;
+
+    // No schedules are stored, so no shift group has a report for the period.
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!(
+        serde_json::from_str::<Value>(&body).unwrap()["artifacts"],
+        json!([])
+    );
+
+    let schedules = get_json(&app, "/admin/report-schedules").await;
+    assert!(schedules["report_schedules"][0]["last_run_at"].is_string());
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn pages_sorts_and_filters_schedules(db: PgPool) {
+    sqlx::query(
+        r#"
+            WITH inserted AS (
+                INSERT INTO teachers (name) VALUES ('Ann Lee'), ('Bob 100%'), ('Cara Lee')
+                RETURNING id, name
+            )
+            INSERT INTO schedules (
+                teacher_id, shift_group, shift, shift_type, start_date, end_date
+            )
+            SELECT inserted.id, rows.shift_group, rows.shift, rows.shift_type,
+                rows.start_date::TIMESTAMPTZ, rows.start_date::TIMESTAMPTZ + INTERVAL '1 hour'
+            FROM inserted
+            JOIN (VALUES
+                ('Ann Lee', 'Group A', 'Morning', 'Regular', '2026-05-04 08:00+02'),
+                ('Ann Lee', 'Group B', 'Evening', 'Cover', '2026-05-04 18:00+02'),
+                ('Bob 100%', 'Group A', 'Morning', 'Training', '2026-05-04 09:00+02'),
+                ('Cara Lee', 'Group A', 'Evening', 'Regular', '2026-05-04 19:00+02'),
+                ('Cara Lee', 'Group A', 'Morning', 'Regular', '2026-05-05 08:00+02')
+            ) AS rows (name, shift_group, shift, shift_type, start_date)
+            ON rows.name = inserted.name
+        "#,
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    let app = create_router(app_state(&db)).await;
+    let schedules = |query: &str| {
+        let app = app.clone();
+        let uri = format!(
+            "/schedules?start_date=2026-05-04%2000:00:00&end_date=2026-05-04%2023:59:59&{}",
+            query
+        );
+
+        async move { get_json(&app, &uri).await }
+    };
+    let teachers = |body: &Value| {
+        body["schedules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["teacher_name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let all = schedules("").await;
+    assert_eq!(all["total"], 4);
+    assert_eq!(
+        teachers(&all),
+        ["Ann Lee", "Ann Lee", "Bob 100%", "Cara Lee"]
+    );
+
+    let page = schedules("sort=start_date&order=desc&limit=2&offset=1").await;
+    assert_eq!(page["total"], 4);
+    assert_eq!(page["schedules"][0]["start_date"], "2026-05-04T18:00:00");
+    assert_eq!(page["schedules"][1]["start_date"], "2026-05-04T09:00:00");
+
+    let past_the_end = schedules("offset=10").await;
+    assert_eq!(past_the_end["total"], 4);
+    assert_eq!(past_the_end["schedules"], json!([]));
+
+    let group = schedules("shift_group=Group%20A&shift_type=Regular,%20Training").await;
+    assert_eq!(teachers(&group), ["Ann Lee", "Bob 100%", "Cara Lee"]);
+
+    let search = schedules("teacher=LEE&shift=Evening").await;
+    assert_eq!(teachers(&search), ["Ann Lee", "Cara Lee"]);
+
+    // `%` is matched literally rather than as a wildcard.
+    assert_eq!(teachers(&schedules("teacher=0%25").await), ["Bob 100%"]);
+
+    let id = sqlx::query_scalar::<_, i32>("SELECT id FROM teachers WHERE name = 'Bob 100%'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(
+        teachers(&schedules(&format!("teacher_id={}", id)).await),
+        ["Bob 100%"]
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn returns_every_schedule_unless_a_page_is_asked_for(db: PgPool) {
+    sqlx::query(
+        r#"
+            WITH teacher AS (INSERT INTO teachers (name) VALUES ('Ann Lee') RETURNING id)
+            INSERT INTO schedules (
+                teacher_id, shift_group, shift, shift_type, start_date, end_date
+            )
+            SELECT id, 'Group A', 'T-' || n, 'Regular',
+                '2026-05-04 08:00+02'::TIMESTAMPTZ + n * INTERVAL '1 minute',
+                '2026-05-04 09:00+02'::TIMESTAMPTZ + n * INTERVAL '1 minute'
+            FROM teacher, GENERATE_SERIES(1, 150) AS n
+        "#,
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    let app = create_router(app_state(&db)).await;
+    let uri = "/schedules?start_date=2026-05-04&end_date=2026-05-04&shift_group=Group%20A";
+
+    let all = get_json(&app, uri).await;
+    assert_eq!(all["total"], 150);
+    assert_eq!(all["limit"], Value::Null);
+    assert_eq!(all["schedules"].as_array().unwrap().len(), 150);
+
+    let page = get_json(&app, &format!("{}&offset=0", uri)).await;
+    assert_eq!(page["limit"], 100);
+    assert_eq!(page["schedules"].as_array().unwrap().len(), 100);
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn every_endpoint_includes_shifts_overlapping_the_range(db: PgPool) {
+    sqlx::query(
+        r#"
+            WITH teacher AS (INSERT INTO teachers (name) VALUES ('Night Owl') RETURNING id)
+            INSERT INTO schedules (
+                teacher_id, shift_group, shift, shift_type, start_date, end_date
+            )
+            SELECT id, 'Group N', 'Night', 'Regular', '2026-05-04 22:00+02', '2026-05-05 02:00+02'
+            FROM teacher
+        "#,
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    let app = create_router(app_state(&db)).await;
+
+    for day in ["2026-05-04", "2026-05-05"] {
+        let schedules = get_json(
+            &app,
+            &format!("/schedules?start_date={}&end_date={}", day, day),
+        )
+        .await;
+        assert_eq!(schedules["total"], 1, "{}", day);
+
+        let report = get(
+            &app,
+            &format!(
+                "/generate-consolidated-report?start_date={}&end_date={}&shift_group=Group%20N",
+                day, day
+            ),
+        )
+        .await;
+        assert!(report.contains("Night Owl,Night,Regular"), "{}", report);
+    }
+
+    // The range is half-open, so a shift ending as it starts is not in it.
+    let after = get_json(
+        &app,
+        "/schedules?start_date=2026-05-05T02:00&end_date=2026-05-05T03:00",
+    )
+    .await;
+    assert_eq!(after["total"], 0);
+
+    // UTC 23:30 is 01:30 in Johannesburg, during the shift.
+    let utc = get_json(
+        &app,
+        "/schedules?start_date=2026-05-04T23:30Z&end_date=2026-05-04T23:31Z",
+    )
+    .await;
+    assert_eq!(utc["total"], 1);
+    assert_eq!(utc["timezone"], "Africa/Johannesburg");
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn backfills_last_processed_dates_in_each_groups_timezone(db: PgPool) {
+    for statement in [
+        r#"
+            INSERT INTO shift_groups (name, timezone, last_processed_date)
+            VALUES ('A', NULL, NULL), ('B', 'America/New_York', NULL), ('C', NULL, '2026-04-01')
+        "#,
+        "INSERT INTO teachers (id, name) VALUES (1, 'Ann Lee')",
+        r#"
+            INSERT INTO schedules (teacher_id, shift_group, shift, shift_type, start_date, end_date)
+            SELECT 1, name, 'T-1', 'Regular', '2026-05-01 22:30Z', '2026-05-01 23:30Z'
+            FROM shift_groups
+        "#,
+    ] {
+        sqlx::query(statement).execute(&db).await.unwrap();
+    }
+
+    let dates = || async {
+        sqlx::query_scalar::<_, Option<NaiveDate>>(
+            "SELECT last_processed_date FROM shift_groups ORDER BY name",
+        )
+        .fetch_all(&db)
+        .await
+        .unwrap()
+    };
+    let date = |day| NaiveDate::from_ymd_opt(2026, 5, day);
+
+    assert_eq!(
+        backfill_last_processed_dates(&db, Johannesburg)
+            .await
+            .unwrap(),
+        2
+    );
+    // 23:30Z is already the 2nd in Johannesburg but still the 1st in New York.
+    assert_eq!(
+        dates().await,
+        [date(2), date(1), NaiveDate::from_ymd_opt(2026, 4, 1)]
+    );
+    assert_eq!(
+        backfill_last_processed_dates(&db, Johannesburg)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn manages_shift_groups(db: PgPool) {
+    let app = create_router(app_state(&db)).await;
+    let write = |method: Method, uri: String, body: Value| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let (status, body) = send(
+        &app,
+        write(
+            Method::POST,
+            "/shift-groups".to_string(),
+            json!({ "name": " JEN 4 ", "region": "Gauteng", "timezone": "Europe/Lisbon" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+    let created = serde_json::from_str::<Value>(&body).unwrap()["shift_group"].clone();
+    assert_eq!(created["name"], "JEN 4");
+    assert_eq!(created["active"], true);
+
+    let (status, _) = send(
+        &app,
+        write(
+            Method::POST,
+            "/shift-groups".to_string(),
+            json!({ "name": "JEN 4" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(
+        &app,
+        write(
+            Method::POST,
+            "/shift-groups".to_string(),
+            json!({ "name": "JEN 5", "timezone": "Mars/Olympus" }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains(r#""field":"timezone""#), "{}", body);
+
+    let uri = format!("/shift-groups/{}", created["id"]);
+    let (status, body) = send(
+        &app,
+        write(
+            Method::PUT,
+            uri.clone(),
+            json!({ "display_name": "Jen Four", "manager": "Sam", "active": false }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let date = NaiveDate::from_ymd_opt(2026, 5, 4).unwrap();
+    let repository = PgConsolidationRepository::new(db.clone(), date);
+    let rows: Vec<DialogueConsolidatedRow> = ["Morning", "Evening"]
+        .into_iter()
+        .map(|shift| DialogueConsolidatedRow {
+            shift_group: "JEN 4".to_string(),
+            shift: shift.to_string(),
+            shift_type: "Regular".to_string(),
+            teacher_name: "Ann Lee".to_string(),
+            start_date: "2026-05-04 08:00".to_string(),
+            end_date: "2026-05-04 09:00".to_string(),
+        })
+        .collect();
+    let start = date.and_hms_opt(6, 0, 0).unwrap().and_utc();
+    let schedules: Vec<ScheduleRecord> = rows
+        .iter()
+        .map(|row| ScheduleRecord {
+            row,
+            start_date: start,
+            end_date: start + chrono::Duration::hours(1),
+        })
+        .collect();
+
+    repository.store_teachers(&schedules).await.unwrap();
+    assert_eq!(
+        repository.store_schedules(&schedules[..1]).await.unwrap(),
+        1
+    );
+    assert_eq!(repository.store_schedules(&schedules).await.unwrap(), 1);
+    assert_eq!(repository.store_schedules(&schedules).await.unwrap(), 0);
+
+    let updated = get_json(&app, &uri).await["shift_group"].clone();
+    assert_eq!(updated["display_name"], "Jen Four");
+    assert_eq!(updated["manager"], "Sam");
+    assert_eq!(updated["region"], Value::Null);
+    assert_eq!(updated["timezone"], Value::Null);
+    assert_eq!(updated["last_processed_date"], "2026-05-04");
+    assert_eq!(updated["schedule_count"], 2);
+    assert_eq!(updated["teacher_count"], 1);
+    assert_eq!(updated["shift_group"], "JEN 4");
+
+    let active = get_json(&app, "/shift-groups?active=true").await;
+    assert_eq!(active["shift_groups"], json!([]));
+
+    let request = Request::delete(uri.as_str()).body(Body::empty()).unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::CONFLICT);
+
+    sqlx::query("DELETE FROM schedules")
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let request = Request::delete(uri.as_str()).body(Body::empty()).unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::OK);
+
+    let request = Request::get(uri.as_str()).body(Body::empty()).unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+#[ignore = "needs DATABASE_URL to point at a Postgres server"]
+async fn renders_reports_in_the_shift_group_timezone(db: PgPool) {
+    let app = create_router(app_state(&db)).await;
+    let request = Request::post("/shift-groups")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "name": "Lisbon", "timezone": "Europe/Lisbon" }).to_string(),
+        ))
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::CREATED);
+
+    let date = NaiveDate::from_ymd_opt(2026, 5, 5).unwrap();
+    let repository = PgConsolidationRepository::new(db.clone(), date);
+    let row = DialogueConsolidatedRow {
+        shift_group: "Lisbon".to_string(),
+        shift: "Night".to_string(),
+        shift_type: "Regular".to_string(),
+        teacher_name: "Ann Lee".to_string(),
+        start_date: "2026-05-05 00:30".to_string(),
+        end_date: "2026-05-05 01:30".to_string(),
+    };
+    // 00:30 in Lisbon, 01:30 in Johannesburg.
+    let start = NaiveDate::from_ymd_opt(2026, 5, 4)
+        .unwrap()
+        .and_hms_opt(23, 30, 0)
+        .unwrap()
+        .and_utc();
+    let schedules = [ScheduleRecord {
+        row: &row,
+        start_date: start,
+        end_date: start + chrono::Duration::hours(1),
+    }];
+    repository.store_teachers(&schedules).await.unwrap();
+    repository.store_schedules(&schedules).await.unwrap();
+
+    let uri = "/generate-consolidated-report?start_date=2026-05-05&end_date=2026-05-05&shift_group=Lisbon";
+    let report = get(&app, uri).await;
+    assert!(report.contains("2026-05-05 00:30:00"), "{}", report);
+
+    let report = get(&app, &format!("{}&tz=Africa/Johannesburg", uri)).await;
+    assert!(report.contains("2026-05-05 01:30:00"), "{}", report);
+
+    let rendered = render_reports(
+        &db,
+        &["Lisbon".to_string()],
+        (date, date),
+        &[CSV_FORMAT.to_string()],
+        Johannesburg,
+    )
+    .await
+    .unwrap();
+    let csv = String::from_utf8(rendered[0].data.clone()).unwrap();
+    assert!(csv.contains("2026-05-05 00:30:00"), "{}", csv);
+}
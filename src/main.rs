@@ -1,35 +1,69 @@
-use std::net::SocketAddr;
-
 use anyhow::Error;
 use axum::{
     extract::DefaultBodyLimit,
     http::{
         header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-        HeaderValue, Method,
+        Method,
     },
 };
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use sqlx::{migrate::Migrator, postgres::PgPoolOptions, Pool, Postgres};
 use tokio::net::TcpListener;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing_appender::rolling;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
-use crate::{config::Config, router::create_router};
+use crate::{
+    config::Config,
+    metrics::Metrics,
+    router::create_router,
+    scheduler::ReportScheduler,
+    utils::{
+        consolidation_jobs::requeue_interrupted_jobs, shift_groups::backfill_last_processed_dates,
+    },
+};
 
+mod cli;
 mod config;
+mod date_range;
+mod error;
+#[cfg(test)]
+mod integration_tests;
+mod metrics;
 mod router;
 mod routes;
+mod scheduler;
+mod shutdown;
 mod utils;
+mod worker;
+
+/// The migrations in `migrations/`, embedded at build time.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Pool<Postgres>,
     pub env: Config,
+    pub metrics: Metrics,
+    pub report_scheduler: ReportScheduler,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let command = cli::parse(&args).unwrap_or_else(|error| {
+        eprintln!("🔥 {}\n\n{}", error, cli::USAGE);
+        std::process::exit(2);
+    });
+
+    if command != cli::Command::Serve {
+        cli::init_logging();
+
+        return cli::run(command).await;
+    }
+
     println!(
         r#"
  _                            _  __   ___       __ _                       
@@ -42,7 +76,9 @@ Automatic Reports Consolidation API © 2023
         "#
     );
 
-    let info_log_file = rolling::daily("./logs", "log");
+    let config = Config::init();
+
+    let info_log_file = rolling::daily(&config.server.logs_directory, "log");
 
     let info_log = tracing_subscriber::fmt::layer()
         .compact()
@@ -55,8 +91,9 @@ Automatic Reports Consolidation API © 2023
         .with_thread_ids(true)
         .json()
         .with_filter(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG")
-                .unwrap_or_else(|_| "sergio_ar_api=debug,tower_http=debug,sqlx=debug".into()),
+            std::env::var("RUST_LOG").unwrap_or_else(|_| {
+                "sergio_ar_api=debug,consolidation=debug,tower_http=debug,sqlx=debug".into()
+            }),
         ));
 
     tracing_subscriber::registry()
@@ -70,16 +107,15 @@ Automatic Reports Consolidation API © 2023
                 .with_ansi(true)
                 .with_thread_ids(true)
                 .with_filter(tracing_subscriber::EnvFilter::new(
-                    std::env::var("RUST_LOG")
-                        .unwrap_or_else(|_| "sergio_ar_api=debug,tower_http=debug".into()),
+                    std::env::var("RUST_LOG").unwrap_or_else(|_| {
+                        "sergio_ar_api=debug,consolidation=debug,tower_http=debug".into()
+                    }),
                 )),
         )
         .init();
 
-    let config = Config::init();
-
     let pool = match PgPoolOptions::new()
-        .max_connections(32)
+        .max_connections(config.server.database_max_connections)
         .connect(&config.database_url)
         .await
     {
@@ -93,41 +129,108 @@ Automatic Reports Consolidation API © 2023
         }
     };
 
-    let migration_result = sqlx::migrate!().run(&pool).await;
+    let migration_result = MIGRATOR.run(&pool).await;
 
     match migration_result {
         Ok(_) => {
             tracing::info!("✅ Database migration successful!");
 
+            match requeue_interrupted_jobs(&pool).await {
+                Ok(0) => {}
+                Ok(count) => {
+                    tracing::warn!(
+                        "⚠️ Found {} interrupted consolidation job(s). Requeued those with attempts left.",
+                        count
+                    );
+                }
+                Err(err) => {
+                    tracing::error!(
+                        "🔥 Failed to check for interrupted consolidations: {:?}",
+                        err
+                    );
+                }
+            }
+
+            match backfill_last_processed_dates(&pool, config.app_timezone).await {
+                Ok(0) => {}
+                Ok(count) => {
+                    tracing::info!(
+                        "✅ Set the last processed date of {} shift group(s) from their schedules.",
+                        count
+                    );
+                }
+                Err(err) => {
+                    tracing::error!("🔥 Failed to backfill last processed dates: {:?}", err);
+                }
+            }
+
             let app_state = AppState {
                 db: pool.clone(),
                 env: config.clone(),
+                metrics: Metrics::new(),
+                report_scheduler: ReportScheduler::default(),
             };
 
+            let workers = TaskTracker::new();
+            let stop_workers = CancellationToken::new();
+            let interrupt_workers = CancellationToken::new();
+
+            worker::spawn_workers(
+                &app_state,
+                &workers,
+                stop_workers.clone(),
+                interrupt_workers.clone(),
+            );
+
+            match app_state.report_scheduler.start(&app_state, &workers).await {
+                Ok(count) => tracing::info!("🕐 Scheduled {} report schedule(s).", count),
+                Err(err) => {
+                    tracing::error!("🔥 Failed to start the report scheduler: {:?}", err);
+                }
+            }
+
             let app = create_router(app_state.clone()).await;
 
             let cors = CorsLayer::new()
-                .allow_origin([
-                    "http://localhost:3001".parse::<HeaderValue>()?,
-                    "http://localhost:5173".parse::<HeaderValue>()?,
-                    "https://autoreports-app.corecapital.co.za".parse::<HeaderValue>()?,
-                ])
+                .allow_origin(config.server.cors_allowed_origins.clone())
                 .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
                 .allow_credentials(true)
                 .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
 
             let app = app
-                .layer(DefaultBodyLimit::max(100_000_000))
+                .layer(DefaultBodyLimit::max(config.server.body_limit_bytes))
                 .layer(cors)
                 .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()));
 
-            let address = SocketAddr::from(([0, 0, 0, 0], 4000));
+            let address = config.server.bind_address;
 
             let listener = TcpListener::bind(&address).await?;
 
             tracing::info!("🚀 Listening on http://{}", address);
 
-            axum::serve(listener, app.into_make_service()).await?;
+            axum::serve(listener, app.into_make_service())
+                .with_graceful_shutdown(shutdown::signal())
+                .await?;
+
+            stop_workers.cancel();
+            app_state.report_scheduler.shutdown().await;
+
+            if shutdown::drain(&workers, config.server.shutdown_timeout).await {
+                tracing::info!("✅ Every consolidation finished. Shutting down.");
+            } else {
+                // Each worker requeues its own job once it has let go of it, so no job is
+                // requeued while a worker may still write to it.
+                tracing::warn!("⚠️ Gave up waiting for consolidations. Interrupting them.");
+                interrupt_workers.cancel();
+
+                if shutdown::drain(&workers, config.server.shutdown_timeout).await {
+                    tracing::info!("✅ Interrupted consolidations were requeued. Shutting down.");
+                } else {
+                    tracing::warn!(
+                        "⚠️ Some workers did not stop. Their jobs are requeued on the next start."
+                    );
+                }
+            }
 
             Ok(())
         }
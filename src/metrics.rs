@@ -0,0 +1,479 @@
+//! In-process counters, histograms and gauges, served at `/metrics` in the Prometheus text
+//! format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDate;
+use consolidation::ConsolidationSummary;
+use sqlx::{Pool, Postgres};
+
+use crate::AppState;
+
+const HTTP_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+const CONSOLIDATION_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations at or below each bucket bound, not cumulative.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, bounds: &[f64], value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; bounds.len()];
+        }
+
+        if let Some(index) = bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[index] += 1;
+        }
+
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    http_requests: BTreeMap<(String, String, u16), u64>,
+    http_durations: BTreeMap<(String, String), Histogram>,
+    consolidation_runs: BTreeMap<&'static str, u64>,
+    consolidation_durations: Histogram,
+    ingested_rows: BTreeMap<(&'static str, &'static str), u64>,
+    ingestion_issues: BTreeMap<String, u64>,
+    last_processed_dates: BTreeMap<String, i64>,
+}
+
+/// Shared by the router and the background consolidation tasks. Series only exist from the
+/// first time they are recorded, so counters and gauges start over when the process restarts.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let mut registry = self.registry.lock().unwrap();
+
+        *registry
+            .http_requests
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        registry
+            .http_durations
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(HTTP_BUCKETS, elapsed.as_secs_f64());
+    }
+
+    /// Records a finished consolidation run. Row counts and processing dates only move when the
+    /// run succeeded.
+    pub fn record_consolidation(
+        &self,
+        process_date: NaiveDate,
+        elapsed: Duration,
+        summary: Option<&ConsolidationSummary>,
+    ) {
+        let mut registry = self.registry.lock().unwrap();
+        let outcome = if summary.is_some() {
+            "success"
+        } else {
+            "failure"
+        };
+
+        *registry.consolidation_runs.entry(outcome).or_default() += 1;
+        registry
+            .consolidation_durations
+            .observe(CONSOLIDATION_BUCKETS, elapsed.as_secs_f64());
+
+        let Some(summary) = summary else {
+            return;
+        };
+
+        for (file_type, outcome, rows) in [
+            ("dialogue", "parsed", summary.parsed_shifts),
+            ("dialogue", "inserted", summary.new_shifts),
+            ("dialogue", "skipped", summary.skipped_shifts),
+            ("invoicing", "parsed", summary.parsed_invoices),
+            ("invoicing", "inserted", summary.inserted_invoices),
+            ("invoicing", "updated", summary.updated_invoices),
+            ("invoicing", "skipped", summary.skipped_invoices),
+        ] {
+            *registry
+                .ingested_rows
+                .entry((file_type, outcome))
+                .or_default() += rows as u64;
+        }
+
+        for (kind, count) in &summary.diagnostics.counts {
+            *registry.ingestion_issues.entry(kind.clone()).or_default() += *count as u64;
+        }
+
+        let timestamp = process_date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight exists")
+            .and_utc()
+            .timestamp();
+
+        for shift_group in &summary.shift_groups {
+            let last = registry
+                .last_processed_dates
+                .entry(shift_group.clone())
+                .or_insert(timestamp);
+
+            *last = (*last).max(timestamp);
+        }
+    }
+
+    /// Renders every series, plus the current state of `db`'s connection pool.
+    pub fn render(&self, db: &Pool<Postgres>) -> String {
+        let registry = self.registry.lock().unwrap();
+        let mut output = String::new();
+
+        header(
+            &mut output,
+            "sergio_ar_http_requests_total",
+            "counter",
+            "HTTP requests by method, matched route and status.",
+        );
+        for ((method, route, status), count) in &registry.http_requests {
+            sample(
+                &mut output,
+                "sergio_ar_http_requests_total",
+                &[
+                    ("method", method.clone()),
+                    ("route", route.clone()),
+                    ("status", status.to_string()),
+                ],
+                *count as f64,
+            );
+        }
+
+        header(
+            &mut output,
+            "sergio_ar_http_request_duration_seconds",
+            "histogram",
+            "Time to produce an HTTP response, by method and matched route.",
+        );
+        for ((method, route), histogram) in &registry.http_durations {
+            histogram_samples(
+                &mut output,
+                "sergio_ar_http_request_duration_seconds",
+                vec![("method", method.clone()), ("route", route.clone())],
+                HTTP_BUCKETS,
+                histogram,
+            );
+        }
+
+        header(
+            &mut output,
+            "sergio_ar_consolidation_runs_total",
+            "counter",
+            "Finished consolidation runs by outcome.",
+        );
+        for (outcome, count) in &registry.consolidation_runs {
+            sample(
+                &mut output,
+                "sergio_ar_consolidation_runs_total",
+                &[("outcome", outcome.to_string())],
+                *count as f64,
+            );
+        }
+
+        header(
+            &mut output,
+            "sergio_ar_consolidation_duration_seconds",
+            "histogram",
+            "Wall-clock time of consolidation runs, successful or not.",
+        );
+        histogram_samples(
+            &mut output,
+            "sergio_ar_consolidation_duration_seconds",
+            Vec::new(),
+            CONSOLIDATION_BUCKETS,
+            &registry.consolidation_durations,
+        );
+
+        header(
+            &mut output,
+            "sergio_ar_ingested_rows_total",
+            "counter",
+            "Rows from successful runs by file type and outcome.",
+        );
+        for ((file_type, outcome), count) in &registry.ingested_rows {
+            sample(
+                &mut output,
+                "sergio_ar_ingested_rows_total",
+                &[
+                    ("file_type", file_type.to_string()),
+                    ("outcome", outcome.to_string()),
+                ],
+                *count as f64,
+            );
+        }
+
+        header(
+            &mut output,
+            "sergio_ar_ingestion_issues_total",
+            "counter",
+            "Rows loaded with a caveat or skipped, by issue kind.",
+        );
+        for (kind, count) in &registry.ingestion_issues {
+            sample(
+                &mut output,
+                "sergio_ar_ingestion_issues_total",
+                &[("kind", kind.clone())],
+                *count as f64,
+            );
+        }
+
+        header(
+            &mut output,
+            "sergio_ar_last_processed_date_timestamp_seconds",
+            "gauge",
+            "Latest process date (midnight UTC) successfully consolidated, by shift group.",
+        );
+        for (shift_group, timestamp) in &registry.last_processed_dates {
+            sample(
+                &mut output,
+                "sergio_ar_last_processed_date_timestamp_seconds",
+                &[("shift_group", shift_group.clone())],
+                *timestamp as f64,
+            );
+        }
+
+        let size = db.size();
+        let idle = db.num_idle() as u32;
+
+        header(
+            &mut output,
+            "sergio_ar_db_pool_connections",
+            "gauge",
+            "Open database connections by state.",
+        );
+        sample(
+            &mut output,
+            "sergio_ar_db_pool_connections",
+            &[("state", "idle".to_string())],
+            idle as f64,
+        );
+        sample(
+            &mut output,
+            "sergio_ar_db_pool_connections",
+            &[("state", "in_use".to_string())],
+            size.saturating_sub(idle) as f64,
+        );
+
+        header(
+            &mut output,
+            "sergio_ar_db_pool_max_connections",
+            "gauge",
+            "Upper bound on open database connections.",
+        );
+        sample(
+            &mut output,
+            "sergio_ar_db_pool_max_connections",
+            &[],
+            db.options().get_max_connections() as f64,
+        );
+
+        output
+    }
+}
+
+fn header(output: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(output, "# HELP {} {}", name, help).unwrap();
+    writeln!(output, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn sample(output: &mut String, name: &str, labels: &[(&str, String)], value: f64) {
+    output.push_str(name);
+
+    if !labels.is_empty() {
+        let labels = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape_label(value)))
+            .collect::<Vec<_>>();
+
+        write!(output, "{{{}}}", labels.join(",")).unwrap();
+    }
+
+    writeln!(output, " {}", value).unwrap();
+}
+
+fn histogram_samples(
+    output: &mut String,
+    name: &str,
+    labels: Labels,
+    bounds: &[f64],
+    histogram: &Histogram,
+) {
+    let bucket_name = format!("{}_bucket", name);
+    let mut cumulative = 0;
+
+    for (index, bound) in bounds.iter().enumerate() {
+        cumulative += histogram.buckets.get(index).copied().unwrap_or(0);
+
+        let mut bucket_labels = labels.clone();
+        bucket_labels.push(("le", bound.to_string()));
+        sample(output, &bucket_name, &bucket_labels, cumulative as f64);
+    }
+
+    let mut bucket_labels = labels.clone();
+    bucket_labels.push(("le", "+Inf".to_string()));
+    sample(output, &bucket_name, &bucket_labels, histogram.count as f64);
+
+    sample(output, &format!("{}_sum", name), &labels, histogram.sum);
+    sample(
+        output,
+        &format!("{}_count", name),
+        &labels,
+        histogram.count as f64,
+    );
+}
+
+/// Counts and times every request by its route template, so `/admin/column-mappings/:id` is
+/// one series however many IDs are requested. Unmatched requests are grouped as `unmatched`.
+pub async fn track_requests(
+    State(metrics): State<Metrics>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    metrics.record_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+
+    response
+}
+
+pub async fn metrics(State(app_state): State<AppState>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        app_state.metrics.render(&app_state.db),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::NaiveDate;
+    use consolidation::ConsolidationSummary;
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::Metrics;
+
+    #[tokio::test]
+    async fn renders_prometheus_text() {
+        let db = PgPoolOptions::new()
+            .max_connections(7)
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let metrics = Metrics::new();
+        let summary = ConsolidationSummary {
+            parsed_shifts: 3,
+            new_shifts: 2,
+            shift_groups: vec!["JEN \"4\"".to_string()],
+            ..ConsolidationSummary::default()
+        };
+
+        metrics.record_request("GET", "/schedules", 200, Duration::from_millis(30));
+        metrics.record_request("GET", "/schedules", 200, Duration::from_secs(20));
+        metrics.record_consolidation(
+            NaiveDate::from_ymd_opt(2026, 5, 2).unwrap(),
+            Duration::from_secs(3),
+            Some(&summary),
+        );
+        metrics.record_consolidation(
+            NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(),
+            Duration::from_secs(1),
+            Some(&summary),
+        );
+
+        let rendered = metrics.render(&db);
+
+        for line in [
+            "# TYPE sergio_ar_http_requests_total counter",
+            r#"sergio_ar_http_requests_total{method="GET",route="/schedules",status="200"} 2"#,
+            r#"sergio_ar_http_request_duration_seconds_bucket{method="GET",route="/schedules",le="0.05"} 1"#,
+            r#"sergio_ar_http_request_duration_seconds_bucket{method="GET",route="/schedules",le="10"} 1"#,
+            r#"sergio_ar_http_request_duration_seconds_bucket{method="GET",route="/schedules",le="+Inf"} 2"#,
+            r#"sergio_ar_consolidation_runs_total{outcome="success"} 2"#,
+            "sergio_ar_consolidation_duration_seconds_sum 4",
+            r#"sergio_ar_ingested_rows_total{file_type="dialogue",outcome="parsed"} 6"#,
+            r#"sergio_ar_ingested_rows_total{file_type="invoicing",outcome="updated"} 0"#,
+            r#"sergio_ar_last_processed_date_timestamp_seconds{shift_group="JEN \"4\""} 1777680000"#,
+            r#"sergio_ar_db_pool_connections{state="in_use"} 0"#,
+            "sergio_ar_db_pool_max_connections 7",
+        ] {
+            assert!(
+                rendered.lines().any(|rendered_line| rendered_line == line),
+                "missing {:?} in\n{}",
+                line,
+                rendered
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_runs_only_count_the_run() {
+        let db = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let metrics = Metrics::new();
+
+        metrics.record_consolidation(
+            NaiveDate::from_ymd_opt(2026, 5, 2).unwrap(),
+            Duration::from_secs(2),
+            None,
+        );
+
+        let rendered = metrics.render(&db);
+
+        assert!(rendered.contains(r#"sergio_ar_consolidation_runs_total{outcome="failure"} 1"#));
+        assert!(!rendered.contains("sergio_ar_ingested_rows_total{"));
+        assert!(!rendered.contains("sergio_ar_last_processed_date_timestamp_seconds{"));
+    }
+}
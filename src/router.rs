@@ -1,44 +1,310 @@
 use axum::{
+    extract::State,
     http::StatusCode,
+    middleware,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
-use serde_json::{json, Value};
+use serde_json::json;
 
 use crate::{
-    routes::{consolidator, data, efficiency},
+    error::{self, ApiError},
+    metrics,
+    routes::{admin, consolidator, data, efficiency, reports, system},
     AppState,
 };
 
 pub async fn create_router(app_state: AppState) -> Router {
     Router::new()
         .route("/", get(index))
+        .route("/healthz", get(system::health::healthz))
+        .route("/readyz", get(system::health::readyz))
+        .route("/version", get(system::health::version))
+        .route("/metrics", get(metrics::metrics))
         .route(
             "/upload-and-process",
             post(consolidator::upload_and_process::upload_and_process),
         )
+        .route(
+            "/consolidation-jobs",
+            get(consolidator::jobs::list_consolidation_jobs),
+        )
+        .route(
+            "/consolidation-jobs/:id",
+            get(consolidator::jobs::get_consolidation_job),
+        )
+        .route(
+            "/consolidation-jobs/:id/cancel",
+            post(consolidator::jobs::cancel_consolidation_job),
+        )
         .route(
             "/generate-consolidated-report",
             get(efficiency::generate_consolidated_report::generate_consolidated_report),
         )
-        .route("/shift-groups", get(data::shift_groups::get_shift_groups))
+        .route(
+            "/shift-groups",
+            get(data::shift_groups::get_shift_groups).post(data::shift_groups::create_shift_group),
+        )
+        .route(
+            "/shift-groups/:id",
+            get(data::shift_groups::get_shift_group)
+                .put(data::shift_groups::update_shift_group)
+                .delete(data::shift_groups::delete_shift_group),
+        )
         .route("/schedules", get(data::schedules::get_schedules))
+        .route(
+            "/admin/column-mappings",
+            get(admin::column_mappings::list_column_mappings)
+                .post(admin::column_mappings::create_column_mapping),
+        )
+        .route(
+            "/admin/column-mappings/:id",
+            delete(admin::column_mappings::delete_column_mapping),
+        )
+        .route(
+            "/reports/artifacts",
+            get(reports::artifacts::list_report_artifacts),
+        )
+        .route(
+            "/reports/artifacts/:id/download",
+            get(reports::artifacts::download_report_artifact),
+        )
+        .route(
+            "/admin/report-schedules",
+            get(admin::report_schedules::list_report_schedules)
+                .post(admin::report_schedules::create_report_schedule),
+        )
+        .route(
+            "/admin/report-schedules/:id",
+            delete(admin::report_schedules::delete_report_schedule),
+        )
+        .route(
+            "/admin/report-schedules/:id/run",
+            post(admin::report_schedules::run_report_schedule),
+        )
+        .route(
+            "/admin/report-subscriptions",
+            get(admin::report_subscriptions::list_report_subscriptions)
+                .post(admin::report_subscriptions::create_report_subscription),
+        )
+        .route(
+            "/admin/report-subscriptions/:id",
+            delete(admin::report_subscriptions::delete_report_subscription),
+        )
+        .route(
+            "/admin/report-subscriptions/:id/deliveries",
+            get(admin::report_subscriptions::list_report_deliveries),
+        )
+        .route(
+            "/admin/webhooks",
+            get(admin::webhooks::list_webhooks).post(admin::webhooks::create_webhook),
+        )
+        .route(
+            "/admin/webhooks/:id",
+            delete(admin::webhooks::delete_webhook),
+        )
+        .route(
+            "/admin/webhooks/:id/deliveries",
+            get(admin::webhooks::list_webhook_deliveries),
+        )
         .fallback(fallback)
+        .layer(middleware::from_fn_with_state(
+            app_state.metrics.clone(),
+            metrics::track_requests,
+        ))
+        .layer(middleware::from_fn(error::request_id))
         .with_state(app_state)
 }
 
-async fn index() -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+async fn index(State(app_state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
     Ok(Json(json!({
         "status": StatusCode::OK.as_u16(),
         "message": "Welcome to Core Capital Automatic Reports API!",
-        "database": true
+        "database": system::health::database_is_up(&app_state.db).await
     })))
 }
 
-async fn fallback() -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
-    Ok(Json(json!({
-        "status": StatusCode::NOT_FOUND.as_u16(),
-        "message": "Route not found. Please contact the developer.",
-    })))
+async fn fallback() -> ApiError {
+    ApiError::RouteNotFound
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header::CONTENT_TYPE, Method, Request, StatusCode},
+        Router,
+    };
+    use chrono_tz::{Africa::Johannesburg, UTC};
+    use consolidation::{
+        datetime_profiles::{DateTimeProfile, DateTimeProfileSelection},
+        timezones::DstPolicy,
+    };
+    use serde_json::Value;
+    use sqlx::postgres::PgPoolOptions;
+    use tower::ServiceExt;
+
+    use super::create_router;
+    use crate::{
+        config::{Config, EmailConfig, QueueConfig, ServerConfig, WebhookConfig},
+        error::REQUEST_ID_HEADER,
+        metrics::Metrics,
+        scheduler::ReportScheduler,
+        AppState,
+    };
+
+    /// A router whose pool never connects, for requests rejected before any query runs.
+    async fn router() -> Router {
+        let db = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let env = Config {
+            database_url: String::new(),
+            app_timezone: Johannesburg,
+            dialogue_timezone: UTC,
+            invoicing_timezone: Johannesburg,
+            dialogue_dst_policy: DstPolicy::Earliest,
+            invoicing_dst_policy: DstPolicy::Earliest,
+            datetime_profiles: DateTimeProfile::builtin(),
+            default_datetime_profile: DateTimeProfileSelection::Flexible,
+            server: ServerConfig::default(),
+            queue: QueueConfig::default(),
+            webhooks: WebhookConfig::default(),
+            email: EmailConfig::default(),
+        };
+
+        create_router(AppState {
+            db,
+            env,
+            metrics: Metrics::new(),
+            report_scheduler: ReportScheduler::default(),
+        })
+        .await
+    }
+
+    async fn send(request: Request<Body>) -> (StatusCode, Option<String>, Value) {
+        let response = router().await.oneshot(request).await.unwrap();
+        let status = response.status();
+        let request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, request_id, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn health_check_does_not_touch_the_database() {
+        let request = Request::get("/healthz").body(Body::empty()).unwrap();
+
+        let (status, request_id, body) = send(request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(request_id.is_some());
+        assert_eq!(body["message"], "ok");
+    }
+
+    #[tokio::test]
+    async fn unknown_routes_return_a_real_404_with_the_request_id() {
+        let request = Request::get("/nope")
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body(Body::empty())
+            .unwrap();
+
+        let (status, request_id, body) = send(request).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(request_id.as_deref(), Some("abc-123"));
+        assert_eq!(body["code"], "route_not_found");
+        assert_eq!(body["request_id"], "abc-123");
+    }
+
+    #[tokio::test]
+    async fn reports_the_invalid_schedules_field() {
+        let request = Request::get(
+            "/schedules?start_date=2026-05-02%2025:00&end_date=2026-05-03&shift_group=JEN%204",
+        )
+        .body(Body::empty())
+        .unwrap();
+
+        let (status, request_id, body) = send(request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["details"][0]["field"], "start_date");
+        assert_eq!(body["request_id"].as_str(), request_id.as_deref());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_schedule_sort_columns() {
+        let request = Request::get(
+            "/schedules?start_date=2026-05-02%2000:00:00&end_date=2026-05-02%2023:59:59&sort=teacher",
+        )
+        .body(Body::empty())
+        .unwrap();
+
+        let (status, _, body) = send(request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"][0]["field"], "sort");
+    }
+
+    #[tokio::test]
+    async fn reports_missing_json_fields() {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/admin/column-mappings")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"file_type":"dialogue","field":"start"}"#))
+            .unwrap();
+
+        let (status, _, body) = send(request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"][0]["field"], "alias");
+    }
+
+    #[tokio::test]
+    async fn rejects_upload_dates_that_are_not_calendar_dates() {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/upload-and-process?date=..%2Fetc")
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=x")
+            .body(Body::from("--x--\r\n"))
+            .unwrap();
+
+        let (status, _, body) = send(request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"][0]["field"], "date");
+    }
+
+    #[tokio::test]
+    async fn metrics_count_requests_by_route_template() {
+        let app = router().await;
+
+        for uri in [
+            "/admin/column-mappings/1",
+            "/admin/column-mappings/2",
+            "/nope",
+        ] {
+            let request = Request::delete(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+
+        let request = Request::get("/metrics").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(body.contains(
+            r#"sergio_ar_http_requests_total{method="DELETE",route="/admin/column-mappings/:id","#
+        ));
+        assert!(body.contains(
+            r#"sergio_ar_http_requests_total{method="DELETE",route="unmatched",status="404"} 1"#
+        ));
+        assert!(!body.contains("/admin/column-mappings/1"));
+    }
 }
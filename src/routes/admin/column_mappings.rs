@@ -0,0 +1,152 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use consolidation::column_mappings::{
+    validate_mapping_field, DIALOGUE_FILE_TYPE, INVOICING_FILE_TYPE,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::{ApiError, JsonBody, Path, Query},
+    utils::column_mappings::ColumnMapping,
+    AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ListColumnMappingsParams {
+    pub file_type: Option<String>,
+    pub field: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateColumnMappingPayload {
+    pub file_type: String,
+    pub field: String,
+    pub alias: String,
+    /// Defaults to after the field's existing aliases.
+    pub position: Option<i32>,
+}
+
+pub async fn list_column_mappings(
+    Query(params): Query<ListColumnMappingsParams>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let column_mappings = sqlx::query_as::<_, ColumnMapping>(
+        r#"
+            SELECT id, file_type, field, alias, position
+            FROM column_mappings
+            WHERE ($1::TEXT IS NULL OR file_type = $1)
+            AND ($2::TEXT IS NULL OR field = $2)
+            ORDER BY file_type, field, position, id
+        "#,
+    )
+    .bind(&params.file_type)
+    .bind(&params.field)
+    .fetch_all(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error fetching column mappings: {:?}", error);
+        ApiError::internal("Error fetching column mappings. Please contact the developer.")
+    })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "column_mappings": column_mappings,
+    })))
+}
+
+pub async fn create_column_mapping(
+    State(app_state): State<AppState>,
+    JsonBody(payload): JsonBody<CreateColumnMappingPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let file_type = payload.file_type.trim().to_ascii_lowercase();
+    let field = payload.field.trim().to_ascii_lowercase();
+    let alias = payload.alias.trim().to_string();
+
+    validate_mapping_field(&file_type, &field).map_err(|error| {
+        let invalid = match file_type.as_str() {
+            DIALOGUE_FILE_TYPE | INVOICING_FILE_TYPE => "field",
+            _ => "file_type",
+        };
+
+        ApiError::invalid_field(invalid, error.to_string())
+    })?;
+
+    if alias.is_empty() {
+        return Err(ApiError::invalid_field("alias", "must not be empty"));
+    }
+
+    let column_mapping = sqlx::query_as::<_, ColumnMapping>(
+        r#"
+            INSERT INTO column_mappings (file_type, field, alias, position)
+            VALUES (
+                $1,
+                $2,
+                $3,
+                COALESCE(
+                    $4,
+                    (SELECT MAX(position) + 10 FROM column_mappings WHERE file_type = $1 AND field = $2),
+                    0
+                )
+            )
+            ON CONFLICT DO NOTHING
+            RETURNING id, file_type, field, alias, position
+        "#,
+    )
+    .bind(&file_type)
+    .bind(&field)
+    .bind(&alias)
+    .bind(payload.position)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error inserting column mapping: {:?}", error);
+        ApiError::internal("Error inserting column mapping. Please contact the developer.")
+    })?
+    .ok_or_else(|| {
+        ApiError::Conflict(format!(
+            "{} {} already has the alias {:?}",
+            file_type, field, alias
+        ))
+    })?;
+
+    tracing::info!(
+        "✅ Added {} column alias {:?} for {}",
+        column_mapping.file_type,
+        column_mapping.alias,
+        column_mapping.field
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": StatusCode::CREATED.as_u16(),
+            "column_mapping": column_mapping,
+        })),
+    ))
+}
+
+pub async fn delete_column_mapping(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let column_mapping = sqlx::query_as::<_, ColumnMapping>(
+        r#"
+            DELETE FROM column_mappings
+            WHERE id = $1
+            RETURNING id, file_type, field, alias, position
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error deleting column mapping: {:?}", error);
+        ApiError::internal("Error deleting column mapping. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::NotFound("Column mapping not found".to_string()))?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "column_mapping": column_mapping,
+    })))
+}
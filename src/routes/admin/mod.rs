@@ -0,0 +1,4 @@
+pub mod column_mappings;
+pub mod report_schedules;
+pub mod report_subscriptions;
+pub mod webhooks;
@@ -0,0 +1,183 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::{ApiError, JsonBody, Path},
+    utils::{
+        report_artifacts::{
+            generate_artifacts, list_schedules, validate_cron, ReportSchedule, SCHEDULE_COLUMNS,
+        },
+        reports::{normalize_formats, normalize_shift_groups, PERIODS},
+    },
+    AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReportSchedulePayload {
+    pub name: String,
+    /// Six fields, seconds first, such as `0 0 6 * * Mon`. Evaluated in `APP_TIMEZONE`.
+    pub cron: String,
+    /// `daily` or `weekly`.
+    pub period: String,
+    /// Empty or missing for every shift group with schedules in the period.
+    pub shift_groups: Option<Vec<String>>,
+    /// Any of `csv` and `xlsx`. Defaults to `csv`.
+    pub formats: Option<Vec<String>>,
+}
+
+pub async fn list_report_schedules(
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report_schedules = list_schedules(&app_state.db).await.map_err(|error| {
+        tracing::error!("Error fetching report schedules: {:?}", error);
+        ApiError::internal("Error fetching report schedules. Please contact the developer.")
+    })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "report_schedules": report_schedules,
+    })))
+}
+
+pub async fn create_report_schedule(
+    State(app_state): State<AppState>,
+    JsonBody(payload): JsonBody<CreateReportSchedulePayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let name = payload.name.trim().to_string();
+    let cron = payload.cron.trim().to_string();
+    let period = payload.period.trim().to_ascii_lowercase();
+
+    if name.is_empty() {
+        return Err(ApiError::invalid_field("name", "must not be empty"));
+    }
+
+    validate_cron(&cron).map_err(|error| ApiError::invalid_field("cron", error.to_string()))?;
+
+    if !PERIODS.contains(&period.as_str()) {
+        return Err(ApiError::invalid_field(
+            "period",
+            format!("expected one of: {}", PERIODS.join(", ")),
+        ));
+    }
+
+    let shift_groups = normalize_shift_groups(payload.shift_groups.unwrap_or_default())
+        .map_err(|error| ApiError::invalid_field("shift_groups", error.to_string()))?;
+    let formats = normalize_formats(payload.formats)
+        .map_err(|error| ApiError::invalid_field("formats", error.to_string()))?;
+
+    let report_schedule = sqlx::query_as::<_, ReportSchedule>(&format!(
+        r#"
+            INSERT INTO report_schedules (name, cron, period, shift_groups, formats)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {}
+        "#,
+        SCHEDULE_COLUMNS
+    ))
+    .bind(&name)
+    .bind(&cron)
+    .bind(&period)
+    .bind(&shift_groups)
+    .bind(&formats)
+    .fetch_one(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error inserting report schedule: {:?}", error);
+        ApiError::internal("Error inserting report schedule. Please contact the developer.")
+    })?;
+
+    app_state
+        .report_scheduler
+        .add(&app_state, &report_schedule)
+        .await
+        .map_err(|error| {
+            tracing::error!("Error scheduling report schedule: {:?}", error);
+            ApiError::internal(
+                "The report schedule was saved but will only run after a restart. Please contact the developer.",
+            )
+        })?;
+
+    tracing::info!(
+        "✅ Added report schedule {:?} ({})",
+        report_schedule.name,
+        report_schedule.cron
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": StatusCode::CREATED.as_u16(),
+            "report_schedule": report_schedule,
+        })),
+    ))
+}
+
+/// Deletes a schedule. The artifacts it generated are kept.
+pub async fn delete_report_schedule(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report_schedule = sqlx::query_as::<_, ReportSchedule>(&format!(
+        "DELETE FROM report_schedules WHERE id = $1 RETURNING {}",
+        SCHEDULE_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error deleting report schedule: {:?}", error);
+        ApiError::internal("Error deleting report schedule. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::NotFound("Report schedule not found".to_string()))?;
+
+    if let Err(error) = app_state.report_scheduler.remove(id).await {
+        tracing::error!(
+            "🔥 Failed to unschedule report schedule {}: {:?}",
+            id,
+            error
+        );
+    }
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "report_schedule": report_schedule,
+    })))
+}
+
+/// Runs a schedule now, for the period before today, and returns the artifacts it stored.
+pub async fn run_report_schedule(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report_schedule = sqlx::query_as::<_, ReportSchedule>(&format!(
+        "SELECT {} FROM report_schedules WHERE id = $1",
+        SCHEDULE_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error fetching report schedule: {:?}", error);
+        ApiError::internal("Error fetching report schedule. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::NotFound("Report schedule not found".to_string()))?;
+
+    let timezone = app_state.env.app_timezone;
+    let today = Utc::now().with_timezone(&timezone).date_naive();
+
+    let artifacts = generate_artifacts(&app_state.db, &report_schedule, today, timezone)
+        .await
+        .map_err(|error| {
+            tracing::error!("Error generating report artifacts: {:?}", error);
+            ApiError::internal("Error generating report artifacts. Please contact the developer.")
+        })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": StatusCode::CREATED.as_u16(),
+            "artifacts": artifacts,
+        })),
+    ))
+}
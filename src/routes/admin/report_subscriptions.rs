@@ -0,0 +1,172 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::{ApiError, JsonBody, Path},
+    utils::{
+        report_emails::{ReportDelivery, ReportSubscription, SUBSCRIPTION_COLUMNS},
+        reports::{normalize_formats, normalize_shift_groups, PERIODS},
+        smtp::validate_address,
+    },
+    AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReportSubscriptionPayload {
+    pub recipient: String,
+    /// Empty or missing for every shift group with schedules in the period.
+    pub shift_groups: Option<Vec<String>>,
+    /// `daily` or `weekly`.
+    pub cadence: String,
+    /// Any of `csv` and `xlsx`. Defaults to `csv`.
+    pub formats: Option<Vec<String>>,
+}
+
+pub async fn list_report_subscriptions(
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report_subscriptions = sqlx::query_as::<_, ReportSubscription>(&format!(
+        "SELECT {} FROM report_subscriptions ORDER BY id",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .fetch_all(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error fetching report subscriptions: {:?}", error);
+        ApiError::internal("Error fetching report subscriptions. Please contact the developer.")
+    })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "report_subscriptions": report_subscriptions,
+    })))
+}
+
+/// Subscribes a recipient. The first email covers the most recent full period, once the send
+/// hour has passed.
+pub async fn create_report_subscription(
+    State(app_state): State<AppState>,
+    JsonBody(payload): JsonBody<CreateReportSubscriptionPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let recipient = payload.recipient.trim().to_string();
+    let cadence = payload.cadence.trim().to_ascii_lowercase();
+
+    validate_address(&recipient)
+        .map_err(|error| ApiError::invalid_field("recipient", error.to_string()))?;
+
+    if !PERIODS.contains(&cadence.as_str()) {
+        return Err(ApiError::invalid_field(
+            "cadence",
+            format!("expected one of: {}", PERIODS.join(", ")),
+        ));
+    }
+
+    let shift_groups = normalize_shift_groups(payload.shift_groups.unwrap_or_default())
+        .map_err(|error| ApiError::invalid_field("shift_groups", error.to_string()))?;
+    let formats = normalize_formats(payload.formats)
+        .map_err(|error| ApiError::invalid_field("formats", error.to_string()))?;
+
+    let report_subscription = sqlx::query_as::<_, ReportSubscription>(&format!(
+        r#"
+            INSERT INTO report_subscriptions (recipient, shift_groups, cadence, formats)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+        "#,
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(&recipient)
+    .bind(&shift_groups)
+    .bind(&cadence)
+    .bind(&formats)
+    .fetch_one(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error inserting report subscription: {:?}", error);
+        ApiError::internal("Error inserting report subscription. Please contact the developer.")
+    })?;
+
+    tracing::info!(
+        "✅ Subscribed {} to the {} report",
+        report_subscription.recipient,
+        report_subscription.cadence
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": StatusCode::CREATED.as_u16(),
+            "report_subscription": report_subscription,
+        })),
+    ))
+}
+
+/// Deletes a subscription along with its delivery log.
+pub async fn delete_report_subscription(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report_subscription = sqlx::query_as::<_, ReportSubscription>(&format!(
+        "DELETE FROM report_subscriptions WHERE id = $1 RETURNING {}",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error deleting report subscription: {:?}", error);
+        ApiError::internal("Error deleting report subscription. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::NotFound("Report subscription not found".to_string()))?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "report_subscription": report_subscription,
+    })))
+}
+
+/// The subscription's 100 most recent emails, sent or failed.
+pub async fn list_report_deliveries(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let internal = |error: sqlx::Error| {
+        tracing::error!("Error fetching report deliveries: {:?}", error);
+        ApiError::internal("Error fetching report deliveries. Please contact the developer.")
+    };
+
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM report_subscriptions WHERE id = $1)",
+    )
+    .bind(id)
+    .fetch_one(&app_state.db)
+    .await
+    .map_err(internal)?;
+
+    if !exists {
+        return Err(ApiError::NotFound(
+            "Report subscription not found".to_string(),
+        ));
+    }
+
+    let deliveries = sqlx::query_as::<_, ReportDelivery>(
+        r#"
+            SELECT
+                id, subscription_id, recipient, start_date, end_date, shift_groups, attachments,
+                status, error, created_at
+            FROM report_deliveries
+            WHERE subscription_id = $1
+            ORDER BY id DESC
+            LIMIT 100
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&app_state.db)
+    .await
+    .map_err(internal)?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "deliveries": deliveries,
+    })))
+}
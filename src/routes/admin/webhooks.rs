@@ -0,0 +1,150 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::{ApiError, JsonBody, Path},
+    utils::webhooks::{generate_secret, validate_webhook_url, Webhook, WebhookDelivery},
+    AppState,
+};
+
+/// Secrets shorter than this are too easy to guess.
+const MIN_SECRET_LENGTH: usize = 16;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookPayload {
+    pub url: String,
+    /// Generated when missing.
+    pub secret: Option<String>,
+}
+
+pub async fn list_webhooks(
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let webhooks =
+        sqlx::query_as::<_, Webhook>("SELECT id, url, created_at FROM webhooks ORDER BY id")
+            .fetch_all(&app_state.db)
+            .await
+            .map_err(|error| {
+                tracing::error!("Error fetching webhooks: {:?}", error);
+                ApiError::internal("Error fetching webhooks. Please contact the developer.")
+            })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "webhooks": webhooks,
+    })))
+}
+
+/// Registers a webhook for every consolidation job that finishes. The response is the only
+/// place the signing secret is shown.
+pub async fn create_webhook(
+    State(app_state): State<AppState>,
+    JsonBody(payload): JsonBody<CreateWebhookPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let url = validate_webhook_url(&payload.url)
+        .map_err(|error| ApiError::invalid_field("url", error.to_string()))?;
+    let secret = match payload.secret {
+        Some(secret) if secret.trim().len() < MIN_SECRET_LENGTH => {
+            return Err(ApiError::invalid_field(
+                "secret",
+                format!("must be at least {} characters", MIN_SECRET_LENGTH),
+            ));
+        }
+        Some(secret) => secret.trim().to_string(),
+        None => generate_secret(),
+    };
+
+    let webhook = sqlx::query_as::<_, Webhook>(
+        r#"
+            INSERT INTO webhooks (url, secret)
+            VALUES ($1, $2)
+            RETURNING id, url, created_at
+        "#,
+    )
+    .bind(url.as_str())
+    .bind(&secret)
+    .fetch_one(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error inserting webhook: {:?}", error);
+        ApiError::internal("Error inserting webhook. Please contact the developer.")
+    })?;
+
+    tracing::info!("✅ Added webhook {} for {}", webhook.id, webhook.url);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": StatusCode::CREATED.as_u16(),
+            "webhook": webhook,
+            "secret": secret,
+        })),
+    ))
+}
+
+/// Deletes a webhook along with its delivery history.
+pub async fn delete_webhook(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let webhook = sqlx::query_as::<_, Webhook>(
+        "DELETE FROM webhooks WHERE id = $1 RETURNING id, url, created_at",
+    )
+    .bind(id)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|error| {
+        tracing::error!("Error deleting webhook: {:?}", error);
+        ApiError::internal("Error deleting webhook. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::NotFound("Webhook not found".to_string()))?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "webhook": webhook,
+    })))
+}
+
+/// The webhook's 100 most recent deliveries.
+pub async fn list_webhook_deliveries(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let internal = |error: sqlx::Error| {
+        tracing::error!("Error fetching webhook deliveries: {:?}", error);
+        ApiError::internal("Error fetching webhook deliveries. Please contact the developer.")
+    };
+
+    let exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM webhooks WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&app_state.db)
+            .await
+            .map_err(internal)?;
+
+    if !exists {
+        return Err(ApiError::NotFound("Webhook not found".to_string()));
+    }
+
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+        r#"
+            SELECT
+                id, webhook_id, event, payload, status, attempts, next_attempt_at,
+                last_status_code, last_error, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY id DESC
+            LIMIT 100
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&app_state.db)
+    .await
+    .map_err(internal)?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "deliveries": deliveries,
+    })))
+}
@@ -0,0 +1,111 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::{ApiError, Path, Query},
+    routes::consolidator::upload_and_process::remove_upload_directory,
+    utils::consolidation_jobs::{cancel_job, get_job, list_jobs, CANCELLED, STATUSES},
+    AppState,
+};
+
+const DEFAULT_LIMIT: i64 = 50;
+
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ListConsolidationJobsParams {
+    pub status: Option<String>,
+    /// Defaults to 50, at most 500.
+    pub limit: Option<i64>,
+}
+
+pub async fn list_consolidation_jobs(
+    Query(params): Query<ListConsolidationJobsParams>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(status) = &params.status {
+        if !STATUSES.contains(&status.as_str()) {
+            return Err(ApiError::invalid_field(
+                "status",
+                format!("expected one of: {}", STATUSES.join(", ")),
+            ));
+        }
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::invalid_field(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+
+    let jobs = list_jobs(&app_state.db, params.status.as_deref(), limit)
+        .await
+        .map_err(|error| {
+            tracing::error!("Error fetching consolidation jobs: {:?}", error);
+            ApiError::internal("Error fetching consolidation jobs. Please contact the developer.")
+        })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "jobs": jobs,
+    })))
+}
+
+pub async fn get_consolidation_job(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let job = get_job(&app_state.db, id)
+        .await
+        .map_err(|error| {
+            tracing::error!("Error fetching consolidation job: {:?}", error);
+            ApiError::internal("Error fetching consolidation job. Please contact the developer.")
+        })?
+        .ok_or_else(|| ApiError::NotFound("Consolidation job not found".to_string()))?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "job": job,
+    })))
+}
+
+/// Cancels a queued job immediately. A running job that is still reading its files stops within
+/// a second or so; one that has started writing finishes, so nothing is applied in part. The job
+/// shows `cancel_requested` either way.
+pub async fn cancel_consolidation_job(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let internal = |error: sqlx::Error| {
+        tracing::error!("Error cancelling consolidation job: {:?}", error);
+        ApiError::internal("Error cancelling consolidation job. Please contact the developer.")
+    };
+
+    let Some(job) = cancel_job(&app_state.db, id).await.map_err(internal)? else {
+        return Err(match get_job(&app_state.db, id).await.map_err(internal)? {
+            Some(job) => ApiError::Conflict(format!(
+                "Consolidation job {} has already finished ({})",
+                job.id, job.status
+            )),
+            None => ApiError::NotFound("Consolidation job not found".to_string()),
+        });
+    };
+
+    tracing::info!("❕ Cancellation requested for consolidation job {}", job.id);
+
+    // A running job's worker deletes the upload once it stops.
+    if job.status == CANCELLED {
+        if let Some(directory) = &job.upload_directory {
+            remove_upload_directory(std::path::Path::new(directory)).await;
+        }
+    }
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "job": job,
+    })))
+}
@@ -1 +1,3 @@
+pub mod jobs;
+pub mod repository;
 pub mod upload_and_process;
@@ -0,0 +1,253 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::Error;
+use chrono::NaiveDate;
+use consolidation::{ConsolidationRepository, InvoicingRow, ScheduleRecord, StoredInvoices};
+use sqlx::{Pool, Postgres};
+
+/// Writes consolidation batches to the `invoices`, `teachers`, `schedules` and `shift_groups`
+/// tables, one statement per batch.
+#[derive(Clone)]
+pub struct PgConsolidationRepository {
+    db: Pool<Postgres>,
+    /// Recorded as the last processed date of the shift groups stored.
+    process_date: NaiveDate,
+    /// Set before the first batch is written.
+    writes_started: Arc<AtomicBool>,
+}
+
+impl PgConsolidationRepository {
+    pub fn new(db: Pool<Postgres>, process_date: NaiveDate) -> PgConsolidationRepository {
+        PgConsolidationRepository {
+            db,
+            process_date,
+            writes_started: Arc::default(),
+        }
+    }
+
+    /// Reports the first write through `writes_started`, so a run can be stopped safely only
+    /// before then.
+    pub fn with_writes_started(mut self, writes_started: Arc<AtomicBool>) -> Self {
+        self.writes_started = writes_started;
+        self
+    }
+}
+
+impl ConsolidationRepository for PgConsolidationRepository {
+    async fn store_invoices(&self, invoices: &[InvoicingRow]) -> Result<StoredInvoices, Error> {
+        self.writes_started.store(true, Ordering::SeqCst);
+
+        let returned = sqlx::query_scalar::<_, bool>(
+            r#"
+                INSERT INTO invoices (
+                    teacher_name,
+                    eligible,
+                    activity_start,
+                    activity_end,
+                    shift
+                )
+                SELECT * FROM UNNEST(
+                    $1::VARCHAR[],
+                    $2::BOOLEAN[],
+                    $3::TIMESTAMPTZ[],
+                    $4::TIMESTAMPTZ[],
+                    $5::VARCHAR[]
+                )
+                ON CONFLICT (teacher_name, shift, activity_start, activity_end)
+                DO UPDATE SET eligible = EXCLUDED.eligible
+                RETURNING (xmax = 0) AS inserted
+            "#,
+        )
+        .bind(
+            invoices
+                .iter()
+                .map(|row| row.teacher_name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(invoices.iter().map(|row| row.eligible).collect::<Vec<_>>())
+        .bind(
+            invoices
+                .iter()
+                .map(|row| row.activity_start)
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            invoices
+                .iter()
+                .map(|row| row.activity_end)
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            invoices
+                .iter()
+                .map(|row| row.shift.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let inserted = returned.iter().filter(|inserted| **inserted).count();
+
+        Ok(StoredInvoices {
+            inserted,
+            updated: returned.len() - inserted,
+        })
+    }
+
+    async fn store_teachers(&self, schedules: &[ScheduleRecord<'_>]) -> Result<usize, Error> {
+        self.writes_started.store(true, Ordering::SeqCst);
+
+        let result = sqlx::query(
+            r#"
+                INSERT INTO teachers (name)
+                SELECT DISTINCT batch.name
+                FROM UNNEST($1::VARCHAR[]) AS batch (name)
+                ON CONFLICT (name) DO NOTHING
+            "#,
+        )
+        .bind(
+            schedules
+                .iter()
+                .map(|schedule| schedule.row.teacher_name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected() as usize)
+    }
+
+    /// Teachers must already exist; the engine calls [Self::store_teachers] first. The batch's
+    /// shift groups are added, or have their last processed date moved up, and their schedule
+    /// and teacher counts grow by what the batch added.
+    async fn store_schedules(&self, schedules: &[ScheduleRecord<'_>]) -> Result<usize, Error> {
+        self.writes_started.store(true, Ordering::SeqCst);
+
+        let shift_groups = schedules
+            .iter()
+            .map(|schedule| schedule.row.shift_group.as_str())
+            .collect::<Vec<_>>();
+        let mut transaction = self.db.begin().await?;
+
+        // Locks the batch's groups, so concurrent batches for a group take turns and each sees
+        // the schedules the other stored when it counts new teachers.
+        sqlx::query(
+            r#"
+                INSERT INTO shift_groups (name, last_processed_date)
+                SELECT DISTINCT batch.shift_group, $2::DATE
+                FROM UNNEST($1::VARCHAR[]) AS batch (shift_group)
+                ORDER BY batch.shift_group
+                ON CONFLICT (name) DO UPDATE
+                SET last_processed_date = GREATEST(
+                    shift_groups.last_processed_date,
+                    EXCLUDED.last_processed_date
+                )
+            "#,
+        )
+        .bind(&shift_groups)
+        .bind(self.process_date)
+        .execute(&mut *transaction)
+        .await?;
+
+        let inserted = sqlx::query_scalar::<_, i64>(
+            r#"
+                WITH inserted AS (
+                    INSERT INTO schedules (teacher_id, start_date, end_date, shift, shift_type, shift_group)
+                    SELECT DISTINCT
+                        teacher.id,
+                        batch.start_date,
+                        batch.end_date,
+                        batch.shift,
+                        batch.shift_type,
+                        batch.shift_group
+                    FROM UNNEST(
+                        $1::VARCHAR[],
+                        $2::TIMESTAMPTZ[],
+                        $3::TIMESTAMPTZ[],
+                        $4::VARCHAR[],
+                        $5::VARCHAR[],
+                        $6::VARCHAR[]
+                    ) AS batch (teacher_name, start_date, end_date, shift, shift_type, shift_group)
+                    JOIN teachers AS teacher ON teacher.name = batch.teacher_name
+                    WHERE NOT EXISTS (
+                        SELECT 1
+                        FROM schedules
+                        WHERE schedules.teacher_id = teacher.id
+                        AND schedules.start_date = batch.start_date
+                        AND schedules.end_date = batch.end_date
+                        AND schedules.shift = batch.shift
+                        AND schedules.shift_type = batch.shift_type
+                        AND schedules.shift_group = batch.shift_group
+                    )
+                    RETURNING teacher_id, shift_group
+                ),
+                -- The table still holds only earlier schedules here, so a teacher without one in
+                -- the group is new to it.
+                added AS (
+                    SELECT
+                        inserted.shift_group,
+                        COUNT(*) AS schedules,
+                        COUNT(DISTINCT inserted.teacher_id) FILTER (
+                            WHERE NOT EXISTS (
+                                SELECT 1
+                                FROM schedules
+                                WHERE schedules.shift_group = inserted.shift_group
+                                AND schedules.teacher_id = inserted.teacher_id
+                            )
+                        ) AS teachers
+                    FROM inserted
+                    GROUP BY inserted.shift_group
+                ),
+                counted AS (
+                    UPDATE shift_groups
+                    SET
+                        schedule_count = shift_groups.schedule_count + added.schedules,
+                        teacher_count = shift_groups.teacher_count + added.teachers
+                    FROM added
+                    WHERE shift_groups.name = added.shift_group
+                )
+                SELECT COUNT(*) FROM inserted
+            "#,
+        )
+        .bind(
+            schedules
+                .iter()
+                .map(|schedule| schedule.row.teacher_name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            schedules
+                .iter()
+                .map(|schedule| schedule.start_date)
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            schedules
+                .iter()
+                .map(|schedule| schedule.end_date)
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            schedules
+                .iter()
+                .map(|schedule| schedule.row.shift.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            schedules
+                .iter()
+                .map(|schedule| schedule.row.shift_type.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(&shift_groups)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(inserted as usize)
+    }
+}
@@ -1,21 +1,59 @@
-use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
-};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::json;
 use sqlx::FromRow;
 
-use crate::AppState;
+use crate::{
+    date_range::{overlaps, DateRange},
+    error::{ApiError, Query},
+    AppState,
+};
+
+/// Page size when only `offset` is given.
+const DEFAULT_LIMIT: i64 = 100;
+
+const MAX_LIMIT: i64 = 1000;
+
+/// Columns schedules can be sorted by, and the expression each sorts on.
+const SORT_COLUMNS: [(&str, &str); 7] = [
+    ("id", "schedules.id"),
+    ("start_date", "schedules.start_date"),
+    ("end_date", "schedules.end_date"),
+    ("teacher_name", "teachers.name"),
+    ("shift_group", "schedules.shift_group"),
+    ("shift", "schedules.shift"),
+    ("shift_type", "schedules.shift_type"),
+];
+
+const ORDERS: [&str; 2] = ["asc", "desc"];
+
+/// The filters shared by the page and its total, after the range in `$2` and `$3`.
+const FILTERS: &str = r#"
+            AND ($1::TEXT IS NULL OR schedules.shift_group = $1)
+            AND ($4::INT IS NULL OR schedules.teacher_id = $4)
+            AND ($5::TEXT IS NULL OR teachers.name ILIKE $5)
+            AND ($6::TEXT[] IS NULL OR schedules.shift_type = ANY($6))
+            AND ($7::TEXT IS NULL OR schedules.shift = $7)
+"#;
 
 #[derive(Debug, Deserialize)]
 pub struct GetSchedulesParams {
-    pub start_date: String,
-    pub end_date: String,
-    pub shift_group: String,
+    pub shift_group: Option<String>,
+    pub teacher_id: Option<i32>,
+    /// Case-insensitive part of the teacher's name.
+    pub teacher: Option<String>,
+    /// Comma-separated, such as `Regular,Cover`.
+    pub shift_type: Option<String>,
+    pub shift: Option<String>,
+    /// One of [SORT_COLUMNS]. Defaults to the teacher's name, then the start.
+    pub sort: Option<String>,
+    /// `asc` (the default) or `desc`.
+    pub order: Option<String>,
+    /// At most 1000. Without `limit` and `offset` every schedule is returned, as before paging;
+    /// with only `offset` a page holds 100.
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
@@ -29,75 +67,155 @@ pub struct Schedule {
     pub shift_type: String,
 }
 
+/// Schedules in the range, all of them or a page at a time, with the total that match the
+/// filters. The range is taken by [DateRange].
 pub async fn get_schedules(
+    range: DateRange,
     Query(params): Query<GetSchedulesParams>,
     State(app_state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
-    let start_date = NaiveDateTime::parse_from_str(&params.start_date, "%Y-%m-%d %H:%M:%S")
-        .map_err(|e| {
-            tracing::error!("Error parsing start_date: {:?}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                json!({ "error": "Error parsing start_date" }),
-            )
-        });
-
-    match start_date {
-        Ok(start_date) => {
-            let end_date = NaiveDateTime::parse_from_str(&params.end_date, "%Y-%m-%d %H:%M:%S")
-                .map_err(|e| {
-                    tracing::error!("Error parsing end_date: {:?}", e);
-                    (
-                        StatusCode::BAD_REQUEST,
-                        json!({ "error": "Error parsing end_date" }),
-                    )
-                });
-
-            match end_date {
-                Ok(end_date) => {
-                    let schedules = sqlx::query_as::<_, Schedule>(
-                        r#"
-                        SELECT
-                            schedules.id,
-                            schedules.start_date,
-                            schedules.end_date,
-                            teachers.name AS teacher_name,
-                            schedules.shift_group,
-                            schedules.shift,
-                            schedules.shift_type
-                        FROM schedules
-                        LEFT JOIN teachers ON schedules.teacher_id = teachers.id
-                        WHERE schedules.shift_group = $1 AND schedules.start_date >= $2 AND schedules.start_date <= $3
-                        ORDER BY teachers.name, schedules.start_date ASC
-                        "#
+) -> Result<impl IntoResponse, ApiError> {
+    let order = params.order.as_deref().unwrap_or("asc");
+
+    if !ORDERS.contains(&order) {
+        return Err(ApiError::invalid_field(
+            "order",
+            format!("expected one of: {}", ORDERS.join(", ")),
+        ));
+    }
+
+    let order_by = match params.sort.as_deref() {
+        None => format!("teachers.name {0}, schedules.start_date {0}", order),
+        Some(sort) => {
+            let (_, column) = SORT_COLUMNS
+                .iter()
+                .find(|(name, _)| *name == sort)
+                .ok_or_else(|| {
+                    ApiError::invalid_field(
+                        "sort",
+                        format!(
+                            "expected one of: {}",
+                            SORT_COLUMNS.map(|(name, _)| name).join(", ")
+                        ),
                     )
-                    .bind(&params.shift_group)
-                    .bind(start_date)
-                    .bind(end_date)
-                    .fetch_all(&app_state.db)
-                    .await
-                    .map_err(|e| {
-                        tracing::error!("Error fetching schedules: {:?}", e);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            json!({ "error": "Error fetching schedules" }),
-                        )
-                    });
-
-                    match schedules {
-                        Ok(schedules) => Ok((
-                            StatusCode::OK,
-                            Json(json!({
-                                "status": StatusCode::OK.as_u16(),
-                                "schedules": schedules
-                            })),
-                        )),
-                        Err((status_code, json)) => Err((status_code, Json(json))),
-                    }
-                }
-                Err((status_code, json)) => Err((status_code, Json(json))),
-            }
+                })?;
+
+            format!("{} {}", column, order)
         }
-        Err((status_code, json)) => Err((status_code, Json(json))),
+    };
+
+    // `None` leaves the query unlimited.
+    let limit = match (params.limit, params.offset) {
+        (None, None) => None,
+        (limit, _) => Some(limit.unwrap_or(DEFAULT_LIMIT)),
+    };
+
+    if limit.is_some_and(|limit| !(1..=MAX_LIMIT).contains(&limit)) {
+        return Err(ApiError::invalid_field(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
     }
+
+    let offset = params.offset.unwrap_or(0);
+
+    if offset < 0 {
+        return Err(ApiError::invalid_field("offset", "must not be negative"));
+    }
+
+    let shift_types = params
+        .shift_type
+        .as_deref()
+        .map(|shift_types| {
+            shift_types
+                .split(',')
+                .map(str::trim)
+                .filter(|shift_type| !shift_type.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|shift_types| !shift_types.is_empty());
+    let teacher = params.teacher.as_deref().map(|teacher| {
+        format!(
+            "%{}%",
+            teacher
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        r#"
+        SELECT COUNT(*)
+        FROM schedules
+        LEFT JOIN teachers ON schedules.teacher_id = teachers.id
+        WHERE {} {}
+        "#,
+        overlaps(2, 3),
+        FILTERS
+    ))
+    .bind(&params.shift_group)
+    .bind(range.start)
+    .bind(range.end)
+    .bind(params.teacher_id)
+    .bind(&teacher)
+    .bind(&shift_types)
+    .bind(&params.shift)
+    .fetch_one(&app_state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error counting schedules: {:?}", e);
+        ApiError::internal("Error fetching schedules. Please contact the developer.")
+    })?;
+
+    // `id` breaks ties so pages do not overlap or skip rows.
+    let schedules = sqlx::query_as::<_, Schedule>(&format!(
+        r#"
+        SELECT
+            schedules.id,
+            schedules.start_date AT TIME ZONE $8 AS start_date,
+            schedules.end_date AT TIME ZONE $8 AS end_date,
+            teachers.name AS teacher_name,
+            schedules.shift_group,
+            schedules.shift,
+            schedules.shift_type
+        FROM schedules
+        LEFT JOIN teachers ON schedules.teacher_id = teachers.id
+        WHERE {} {}
+        ORDER BY {}, schedules.id {}
+        LIMIT $9::BIGINT OFFSET $10
+        "#,
+        overlaps(2, 3),
+        FILTERS,
+        order_by,
+        order
+    ))
+    .bind(&params.shift_group)
+    .bind(range.start)
+    .bind(range.end)
+    .bind(params.teacher_id)
+    .bind(&teacher)
+    .bind(&shift_types)
+    .bind(&params.shift)
+    .bind(range.timezone.name())
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&app_state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error fetching schedules: {:?}", e);
+        ApiError::internal("Error fetching schedules. Please contact the developer.")
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "status": StatusCode::OK.as_u16(),
+            "timezone": range.timezone.name(),
+            "total": total,
+            "limit": limit,
+            "offset": offset,
+            "schedules": schedules
+        })),
+    ))
 }
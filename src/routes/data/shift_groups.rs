@@ -1,41 +1,236 @@
-use axum::extract::State;
-use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::json;
 
-use crate::AppState;
+use crate::{
+    error::{ApiError, JsonBody, Path, Query},
+    utils::shift_groups::{list_shift_groups, ShiftGroup, SHIFT_GROUP_COLUMNS},
+    AppState,
+};
 
-use axum::{http::StatusCode, response::IntoResponse, Json};
-use serde_json::{json, Value};
+#[derive(Debug, Deserialize)]
+pub struct ListShiftGroupsParams {
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateShiftGroupPayload {
+    /// The `shift_group` its schedules carry.
+    pub name: String,
+    pub display_name: Option<String>,
+    pub region: Option<String>,
+    pub manager: Option<String>,
+    /// Defaults to `true`.
+    pub active: Option<bool>,
+    /// IANA timezone. Missing for `APP_TIMEZONE`.
+    pub timezone: Option<String>,
+}
 
-#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
-pub struct ShiftGroup {
-    pub shift_group: String,
+/// Replaces everything but the name, which schedules are matched on.
+#[derive(Debug, Deserialize)]
+pub struct UpdateShiftGroupPayload {
+    pub display_name: Option<String>,
+    pub region: Option<String>,
+    pub manager: Option<String>,
+    pub active: bool,
+    pub timezone: Option<String>,
 }
 
+/// Shift groups with their schedule and teacher counts.
 pub async fn get_shift_groups(
+    Query(params): Query<ListShiftGroupsParams>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let shift_groups = list_shift_groups(&app_state.db, None, params.active)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error fetching shift groups: {:?}", e);
+            ApiError::internal("Error fetching shift groups. Please contact the developer.")
+        })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "shift_groups": shift_groups
+    })))
+}
+
+pub async fn get_shift_group(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let shift_group = list_shift_groups(&app_state.db, Some(id), None)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error fetching shift group: {:?}", e);
+            ApiError::internal("Error fetching shift group. Please contact the developer.")
+        })?
+        .pop()
+        .ok_or_else(|| ApiError::NotFound("Shift group not found".to_string()))?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "shift_group": shift_group
+    })))
+}
+
+/// Adds a shift group before any of its schedules are uploaded.
+pub async fn create_shift_group(
+    State(app_state): State<AppState>,
+    JsonBody(payload): JsonBody<CreateShiftGroupPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let name = payload.name.trim().to_string();
+
+    if name.is_empty() {
+        return Err(ApiError::invalid_field("name", "must not be empty"));
+    }
+
+    let timezone = validate_timezone(payload.timezone)?;
+
+    let shift_group = sqlx::query_as::<_, ShiftGroup>(&format!(
+        r#"
+            INSERT INTO shift_groups (name, display_name, region, manager, active, timezone)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING {}
+        "#,
+        SHIFT_GROUP_COLUMNS
+    ))
+    .bind(&name)
+    .bind(non_empty(payload.display_name))
+    .bind(non_empty(payload.region))
+    .bind(non_empty(payload.manager))
+    .bind(payload.active.unwrap_or(true))
+    .bind(timezone)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error inserting shift group: {:?}", e);
+        ApiError::internal("Error inserting shift group. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::Conflict(format!("Shift group {:?} already exists", name)))?;
+
+    tracing::info!("✅ Added shift group {:?}", shift_group.name);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": StatusCode::CREATED.as_u16(),
+            "shift_group": shift_group
+        })),
+    ))
+}
+
+pub async fn update_shift_group(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+    JsonBody(payload): JsonBody<UpdateShiftGroupPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let timezone = validate_timezone(payload.timezone)?;
+
+    let shift_group = sqlx::query_as::<_, ShiftGroup>(&format!(
+        r#"
+            UPDATE shift_groups
+            SET
+                display_name = $2,
+                region = $3,
+                manager = $4,
+                active = $5,
+                timezone = $6,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+        "#,
+        SHIFT_GROUP_COLUMNS
+    ))
+    .bind(id)
+    .bind(non_empty(payload.display_name))
+    .bind(non_empty(payload.region))
+    .bind(non_empty(payload.manager))
+    .bind(payload.active)
+    .bind(timezone)
+    .fetch_optional(&app_state.db)
+    .await
+    .map_err(|e| {
+        tracing::error!("Error updating shift group: {:?}", e);
+        ApiError::internal("Error updating shift group. Please contact the developer.")
+    })?
+    .ok_or_else(|| ApiError::NotFound("Shift group not found".to_string()))?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "shift_group": shift_group
+    })))
+}
+
+/// Deletes a shift group without schedules. Ingestion would add one with schedules straight
+/// back, so those can only be deactivated.
+pub async fn delete_shift_group(
+    Path(id): Path<i32>,
     State(app_state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
-    let shift_groups = sqlx::query_as::<_, ShiftGroup>(
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = sqlx::query_as::<_, ShiftGroup>(&format!(
         r#"
-        SELECT DISTINCT shift_group
-        FROM schedules
+            DELETE FROM shift_groups
+            WHERE id = $1
+            AND NOT EXISTS (SELECT 1 FROM schedules WHERE schedules.shift_group = shift_groups.name)
+            RETURNING {}
         "#,
-    )
-    .fetch_all(&app_state.db)
+        SHIFT_GROUP_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&app_state.db)
     .await
     .map_err(|e| {
-        tracing::error!("Error fetching shift groups: {:?}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            json!({ "error": "Error fetching shift groups" }),
-        )
-    });
-
-    match shift_groups {
-        Ok(shift_groups) => Ok(Json(json!({
-            "status": StatusCode::OK.as_u16(),
-            "shift_groups": shift_groups
-        }))),
-        Err((status_code, json)) => Err((status_code, Json(json))),
+        tracing::error!("Error deleting shift group: {:?}", e);
+        ApiError::internal("Error deleting shift group. Please contact the developer.")
+    })?;
+
+    let shift_group = match deleted {
+        Some(shift_group) => shift_group,
+        None => {
+            let exists = sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS (SELECT 1 FROM shift_groups WHERE id = $1)",
+            )
+            .bind(id)
+            .fetch_one(&app_state.db)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error fetching shift group: {:?}", e);
+                ApiError::internal("Error deleting shift group. Please contact the developer.")
+            })?;
+
+            return Err(match exists {
+                true => ApiError::Conflict(
+                    "Shift group still has schedules. Deactivate it instead".to_string(),
+                ),
+                false => ApiError::NotFound("Shift group not found".to_string()),
+            });
+        }
+    };
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "shift_group": shift_group
+    })))
+}
+
+/// Checks an optional IANA timezone, returning its canonical name.
+fn validate_timezone(timezone: Option<String>) -> Result<Option<String>, ApiError> {
+    match non_empty(timezone) {
+        Some(name) => name
+            .parse::<Tz>()
+            .map(|timezone| Some(timezone.name().to_string()))
+            .map_err(|_| {
+                ApiError::invalid_field("timezone", format!("unknown timezone {:?}", name))
+            }),
+        None => Ok(None),
     }
 }
+
+/// Trims the value, treating a blank one as missing.
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
@@ -1,21 +1,25 @@
-use axum::{
-    body::Body,
-    extract::{Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
-};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use anyhow::Error;
+use axum::{body::Body, extract::State, response::IntoResponse};
+use chrono::NaiveDateTime;
+use consolidation::timezones::resolve_timezone;
+use csv::WriterBuilder;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use sqlx::FromRow;
+use sqlx::{FromRow, Pool, Postgres};
+use xlsx_writer::{write_xlsx, XlsxCell};
 
-use crate::AppState;
+use crate::{
+    date_range::{overlaps, DateRange},
+    error::{ApiError, Query},
+    utils::shift_groups::shift_group_timezone,
+    AppState,
+};
 
+/// The range is read like [DateRange]'s, but in the group's timezone when `tz` is missing.
 #[derive(Debug, Deserialize)]
 pub struct ConsolidatedReportParams {
-    pub start_date: NaiveDate,
-    pub end_date: NaiveDate,
+    pub start_date: String,
+    pub end_date: String,
+    pub tz: Option<String>,
     pub shift_group: String,
 }
 
@@ -33,50 +37,97 @@ pub struct Schedule {
 pub async fn generate_consolidated_report(
     Query(params): Query<ConsolidatedReportParams>,
     State(app_state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
-    let start_date = params.start_date;
-    let end_date = params.end_date;
-    let shift_group = params.shift_group;
-
-    let start_date = NaiveDateTime::new(
-        start_date,
-        NaiveTime::parse_from_str("00:00:00", "%H:%M:%S").unwrap(),
-    );
-    let end_date = NaiveDateTime::new(
-        end_date,
-        NaiveTime::parse_from_str("23:59:59", "%H:%M:%S").unwrap(),
-    );
+) -> Result<impl IntoResponse, ApiError> {
+    let group_timezone = shift_group_timezone(&app_state.db, &params.shift_group)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error fetching shift group: {:?}", e);
+            ApiError::internal("Error fetching shift group. Please contact the developer.")
+        })?;
+    let timezone = resolve_timezone(
+        params.tz.as_deref(),
+        group_timezone.unwrap_or(app_state.env.app_timezone),
+    )
+    .map_err(|error| ApiError::invalid_field("tz", error.to_string()))?;
+    let range = DateRange::parse(&params.start_date, &params.end_date, timezone)?;
+
+    let consolidated_report_csv =
+        build_consolidated_report(&app_state.db, &range, &params.shift_group)
+            .await
+            .map_err(Error::from)
+            .and_then(|report| report.to_csv())
+            .map_err(|error| {
+                tracing::error!("Error fetching schedules for range: {:?}", error);
+                ApiError::internal(
+                    "Error fetching schedules for range. Please contact the developer.",
+                )
+            })?;
+
+    Ok(Body::from(consolidated_report_csv).into_response())
+}
+
+/// Where the per-teacher summary starts, after the schedule columns and a gap.
+const SUMMARY_COLUMN: usize = 8;
+
+/// A consolidated report as rows of cells, so each format is written from the same values rather
+/// than by re-parsing another format.
+#[derive(Debug, Clone)]
+pub struct ConsolidatedReport {
+    pub rows: Vec<Vec<XlsxCell>>,
+}
+
+impl ConsolidatedReport {
+    /// Quotes fields that need it, such as teacher names written `Surname, Name`.
+    pub fn to_csv(&self) -> Result<String, Error> {
+        let mut writer = WriterBuilder::new().flexible(true).from_writer(Vec::new());
 
-    let mut schedules_for_range = sqlx::query_as::<_, Schedule>(
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|cell| match cell {
+                XlsxCell::Text(value) => value.clone(),
+                XlsxCell::Number(value) => value.to_string(),
+                XlsxCell::DateTime(value) => value.to_string(),
+                XlsxCell::Empty => String::new(),
+            }))?;
+        }
+
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    pub fn to_xlsx(&self, sheet_name: &str) -> Result<Vec<u8>, Error> {
+        write_xlsx(sheet_name, &self.rows)
+    }
+}
+
+/// Builds the consolidated report for one shift group's schedules overlapping `range`, with
+/// dates shown in the range's timezone.
+pub async fn build_consolidated_report(
+    db: &Pool<Postgres>,
+    range: &DateRange,
+    shift_group: &str,
+) -> Result<ConsolidatedReport, sqlx::Error> {
+    let mut schedules_for_range = sqlx::query_as::<_, Schedule>(&format!(
         r#"
             SELECT
                 schedules.id as id,
-                schedules.start_date as start_date,
-                schedules.end_date as end_date,
+                schedules.start_date AT TIME ZONE $4 as start_date,
+                schedules.end_date AT TIME ZONE $4 as end_date,
                 teachers.name as teacher_name,
                 schedules.shift_group as shift_group,
                 schedules.shift as shift,
                 schedules.shift_type as shift_type
             FROM schedules
             LEFT JOIN teachers ON schedules.teacher_id = teachers.id
-            WHERE schedules.start_date >= $1 AND schedules.end_date <= $2 AND schedules.shift_group = $3
-        "#
-    )
-    .bind(start_date)
-    .bind(end_date)
-    .bind(&shift_group)
-    .fetch_all(&app_state.db)
-    .await
-    .map_err(|error| {
-        tracing::error!("Error fetching schedules for range: {:?}", error);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "status": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-                "message": "Error fetching schedules for range. Please contact the developer.",
-            })),
-        )
-    })?;
+            WHERE {}
+                AND schedules.shift_group = $3
+        "#,
+        overlaps(1, 2)
+    ))
+    .bind(range.start)
+    .bind(range.end)
+    .bind(shift_group)
+    .bind(range.timezone.name())
+    .fetch_all(db)
+    .await?;
 
     schedules_for_range.sort_by(|a, b| {
         let a_cmb = format!(
@@ -92,14 +143,30 @@ pub async fn generate_consolidated_report(
             b.start_date.time()
         );
 
-        a_cmb.to_lowercase().cmp(&&b_cmb.to_lowercase())
+        a_cmb.to_lowercase().cmp(&b_cmb.to_lowercase())
     });
 
-    let mut consolidated_report_csv =
-        "Teacher,Shift,Shift Type,Start Date,End Date,,,,Teacher,Scheduled,Picked Up,Dropped,Dropped & Picked Up,Internal Pickups\n"
-            .to_string();
-
-    let mut csv_lines: Vec<String> = Vec::new();
+    let mut rows = vec![[
+        "Teacher",
+        "Shift",
+        "Shift Type",
+        "Start Date",
+        "End Date",
+        "",
+        "",
+        "",
+        "Teacher",
+        "Scheduled",
+        "Picked Up",
+        "Dropped",
+        "Dropped & Picked Up",
+        "Internal Pickups",
+    ]
+    .map(|heading| match heading {
+        "" => XlsxCell::Empty,
+        heading => XlsxCell::text(heading),
+    })
+    .to_vec()];
 
     let mut table_teachers: Vec<String> = Vec::new();
 
@@ -108,14 +175,17 @@ pub async fn generate_consolidated_report(
             table_teachers.push(schedule.teacher_name.clone());
         }
 
-        csv_lines.push(format!(
-            "{},{},{},{},{},,,,\n",
-            schedule.teacher_name,
-            schedule.shift,
-            schedule.shift_type,
-            schedule.start_date,
-            schedule.end_date
-        ));
+        rows.push(vec![
+            XlsxCell::Text(schedule.teacher_name),
+            XlsxCell::Text(schedule.shift),
+            XlsxCell::Text(schedule.shift_type),
+            XlsxCell::Text(schedule.start_date.to_string()),
+            XlsxCell::Text(schedule.end_date.to_string()),
+            XlsxCell::Empty,
+            XlsxCell::Empty,
+            XlsxCell::Empty,
+            XlsxCell::Empty,
+        ]);
     }
 
     let mut current_teacher = 0;
@@ -151,57 +221,83 @@ pub async fn generate_consolidated_report(
             }
         }
 
-        let initial_line = csv_lines[current_teacher].clone();
-
-        let mut new_line = initial_line.replace("\n", "");
-
-        new_line.push_str(&format!(
-            "{},{},{},{},{},{}\n",
-            teacher, scheduled, picked_up, dropped, dropped_and_picked_up, internal_picked_up
-        ));
-
-        csv_lines[current_teacher] = new_line;
+        // The summary table sits to the right of the schedules, one teacher per row.
+        current_teacher += 1;
+        summary_row(
+            &mut rows[current_teacher],
+            XlsxCell::Text(teacher),
+            [
+                scheduled,
+                picked_up,
+                dropped,
+                dropped_and_picked_up,
+                internal_picked_up,
+            ],
+        );
 
         total_scheduled += scheduled;
         total_picked_up += picked_up;
         total_dropped += dropped;
         total_internal_picked_up += internal_picked_up;
         total_dropped_and_picked_up += dropped_and_picked_up;
-
-        current_teacher += 1;
     }
 
-    if current_teacher < csv_lines.len() {
-        let initial_line = csv_lines[current_teacher].clone();
-
-        let mut new_line = initial_line.replace("\n", "");
+    if current_teacher + 1 >= rows.len() {
+        rows.push(vec![XlsxCell::Empty; SUMMARY_COLUMN + 1]);
+    }
 
-        let total_line = format!(
-            "Total,{},{},{},{},{}\n",
+    summary_row(
+        &mut rows[current_teacher + 1],
+        XlsxCell::text("Total"),
+        [
             total_scheduled,
             total_picked_up,
             total_dropped,
             total_dropped_and_picked_up + total_internal_picked_up,
-            total_internal_picked_up
-        );
+            total_internal_picked_up,
+        ],
+    );
 
-        new_line.push_str(&total_line);
+    Ok(ConsolidatedReport { rows })
+}
 
-        csv_lines[current_teacher] = new_line;
-    } else {
-        csv_lines.push(format!(
-            ",,,,,,,,Total,{},{},{},{},{}\n",
-            total_scheduled,
-            total_picked_up,
-            total_dropped,
-            total_dropped_and_picked_up + total_internal_picked_up,
-            total_internal_picked_up
-        ));
-    }
+/// Fills in the summary columns of a report row.
+fn summary_row(row: &mut Vec<XlsxCell>, label: XlsxCell, counts: [i64; 5]) {
+    row.truncate(SUMMARY_COLUMN);
+    row.push(label);
+    row.extend(counts.map(|count| XlsxCell::Number(count as f64)));
+}
 
-    for line in csv_lines {
-        consolidated_report_csv.push_str(&line);
-    }
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
 
-    Ok(Body::from(consolidated_report_csv).into_response())
+    use calamine::{Data, Reader, Xlsx};
+    use xlsx_writer::XlsxCell;
+
+    use super::ConsolidatedReport;
+
+    #[test]
+    fn keeps_names_with_commas_in_one_cell() {
+        let report = ConsolidatedReport {
+            rows: vec![
+                vec![XlsxCell::text("Teacher"), XlsxCell::Empty],
+                vec![XlsxCell::text("Magongo, Babalwa"), XlsxCell::Number(2.0)],
+            ],
+        };
+
+        assert_eq!(
+            report.to_csv().unwrap(),
+            "Teacher,\n\"Magongo, Babalwa\",2\n"
+        );
+
+        let mut workbook = Xlsx::new(Cursor::new(report.to_xlsx("JEN 4, PM").unwrap())).unwrap();
+        let range = workbook.worksheet_range("JEN 4, PM").unwrap();
+
+        assert_eq!(
+            range.get_value((1, 0)),
+            Some(&Data::String("Magongo, Babalwa".into()))
+        );
+        assert_eq!(range.get_value((1, 1)), Some(&Data::Float(2.0)));
+    }
 }
@@ -1,3 +1,6 @@
+pub mod admin;
 pub mod consolidator;
 pub mod data;
 pub mod efficiency;
+pub mod reports;
+pub mod system;
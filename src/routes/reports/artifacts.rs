@@ -0,0 +1,93 @@
+use axum::{
+    extract::State,
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    error::{ApiError, Path, Query},
+    utils::report_artifacts::{get_artifact_content, list_artifacts},
+    AppState,
+};
+
+const DEFAULT_LIMIT: i64 = 50;
+
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ListReportArtifactsParams {
+    pub shift_group: Option<String>,
+    pub schedule_id: Option<i32>,
+    /// Defaults to 50, at most 500.
+    pub limit: Option<i64>,
+}
+
+/// Stored reports, newest first, without their content.
+pub async fn list_report_artifacts(
+    Query(params): Query<ListReportArtifactsParams>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::invalid_field(
+            "limit",
+            format!("must be between 1 and {}", MAX_LIMIT),
+        ));
+    }
+
+    let artifacts = list_artifacts(
+        &app_state.db,
+        params.shift_group.as_deref(),
+        params.schedule_id,
+        limit,
+    )
+    .await
+    .map_err(|error| {
+        tracing::error!("Error fetching report artifacts: {:?}", error);
+        ApiError::internal("Error fetching report artifacts. Please contact the developer.")
+    })?;
+
+    Ok(Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "artifacts": artifacts,
+    })))
+}
+
+pub async fn download_report_artifact(
+    Path(id): Path<i32>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (filename, content_type, content) = get_artifact_content(&app_state.db, id)
+        .await
+        .map_err(|error| {
+            tracing::error!("Error fetching report artifact: {:?}", error);
+            ApiError::internal("Error fetching report artifact. Please contact the developer.")
+        })?
+        .ok_or_else(|| ApiError::NotFound("Report artifact not found".to_string()))?;
+
+    let filename = filename
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    Ok((
+        [
+            (CONTENT_TYPE, content_type),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        content,
+    ))
+}
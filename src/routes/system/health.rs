@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::{json, Value};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+use crate::{AppState, MIGRATOR};
+
+/// Liveness: the process is up and serving requests. Checks nothing else.
+pub async fn healthz() -> impl IntoResponse {
+    Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "ok",
+    }))
+}
+
+/// Readiness: the database answers, every embedded migration is applied and uploads can be
+/// written. Responds 503 with the failing checks otherwise.
+pub async fn readyz(State(app_state): State<AppState>) -> impl IntoResponse {
+    let database = check_database(&app_state.db).await;
+    let migrations = match &database {
+        Ok(()) => check_migrations(&app_state.db).await,
+        Err(_) => Err("skipped because the database is unavailable".to_string()),
+    };
+    let storage = check_storage(&app_state.env.server.upload_directory).await;
+
+    let ready = database.is_ok() && migrations.is_ok() && storage.is_ok();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    if !ready {
+        tracing::warn!(
+            "⚠️ Not ready: database {:?}, migrations {:?}, storage {:?}",
+            database,
+            migrations,
+            storage
+        );
+    }
+
+    (
+        status,
+        Json(json!({
+            "status": status.as_u16(),
+            "message": if ready { "ready" } else { "not ready" },
+            "checks": {
+                "database": check_result(database),
+                "migrations": check_result(migrations),
+                "storage": check_result(storage),
+            },
+        })),
+    )
+}
+
+/// Build information, plus the migrations the database reports as applied (`null` if it
+/// cannot be reached).
+pub async fn version(State(app_state): State<AppState>) -> impl IntoResponse {
+    let applied_migrations = applied_migrations(&app_state.db)
+        .await
+        .map_err(|error| {
+            tracing::warn!("⚠️ Could not read applied migrations: {:?}", error);
+        })
+        .ok();
+
+    Json(json!({
+        "status": StatusCode::OK.as_u16(),
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("GIT_SHA"),
+        "migrations": {
+            "embedded": embedded_migrations(),
+            "applied": applied_migrations,
+        },
+    }))
+}
+
+fn check_result(result: Result<(), String>) -> Value {
+    match result {
+        Ok(()) => json!({ "ok": true }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    }
+}
+
+/// Whether the database answers a ping.
+pub async fn database_is_up(db: &Pool<Postgres>) -> bool {
+    check_database(db).await.is_ok()
+}
+
+async fn check_database(db: &Pool<Postgres>) -> Result<(), String> {
+    sqlx::query("SELECT 1")
+        .execute(db)
+        .await
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+fn embedded_migrations() -> Vec<i64> {
+    MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .collect()
+}
+
+async fn applied_migrations(db: &Pool<Postgres>) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+        .fetch_all(db)
+        .await
+}
+
+async fn check_migrations(db: &Pool<Postgres>) -> Result<(), String> {
+    let applied = applied_migrations(db)
+        .await
+        .map_err(|error| error.to_string())?;
+    let pending = embedded_migrations()
+        .into_iter()
+        .filter(|version| !applied.contains(version))
+        .collect::<Vec<_>>();
+
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("pending migrations: {:?}", pending))
+    }
+}
+
+/// Creates the upload directory if needed and writes and removes a probe file in it.
+async fn check_storage(directory: &Path) -> Result<(), String> {
+    let probe = directory.join(format!(".readyz-{}", Uuid::new_v4()));
+
+    tokio::fs::create_dir_all(directory)
+        .await
+        .map_err(|error| format!("cannot create {}: {}", directory.display(), error))?;
+    tokio::fs::write(&probe, b"ok")
+        .await
+        .map_err(|error| format!("cannot write to {}: {}", directory.display(), error))?;
+    tokio::fs::remove_file(&probe)
+        .await
+        .map_err(|error| format!("cannot clean up {}: {}", probe.display(), error))
+}
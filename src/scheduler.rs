@@ -0,0 +1,123 @@
+//! Runs report schedules on their cron expressions with `tokio-cron-scheduler`.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Error;
+use chrono::Utc;
+use tokio::sync::Mutex;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+
+use crate::{
+    utils::report_artifacts::{generate_artifacts, list_schedules, ReportSchedule},
+    AppState,
+};
+
+/// Handle to the running scheduler. Until [ReportScheduler::start] is called, schedules are only
+/// stored, which is what tests and CLI commands want.
+#[derive(Clone, Default)]
+pub struct ReportScheduler {
+    running: Arc<Mutex<Option<Running>>>,
+}
+
+struct Running {
+    scheduler: JobScheduler,
+    /// Runs are tracked here so the shutdown drain waits for them.
+    tasks: TaskTracker,
+    /// Scheduler job of each report schedule.
+    jobs: HashMap<i32, Uuid>,
+}
+
+impl ReportScheduler {
+    /// Schedules every stored report schedule and starts the scheduler. Returns how many were
+    /// scheduled.
+    pub async fn start(&self, app_state: &AppState, tasks: &TaskTracker) -> Result<usize, Error> {
+        let scheduler = JobScheduler::new().await?;
+        let schedules = list_schedules(&app_state.db).await?;
+
+        *self.running.lock().await = Some(Running {
+            scheduler: scheduler.clone(),
+            tasks: tasks.clone(),
+            jobs: HashMap::new(),
+        });
+
+        for schedule in &schedules {
+            self.add(app_state, schedule).await?;
+        }
+
+        scheduler.start().await?;
+
+        Ok(schedules.len())
+    }
+
+    /// Schedules a report schedule created after startup.
+    pub async fn add(&self, app_state: &AppState, schedule: &ReportSchedule) -> Result<(), Error> {
+        let mut running = self.running.lock().await;
+        let Some(running) = running.as_mut() else {
+            return Ok(());
+        };
+
+        let job_app_state = app_state.clone();
+        let job_schedule = schedule.clone();
+        let tasks = running.tasks.clone();
+
+        let job = Job::new_async_tz(
+            schedule.cron.as_str(),
+            app_state.env.app_timezone,
+            move |_, _| {
+                let app_state = job_app_state.clone();
+                let schedule = job_schedule.clone();
+
+                Box::pin(tasks.track_future(async move {
+                    run_schedule(&app_state, &schedule).await;
+                }))
+            },
+        )?;
+
+        let job_id = running.scheduler.add(job).await?;
+        running.jobs.insert(schedule.id, job_id);
+
+        Ok(())
+    }
+
+    /// Stops running a deleted report schedule.
+    pub async fn remove(&self, schedule_id: i32) -> Result<(), Error> {
+        let mut running = self.running.lock().await;
+        let Some(running) = running.as_mut() else {
+            return Ok(());
+        };
+
+        if let Some(job_id) = running.jobs.remove(&schedule_id) {
+            running.scheduler.remove(&job_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops starting runs. Runs already going are left to the shutdown drain.
+    pub async fn shutdown(&self) {
+        if let Some(mut running) = self.running.lock().await.take() {
+            if let Err(error) = running.scheduler.shutdown().await {
+                tracing::warn!("⚠️ Failed to stop the report scheduler: {:?}", error);
+            }
+        }
+    }
+}
+
+/// Generates and stores the schedule's reports for the period before today.
+async fn run_schedule(app_state: &AppState, schedule: &ReportSchedule) {
+    let timezone = app_state.env.app_timezone;
+    let today = Utc::now().with_timezone(&timezone).date_naive();
+
+    tracing::info!("📄 Running report schedule {:?}.", schedule.name);
+
+    match generate_artifacts(&app_state.db, schedule, today, timezone).await {
+        Ok(artifacts) => tracing::info!(
+            "✅ Report schedule {:?} stored {} artifact(s).",
+            schedule.name,
+            artifacts.len()
+        ),
+        Err(error) => tracing::error!("🔥 Report schedule {:?} failed: {:?}", schedule.name, error),
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use tokio_util::task::TaskTracker;
+
+/// Resolves on Ctrl+C, or on SIGTERM where there is one (pm2 and container runtimes send it).
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            tracing::error!("🔥 Failed to listen for Ctrl+C: {:?}", error);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(error) => {
+                tracing::error!("🔥 Failed to listen for SIGTERM: {:?}", error);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("🕐 Shutdown requested. No longer accepting connections.");
+}
+
+/// Waits up to `timeout` for every tracked task to finish. Returns whether they all did.
+pub async fn drain(tasks: &TaskTracker, timeout: Duration) -> bool {
+    tasks.close();
+
+    if tasks.is_empty() {
+        return true;
+    }
+
+    tracing::info!(
+        "🕐 Waiting up to {}s for {} consolidation worker(s) to finish.",
+        timeout.as_secs(),
+        tasks.len()
+    );
+
+    tokio::time::timeout(timeout, tasks.wait()).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio_util::task::TaskTracker;
+
+    use super::drain;
+
+    #[tokio::test]
+    async fn waits_for_tasks_that_finish_in_time() {
+        let tasks = TaskTracker::new();
+        tasks.spawn(tokio::time::sleep(Duration::from_millis(20)));
+
+        assert!(drain(&tasks, Duration::from_secs(5)).await);
+        assert!(tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_timeout() {
+        let tasks = TaskTracker::new();
+        tasks.spawn(tokio::time::sleep(Duration::from_secs(60)));
+
+        assert!(!drain(&tasks, Duration::from_millis(20)).await);
+    }
+}
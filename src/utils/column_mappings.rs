@@ -0,0 +1,68 @@
+use anyhow::Error;
+use consolidation::column_mappings::ColumnMappings;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
+pub struct ColumnMapping {
+    pub id: i32,
+    pub file_type: String,
+    pub field: String,
+    pub alias: String,
+    /// Lower positions are matched first. For invoicing shifts this is the column preference.
+    pub position: i32,
+}
+
+pub fn mappings_from_rows(rows: &[ColumnMapping]) -> ColumnMappings {
+    let mut rows = rows.iter().collect::<Vec<_>>();
+    rows.sort_by_key(|row| (row.position, row.id));
+
+    ColumnMappings::from_aliases(rows.into_iter().map(|row| {
+        (
+            row.file_type.as_str(),
+            row.field.as_str(),
+            row.alias.as_str(),
+        )
+    }))
+}
+
+pub async fn load_column_mappings(db: &Pool<Postgres>) -> Result<ColumnMappings, Error> {
+    let rows = sqlx::query_as::<_, ColumnMapping>(
+        r#"
+            SELECT id, file_type, field, alias, position
+            FROM column_mappings
+        "#,
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(mappings_from_rows(&rows).with_default_fallback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mappings_from_rows, ColumnMapping};
+
+    #[test]
+    fn orders_database_aliases_by_position() {
+        let row = |id: i32, alias: &str, position: i32| ColumnMapping {
+            id,
+            file_type: "invoicing".to_string(),
+            field: "shift".to_string(),
+            alias: alias.to_string(),
+            position,
+        };
+
+        let mappings = mappings_from_rows(&[
+            row(3, "Shift", 20),
+            row(1, "Shift Label", 5),
+            row(2, "Slot", 20),
+        ]);
+
+        assert_eq!(
+            mappings.aliases("invoicing", "shift"),
+            vec!["Shift Label", "Slot", "Shift"]
+        );
+        assert!(mappings.aliases("dialogue", "shift").is_empty());
+    }
+}
@@ -0,0 +1,351 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgExecutor, Pool, Postgres};
+
+pub const QUEUED: &str = "queued";
+pub const RUNNING: &str = "running";
+pub const SUCCEEDED: &str = "succeeded";
+pub const FAILED: &str = "failed";
+pub const CANCELLED: &str = "cancelled";
+
+pub const STATUSES: [&str; 5] = [QUEUED, RUNNING, SUCCEEDED, FAILED, CANCELLED];
+
+/// Recorded on jobs that were running when the server stopped.
+pub const INTERRUPTED_MESSAGE: &str = "The server stopped before this job finished.";
+
+/// Retry delays stop doubling here.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+const COLUMNS: &str = r#"
+    id, process_date, datetime_profile, upload_directory, status, attempts, max_attempts,
+    cancel_requested, error, run_at, started_at, finished_at, created_at
+"#;
+
+/// The consolidation of one upload. Workers claim `queued` jobs whose `run_at` has passed.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ConsolidationJob {
+    pub id: i32,
+    pub process_date: NaiveDate,
+    /// The profile named on upload, or `None` for the configured default.
+    pub datetime_profile: Option<String>,
+    /// Where this job's upload was written. `None` for jobs queued before every upload had its
+    /// own directory, which read the shared `temp/<date>` one.
+    #[serde(skip_serializing)]
+    pub upload_directory: Option<String>,
+    pub status: String,
+    /// Claims so far, including the current one.
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub cancel_requested: bool,
+    /// Why the last attempt failed. Kept while a retry is queued.
+    pub error: Option<String>,
+    pub run_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Queues a job whose upload goes in `<upload_root>/<job id>`. Run it in the transaction that
+/// writes the files, so workers only see the job once they are there.
+pub async fn enqueue_job<'e>(
+    db: impl PgExecutor<'e>,
+    process_date: NaiveDate,
+    datetime_profile: Option<&str>,
+    max_attempts: i32,
+    upload_root: &str,
+) -> Result<ConsolidationJob, Error> {
+    let job = sqlx::query_as::<_, ConsolidationJob>(&format!(
+        r#"
+            WITH next AS (SELECT NEXTVAL('consolidation_jobs_id_seq')::INT AS id)
+            INSERT INTO consolidation_jobs (
+                id,
+                process_date,
+                datetime_profile,
+                max_attempts,
+                upload_directory
+            )
+            SELECT next.id, $1, $2, $3, $4 || '/' || next.id
+            FROM next
+            RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .bind(process_date)
+    .bind(datetime_profile)
+    .bind(max_attempts)
+    .bind(upload_root)
+    .fetch_one(db)
+    .await?;
+
+    Ok(job)
+}
+
+/// Marks the next due job `running` and returns it. Concurrent workers skip each other's rows,
+/// and a date is never consolidated by two jobs at once.
+pub async fn claim_job(db: &Pool<Postgres>) -> Result<Option<ConsolidationJob>, Error> {
+    let result = sqlx::query_as::<_, ConsolidationJob>(&format!(
+        r#"
+            UPDATE consolidation_jobs
+            SET status = 'running', attempts = attempts + 1, started_at = NOW()
+            WHERE id = (
+                SELECT id
+                FROM consolidation_jobs AS queued
+                WHERE status = 'queued'
+                AND run_at <= NOW()
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM consolidation_jobs AS running
+                    WHERE running.status = 'running'
+                    AND running.process_date = queued.process_date
+                )
+                ORDER BY run_at, id
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .fetch_optional(db)
+    .await;
+
+    match result {
+        Ok(job) => Ok(job),
+        // Another worker claimed a job for the same date in the meantime.
+        Err(sqlx::Error::Database(error)) if error.is_unique_violation() => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Ends a running job as `succeeded`, `failed` or `cancelled`. Does nothing to a job that is no
+/// longer running.
+pub async fn finish_job(
+    db: &Pool<Postgres>,
+    id: i32,
+    status: &str,
+    error: Option<String>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+            UPDATE consolidation_jobs
+            SET status = $2, error = $3, finished_at = NOW()
+            WHERE id = $1 AND status = 'running'
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(error)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Puts a running job back in the queue, due after `delay`.
+pub async fn retry_job(
+    db: &Pool<Postgres>,
+    id: i32,
+    delay: Duration,
+    error: String,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+            UPDATE consolidation_jobs
+            SET
+                status = 'queued',
+                error = $3,
+                run_at = NOW() + MAKE_INTERVAL(secs => $2)
+            WHERE id = $1 AND status = 'running'
+        "#,
+    )
+    .bind(id)
+    .bind(delay.as_secs_f64())
+    .bind(error)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn cancel_requested(db: &Pool<Postgres>, id: i32) -> Result<bool, Error> {
+    let requested =
+        sqlx::query_scalar("SELECT cancel_requested FROM consolidation_jobs WHERE id = $1")
+            .bind(id)
+            .fetch_one(db)
+            .await?;
+
+    Ok(requested)
+}
+
+/// Cancels a queued job outright and asks the worker running a running one to stop. Returns
+/// `None` if the job does not exist or has already finished.
+pub async fn cancel_job(
+    db: &Pool<Postgres>,
+    id: i32,
+) -> Result<Option<ConsolidationJob>, sqlx::Error> {
+    sqlx::query_as::<_, ConsolidationJob>(&format!(
+        r#"
+            UPDATE consolidation_jobs
+            SET
+                cancel_requested = TRUE,
+                status = CASE WHEN status = 'queued' THEN 'cancelled' ELSE status END,
+                finished_at = CASE WHEN status = 'queued' THEN NOW() ELSE finished_at END
+            WHERE id = $1 AND status IN ('queued', 'running')
+            RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(db)
+    .await
+}
+
+pub async fn get_job(
+    db: &Pool<Postgres>,
+    id: i32,
+) -> Result<Option<ConsolidationJob>, sqlx::Error> {
+    sqlx::query_as::<_, ConsolidationJob>(&format!(
+        "SELECT {} FROM consolidation_jobs WHERE id = $1",
+        COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(db)
+    .await
+}
+
+/// The most recent jobs first.
+pub async fn list_jobs(
+    db: &Pool<Postgres>,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<ConsolidationJob>, sqlx::Error> {
+    sqlx::query_as::<_, ConsolidationJob>(&format!(
+        r#"
+            SELECT {}
+            FROM consolidation_jobs
+            WHERE ($1::TEXT IS NULL OR status = $1)
+            ORDER BY id DESC
+            LIMIT $2
+        "#,
+        COLUMNS
+    ))
+    .bind(status)
+    .bind(limit)
+    .fetch_all(db)
+    .await
+}
+
+/// Requeues jobs left `running` by a server that stopped, unless they were cancelled or have
+/// used up their attempts. Only call it at startup, before any worker runs, since this server is
+/// their only worker.
+pub async fn requeue_interrupted_jobs(db: &Pool<Postgres>) -> Result<u64, Error> {
+    requeue_running(db, None).await
+}
+
+/// Requeues one job its worker gave up on during shutdown, the same way as
+/// [requeue_interrupted_jobs].
+pub async fn requeue_interrupted_job(db: &Pool<Postgres>, id: i32) -> Result<(), Error> {
+    requeue_running(db, Some(id)).await?;
+
+    Ok(())
+}
+
+async fn requeue_running(db: &Pool<Postgres>, id: Option<i32>) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+            UPDATE consolidation_jobs
+            SET
+                status = CASE
+                    WHEN cancel_requested THEN 'cancelled'
+                    WHEN attempts < max_attempts THEN 'queued'
+                    ELSE 'failed'
+                END,
+                error = $1,
+                run_at = NOW(),
+                finished_at = CASE
+                    WHEN NOT cancel_requested AND attempts < max_attempts THEN NULL
+                    ELSE NOW()
+                END
+            WHERE status = 'running' AND ($2::INT IS NULL OR id = $2)
+        "#,
+    )
+    .bind(INTERRUPTED_MESSAGE)
+    .bind(id)
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Whether an error is worth retrying: the database was unreachable, overloaded or restarting,
+/// or aborted the transaction for a conflict. Bad input fails the same way every time.
+pub fn is_transient(error: &Error) -> bool {
+    error.chain().any(|cause| {
+        match cause.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::Io(_))
+            | Some(sqlx::Error::PoolTimedOut)
+            | Some(sqlx::Error::PoolClosed)
+            | Some(sqlx::Error::WorkerCrashed) => true,
+            Some(sqlx::Error::Database(error)) => error.code().is_some_and(|code| {
+                // Connection exceptions, serialization failures and deadlocks, insufficient
+                // resources, and operator intervention such as a server restart.
+                ["08", "40", "53", "57"]
+                    .iter()
+                    .any(|class| code.starts_with(class))
+            }),
+            _ => cause.downcast_ref::<std::io::Error>().is_some_and(|error| {
+                matches!(
+                    error.kind(),
+                    std::io::ErrorKind::ConnectionRefused
+                        | std::io::ErrorKind::ConnectionReset
+                        | std::io::ErrorKind::ConnectionAborted
+                        | std::io::ErrorKind::TimedOut
+                )
+            }),
+        }
+    })
+}
+
+/// `base` doubled for every attempt after the first, up to ten minutes.
+pub fn retry_delay(base: Duration, attempt: i32) -> Duration {
+    let doublings = attempt.saturating_sub(1).clamp(0, 16) as u32;
+
+    base.saturating_mul(2u32.pow(doublings))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::{anyhow, Context};
+
+    use super::{is_transient, retry_delay};
+
+    #[test]
+    fn doubles_the_retry_delay_up_to_a_cap() {
+        let base = Duration::from_secs(10);
+
+        assert_eq!(retry_delay(base, 1), Duration::from_secs(10));
+        assert_eq!(retry_delay(base, 3), Duration::from_secs(40));
+        assert_eq!(retry_delay(base, 30), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn only_retries_database_availability_errors() {
+        let timed_out = anyhow::Error::from(sqlx::Error::PoolTimedOut).context("Saving shifts");
+        let refused = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+            .context("Connecting")
+            .unwrap_err();
+
+        assert!(is_transient(&timed_out));
+        assert!(is_transient(&refused));
+        assert!(!is_transient(&anyhow!("Missing column \"Shift Group\"")));
+        assert!(!is_transient(&anyhow::Error::from(
+            sqlx::Error::RowNotFound
+        )));
+    }
+}
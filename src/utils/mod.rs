@@ -1 +1,10 @@
+pub mod column_mappings;
+pub mod consolidation_jobs;
 pub mod invoicing_parser;
+pub mod report_artifacts;
+pub mod report_emails;
+pub mod reports;
+pub mod shift_groups;
+pub mod smtp;
+pub mod teachers;
+pub mod webhooks;
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, Pool, Postgres};
+
+use crate::utils::reports::{render_reports, report_period, shift_groups_in_period};
+
+/// Every `report_schedules` column, in [ReportSchedule] order.
+pub const SCHEDULE_COLUMNS: &str = r#"
+    id, name, cron, period, shift_groups, formats, last_run_at, created_at
+"#;
+
+/// Every `report_artifacts` column but the content, in [ReportArtifact] order.
+pub const ARTIFACT_COLUMNS: &str = r#"
+    id, schedule_id, shift_group, start_date, end_date, format, filename, content_type,
+    size_bytes, sha256, created_at
+"#;
+
+/// Consolidated reports generated on a cron expression and kept as artifacts.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReportSchedule {
+    pub id: i32,
+    pub name: String,
+    /// Six fields, seconds first, such as `0 0 6 * * Mon`. Evaluated in `APP_TIMEZONE`.
+    pub cron: String,
+    /// `daily` for the day before each run, `weekly` for the Monday to Sunday before it.
+    pub period: String,
+    /// Empty for every shift group with schedules in the period.
+    pub shift_groups: Vec<String>,
+    pub formats: Vec<String>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A stored report. It does not change when the data it was generated from is re-processed.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReportArtifact {
+    pub id: i32,
+    /// `None` once the schedule that generated it is deleted.
+    pub schedule_id: Option<i32>,
+    pub shift_group: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub format: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    /// Hex SHA-256 of the content.
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Parses a cron expression the way the scheduler will.
+pub fn validate_cron(expression: &str) -> Result<cron::Schedule, Error> {
+    cron::Schedule::from_str(expression)
+        .map_err(|error| anyhow!("{} (expected six fields, such as \"0 0 6 * * Mon\")", error))
+}
+
+pub async fn list_schedules(db: &Pool<Postgres>) -> Result<Vec<ReportSchedule>, sqlx::Error> {
+    sqlx::query_as::<_, ReportSchedule>(&format!(
+        "SELECT {} FROM report_schedules ORDER BY id",
+        SCHEDULE_COLUMNS
+    ))
+    .fetch_all(db)
+    .await
+}
+
+/// Renders the schedule's reports for the period before `today` and stores each one.
+pub async fn generate_artifacts(
+    db: &Pool<Postgres>,
+    schedule: &ReportSchedule,
+    today: NaiveDate,
+    timezone: Tz,
+) -> Result<Vec<ReportArtifact>, Error> {
+    let (start_date, end_date) = report_period(&schedule.period, today);
+    let shift_groups = match schedule.shift_groups.is_empty() {
+        true => shift_groups_in_period(db, start_date, end_date, timezone).await?,
+        false => schedule.shift_groups.clone(),
+    };
+    let reports = render_reports(
+        db,
+        &shift_groups,
+        (start_date, end_date),
+        &schedule.formats,
+        timezone,
+    )
+    .await?;
+
+    let mut transaction = db.begin().await?;
+    let mut artifacts = Vec::with_capacity(reports.len());
+
+    for report in reports {
+        let artifact = sqlx::query_as::<_, ReportArtifact>(&format!(
+            r#"
+                INSERT INTO report_artifacts (
+                    schedule_id, shift_group, start_date, end_date, format, filename,
+                    content_type, content, size_bytes, sha256
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING {}
+            "#,
+            ARTIFACT_COLUMNS
+        ))
+        .bind(schedule.id)
+        .bind(&report.shift_group)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(&report.format)
+        .bind(&report.filename)
+        .bind(report.content_type)
+        .bind(&report.data)
+        .bind(report.data.len() as i32)
+        .bind(hex::encode(Sha256::digest(&report.data)))
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        artifacts.push(artifact);
+    }
+
+    sqlx::query("UPDATE report_schedules SET last_run_at = NOW() WHERE id = $1")
+        .bind(schedule.id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(artifacts)
+}
+
+/// The most recent artifacts first.
+pub async fn list_artifacts(
+    db: &Pool<Postgres>,
+    shift_group: Option<&str>,
+    schedule_id: Option<i32>,
+    limit: i64,
+) -> Result<Vec<ReportArtifact>, sqlx::Error> {
+    sqlx::query_as::<_, ReportArtifact>(&format!(
+        r#"
+            SELECT {}
+            FROM report_artifacts
+            WHERE ($1::TEXT IS NULL OR shift_group = $1)
+            AND ($2::INT IS NULL OR schedule_id = $2)
+            ORDER BY id DESC
+            LIMIT $3
+        "#,
+        ARTIFACT_COLUMNS
+    ))
+    .bind(shift_group)
+    .bind(schedule_id)
+    .bind(limit)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn get_artifact_content(
+    db: &Pool<Postgres>,
+    id: i32,
+) -> Result<Option<(String, String, Vec<u8>)>, sqlx::Error> {
+    sqlx::query_as("SELECT filename, content_type, content FROM report_artifacts WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db)
+        .await
+}
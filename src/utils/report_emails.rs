@@ -0,0 +1,232 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Postgres};
+
+use crate::utils::{
+    reports::render_reports,
+    smtp::{Attachment, Email},
+};
+
+pub const SENT: &str = "sent";
+pub const FAILED: &str = "failed";
+
+/// Every `report_subscriptions` column, in [ReportSubscription] order.
+pub const SUBSCRIPTION_COLUMNS: &str = r#"
+    id, recipient, shift_groups, cadence, formats, last_period_end, next_attempt_at, created_at
+"#;
+
+/// A recipient of the consolidated report. `daily` emails cover the previous day and `weekly`
+/// ones the previous Monday to Sunday.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReportSubscription {
+    pub id: i32,
+    pub recipient: String,
+    /// Empty for every shift group with schedules in the period.
+    pub shift_groups: Vec<String>,
+    pub cadence: String,
+    pub formats: Vec<String>,
+    /// The last day of the most recent period sent.
+    pub last_period_end: Option<NaiveDate>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReportDelivery {
+    pub id: i32,
+    pub subscription_id: i32,
+    pub recipient: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub shift_groups: Vec<String>,
+    pub attachments: Vec<String>,
+    /// `sent` or `failed`.
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The day periods are worked out from at `now`: today once `send_hour` has passed, yesterday
+/// before that.
+pub fn reference_date(now: DateTime<Tz>, send_hour: u32) -> NaiveDate {
+    let today = now.date_naive();
+
+    match now.time() < NaiveTime::from_hms_opt(send_hour, 0, 0).unwrap_or(NaiveTime::MIN) {
+        true => today.pred_opt().unwrap_or(today),
+        false => today,
+    }
+}
+
+/// Claims the next subscription whose latest period has not been sent, holding it for `lease`
+/// so it is not picked up again while it is being sent.
+pub async fn claim_due_subscription(
+    db: &Pool<Postgres>,
+    daily_end: NaiveDate,
+    weekly_end: NaiveDate,
+    lease: Duration,
+) -> Result<Option<ReportSubscription>, Error> {
+    let subscription = sqlx::query_as::<_, ReportSubscription>(&format!(
+        r#"
+            UPDATE report_subscriptions
+            SET next_attempt_at = NOW() + MAKE_INTERVAL(secs => $3)
+            WHERE id = (
+                SELECT id
+                FROM report_subscriptions
+                WHERE next_attempt_at <= NOW()
+                AND (
+                    last_period_end IS NULL
+                    OR last_period_end < CASE WHEN cadence = 'weekly' THEN $2 ELSE $1 END
+                )
+                ORDER BY next_attempt_at, id
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            RETURNING {}
+        "#,
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(daily_end)
+    .bind(weekly_end)
+    .bind(lease.as_secs_f64())
+    .fetch_optional(db)
+    .await?;
+
+    Ok(subscription)
+}
+
+/// Marks the period ending `period_end` as sent, or schedules another try after `retry_after`.
+pub async fn finish_subscription_period(
+    db: &Pool<Postgres>,
+    id: i32,
+    period_end: NaiveDate,
+    retry_after: Option<Duration>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+            UPDATE report_subscriptions
+            SET
+                last_period_end = CASE WHEN $3::FLOAT8 IS NULL THEN $2 ELSE last_period_end END,
+                next_attempt_at = NOW() + MAKE_INTERVAL(secs => COALESCE($3, 0))
+            WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(period_end)
+    .bind(retry_after.map(|delay| delay.as_secs_f64()))
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Records an email sent, or given up on, for `period`.
+pub async fn log_delivery(
+    db: &Pool<Postgres>,
+    subscription: &ReportSubscription,
+    (start_date, end_date): (NaiveDate, NaiveDate),
+    shift_groups: &[String],
+    attachments: &[String],
+    status: &str,
+    error: Option<String>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+            INSERT INTO report_deliveries (
+                subscription_id, recipient, start_date, end_date, shift_groups, attachments,
+                status, error
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(subscription.id)
+    .bind(&subscription.recipient)
+    .bind(start_date)
+    .bind(end_date)
+    .bind(shift_groups)
+    .bind(attachments)
+    .bind(status)
+    .bind(error)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// The email for one period: a consolidated report per shift group, in each requested format.
+pub async fn build_report_email(
+    db: &Pool<Postgres>,
+    subscription: &ReportSubscription,
+    from: &str,
+    shift_groups: &[String],
+    (start_date, end_date): (NaiveDate, NaiveDate),
+    timezone: Tz,
+) -> Result<Email, Error> {
+    let period = match start_date == end_date {
+        true => start_date.to_string(),
+        false => format!("{} to {}", start_date, end_date),
+    };
+    let attachments = render_reports(
+        db,
+        shift_groups,
+        (start_date, end_date),
+        &subscription.formats,
+        timezone,
+    )
+    .await?
+    .into_iter()
+    .map(|report| Attachment {
+        filename: report.filename,
+        content_type: report.content_type.to_string(),
+        data: report.data,
+    })
+    .collect();
+
+    let body = match shift_groups.is_empty() {
+        true => format!("No schedules were consolidated for {}.\n", period),
+        false => format!(
+            "The consolidated reports for {} are attached for:\n\n{}\n",
+            period,
+            shift_groups
+                .iter()
+                .map(|shift_group| format!("- {}", shift_group))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    };
+
+    Ok(Email {
+        from: from.to_string(),
+        to: subscription.recipient.clone(),
+        subject: format!("Consolidated report for {}", period),
+        body,
+        attachments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+    use chrono_tz::Africa::Johannesburg;
+
+    use super::reference_date;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn waits_for_the_send_hour() {
+        let early = Johannesburg
+            .with_ymd_and_hms(2026, 5, 13, 6, 59, 0)
+            .unwrap();
+        let late = Johannesburg.with_ymd_and_hms(2026, 5, 13, 7, 0, 0).unwrap();
+
+        assert_eq!(reference_date(early, 7), date("2026-05-12"));
+        assert_eq!(reference_date(late, 7), date("2026-05-13"));
+        assert_eq!(reference_date(early, 0), date("2026-05-13"));
+    }
+}
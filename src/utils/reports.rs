@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Error};
+use chrono::{Datelike, Days, NaiveDate};
+use chrono_tz::Tz;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    date_range::{overlaps, DateRange},
+    routes::efficiency::generate_consolidated_report::build_consolidated_report,
+    utils::shift_groups::shift_group_timezone,
+};
+
+/// Covers the day before.
+pub const DAILY: &str = "daily";
+/// Covers the previous Monday to Sunday.
+pub const WEEKLY: &str = "weekly";
+
+pub const PERIODS: [&str; 2] = [DAILY, WEEKLY];
+
+pub const CSV_FORMAT: &str = "csv";
+pub const XLSX_FORMAT: &str = "xlsx";
+
+pub const FORMATS: [&str; 2] = [CSV_FORMAT, XLSX_FORMAT];
+
+/// One shift group's consolidated report in one format.
+#[derive(Debug, Clone)]
+pub struct RenderedReport {
+    pub shift_group: String,
+    pub format: String,
+    pub filename: String,
+    pub content_type: &'static str,
+    pub data: Vec<u8>,
+}
+
+/// The first and last day of the most recent period of `cadence` that ended before `today`.
+pub fn report_period(cadence: &str, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match cadence {
+        WEEKLY => {
+            let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+
+            (monday - Days::new(7), monday - Days::new(1))
+        }
+        _ => {
+            let yesterday = today - Days::new(1);
+
+            (yesterday, yesterday)
+        }
+    }
+}
+
+/// Shift groups with schedules overlapping the days from `start_date` to `end_date` in `timezone`.
+pub async fn shift_groups_in_period(
+    db: &Pool<Postgres>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    timezone: Tz,
+) -> Result<Vec<String>, sqlx::Error> {
+    let range = DateRange::from_dates(start_date, end_date, timezone);
+
+    sqlx::query_scalar(&format!(
+        r#"
+            SELECT DISTINCT shift_group
+            FROM schedules
+            WHERE {}
+            ORDER BY shift_group
+        "#,
+        overlaps(1, 2)
+    ))
+    .bind(range.start)
+    .bind(range.end)
+    .fetch_all(db)
+    .await
+}
+
+/// Trims and de-duplicates shift group names, keeping their order.
+pub fn normalize_shift_groups(shift_groups: Vec<String>) -> Result<Vec<String>, Error> {
+    let mut normalized = Vec::<String>::new();
+
+    for shift_group in shift_groups {
+        let shift_group = shift_group.trim().to_string();
+
+        if shift_group.is_empty() {
+            return Err(anyhow!("must not contain empty names"));
+        }
+
+        if !normalized.contains(&shift_group) {
+            normalized.push(shift_group);
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// Checks and de-duplicates requested formats. `None` means CSV only.
+pub fn normalize_formats(formats: Option<Vec<String>>) -> Result<Vec<String>, Error> {
+    let mut normalized = Vec::<String>::new();
+
+    for format in formats.unwrap_or_else(|| vec![CSV_FORMAT.to_string()]) {
+        let format = format.trim().to_ascii_lowercase();
+
+        if !FORMATS.contains(&format.as_str()) {
+            return Err(anyhow!("expected any of: {}", FORMATS.join(", ")));
+        }
+
+        if !normalized.contains(&format) {
+            normalized.push(format);
+        }
+    }
+
+    if normalized.is_empty() {
+        return Err(anyhow!("must not be empty"));
+    }
+
+    Ok(normalized)
+}
+
+/// Renders the consolidated report of every shift group for the period, in each of `formats`.
+/// Each group's period and dates are in its own timezone, or `timezone` when it has none.
+pub async fn render_reports(
+    db: &Pool<Postgres>,
+    shift_groups: &[String],
+    (start_date, end_date): (NaiveDate, NaiveDate),
+    formats: &[String],
+    timezone: Tz,
+) -> Result<Vec<RenderedReport>, Error> {
+    let mut reports = Vec::new();
+
+    for shift_group in shift_groups {
+        let timezone = shift_group_timezone(db, shift_group)
+            .await?
+            .unwrap_or(timezone);
+        let range = DateRange::from_dates(start_date, end_date, timezone);
+        let report = build_consolidated_report(db, &range, shift_group).await?;
+        let filename = format!(
+            "consolidated-report-{}-{}-{}",
+            shift_group, start_date, end_date
+        );
+
+        for format in formats {
+            let (content_type, data) = match format.as_str() {
+                XLSX_FORMAT => (xlsx_writer::CONTENT_TYPE, report.to_xlsx(shift_group)?),
+                _ => ("text/csv; charset=utf-8", report.to_csv()?.into_bytes()),
+            };
+
+            reports.push(RenderedReport {
+                shift_group: shift_group.clone(),
+                format: format.clone(),
+                filename: format!("{}.{}", filename, format),
+                content_type,
+                data,
+            });
+        }
+    }
+
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{normalize_formats, normalize_shift_groups, report_period, DAILY, WEEKLY};
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn covers_the_last_full_day_or_week() {
+        // A Wednesday.
+        let today = date("2026-05-13");
+
+        assert_eq!(
+            report_period(DAILY, today),
+            (date("2026-05-12"), date("2026-05-12"))
+        );
+        assert_eq!(
+            report_period(WEEKLY, today),
+            (date("2026-05-04"), date("2026-05-10"))
+        );
+        assert_eq!(
+            report_period(WEEKLY, date("2026-05-11")),
+            (date("2026-05-04"), date("2026-05-10"))
+        );
+    }
+
+    #[test]
+    fn normalizes_requested_groups_and_formats() {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+
+        assert_eq!(
+            normalize_shift_groups(strings(&[" Group A", "Group A", "Group B"])).unwrap(),
+            ["Group A", "Group B"]
+        );
+        assert!(normalize_shift_groups(strings(&[" "])).is_err());
+        assert_eq!(normalize_formats(None).unwrap(), ["csv"]);
+        assert_eq!(
+            normalize_formats(Some(strings(&["XLSX", "csv", "xlsx"]))).unwrap(),
+            ["xlsx", "csv"]
+        );
+        assert!(normalize_formats(Some(strings(&["pdf"]))).is_err());
+        assert!(normalize_formats(Some(Vec::new())).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Postgres};
+
+/// Every `shift_groups` column, in [ShiftGroup] order.
+pub const SHIFT_GROUP_COLUMNS: &str = r#"
+    id, name, display_name, region, manager, active, timezone, last_processed_date,
+    schedule_count, teacher_count, created_at, updated_at
+"#;
+
+/// What is known about a shift group besides its schedules. Ingestion adds groups it has not
+/// seen before.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ShiftGroup {
+    pub id: i32,
+    /// The `shift_group` its schedules carry. It cannot be changed.
+    pub name: String,
+    pub display_name: Option<String>,
+    pub region: Option<String>,
+    pub manager: Option<String>,
+    pub active: bool,
+    /// IANA timezone. `None` for `APP_TIMEZONE`.
+    pub timezone: Option<String>,
+    /// The latest upload date that had schedules for the group.
+    pub last_processed_date: Option<NaiveDate>,
+    /// Kept up to date by ingestion, so listing groups never counts schedules.
+    pub schedule_count: i64,
+    pub teacher_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A [ShiftGroup] as `GET /shift-groups` lists it. `shift_group` repeats the name under the key
+/// the endpoint returned before groups had metadata.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ShiftGroupSummary {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub group: ShiftGroup,
+    pub shift_group: String,
+}
+
+/// Shift groups by name, all of them or only those with the given `id` or `active` flag.
+pub async fn list_shift_groups(
+    db: &Pool<Postgres>,
+    id: Option<i32>,
+    active: Option<bool>,
+) -> Result<Vec<ShiftGroupSummary>, sqlx::Error> {
+    sqlx::query_as::<_, ShiftGroupSummary>(&format!(
+        r#"
+            SELECT {}, name AS shift_group
+            FROM shift_groups
+            WHERE ($1::INT IS NULL OR id = $1)
+            AND ($2::BOOLEAN IS NULL OR active = $2)
+            ORDER BY name
+        "#,
+        SHIFT_GROUP_COLUMNS
+    ))
+    .bind(id)
+    .bind(active)
+    .fetch_all(db)
+    .await
+}
+
+/// The timezone the group's reports are rendered in, `None` when it has none and uses the
+/// default.
+pub async fn shift_group_timezone(
+    db: &Pool<Postgres>,
+    shift_group: &str,
+) -> Result<Option<Tz>, sqlx::Error> {
+    let timezone = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT timezone FROM shift_groups WHERE name = $1",
+    )
+    .bind(shift_group)
+    .fetch_optional(db)
+    .await?
+    .flatten();
+
+    // Stored names were checked when they were set.
+    Ok(timezone.and_then(|timezone| timezone.parse().ok()))
+}
+
+/// Gives groups that were added from existing schedules, and so were never processed, the date
+/// of their latest schedule in the group's timezone or `app_timezone`. Only call it at startup,
+/// before any worker runs, so ingestion has not set the date yet.
+pub async fn backfill_last_processed_dates(
+    db: &Pool<Postgres>,
+    app_timezone: Tz,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+            UPDATE shift_groups
+            SET last_processed_date = latest.end_date
+            FROM (
+                SELECT
+                    shift_groups.name,
+                    MAX(
+                        schedules.end_date AT TIME ZONE COALESCE(shift_groups.timezone, $1)
+                    )::DATE AS end_date
+                FROM shift_groups
+                JOIN schedules ON schedules.shift_group = shift_groups.name
+                WHERE shift_groups.last_processed_date IS NULL
+                GROUP BY shift_groups.name
+            ) AS latest
+            WHERE latest.name = shift_groups.name
+            AND shift_groups.last_processed_date IS NULL
+        "#,
+    )
+    .bind(app_timezone.name())
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
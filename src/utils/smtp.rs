@@ -0,0 +1,345 @@
+//! Report emails, sent with `lettre`.
+
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{anyhow, Context, Error};
+use lettre::{
+    message::{
+        header::{ContentTransferEncoding, ContentType},
+        Attachment as MailAttachment, Body, MultiPart, SinglePart,
+    },
+    transport::smtp::{authentication::Credentials, extension::ClientId},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with `STARTTLS`, usually on port 587.
+    StartTls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// No encryption. Only for local mail sinks, and never with credentials.
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+impl FromStr for SmtpSecurity {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<SmtpSecurity, Error> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            "none" => Ok(SmtpSecurity::None),
+            other => Err(anyhow!(
+                "unknown SMTP security {:?}, expected starttls, tls or none",
+                other
+            )),
+        }
+    }
+}
+
+/// Where and as whom to send.
+#[derive(Debug, Clone)]
+pub struct SmtpServer {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/// Accepts a single bare address such as `manager@example.com`. Display names, lists and
+/// anything that could end a header line are rejected.
+pub fn validate_address(address: &str) -> Result<(), Error> {
+    let Some((local, domain)) = address.split_once('@') else {
+        return Err(anyhow!(
+            "expected an email address such as name@example.com"
+        ));
+    };
+
+    if local.is_empty()
+        || domain.is_empty()
+        || domain.contains('@')
+        || !domain.contains('.')
+        || address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>,;\"()[]\\".contains(c))
+    {
+        return Err(anyhow!(
+            "expected an email address such as name@example.com"
+        ));
+    }
+
+    Ok(())
+}
+
+impl SmtpServer {
+    /// Credentials are only ever sent over an encrypted connection.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.username.is_some() && self.security == SmtpSecurity::None {
+            return Err(anyhow!(
+                "refusing to send SMTP credentials without TLS, use starttls or tls"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, Error> {
+        self.validate()?;
+
+        let builder = match self.security {
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host)
+            }
+        };
+        let builder = builder
+            .port(self.port)
+            .timeout(Some(self.timeout))
+            .hello_name(ClientId::Domain(env!("CARGO_PKG_NAME").to_string()));
+
+        Ok(match &self.username {
+            Some(username) => builder
+                .credentials(Credentials::new(
+                    username.clone(),
+                    self.password.clone().unwrap_or_default(),
+                ))
+                .build(),
+            None => builder.build(),
+        })
+    }
+}
+
+impl Email {
+    pub fn to_message(&self) -> Result<Message, Error> {
+        let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(self.body.clone()));
+
+        for attachment in &self.attachments {
+            let content_type = ContentType::parse(&attachment.content_type)
+                .with_context(|| format!("Invalid content type for {}", attachment.filename))?;
+
+            // Base64 keeps CSV line endings intact, whatever they are.
+            let body =
+                Body::new_with_encoding(attachment.data.clone(), ContentTransferEncoding::Base64)
+                    .map_err(|_| anyhow!("Could not encode {}", attachment.filename))?;
+
+            parts = parts.singlepart(
+                MailAttachment::new(attachment.filename.clone()).body(body, content_type),
+            );
+        }
+
+        let message = Message::builder()
+            .from(self.from.parse().context("Invalid sender")?)
+            .to(self.to.parse().context("Invalid recipient")?)
+            .subject(&self.subject)
+            .multipart(parts)?;
+
+        Ok(message)
+    }
+}
+
+/// Sends `email` and waits for the server to accept it.
+pub async fn send_email(server: &SmtpServer, email: &Email) -> Result<(), Error> {
+    validate_address(&email.from).context("Invalid sender")?;
+    validate_address(&email.to).context("Invalid recipient")?;
+
+    let message = email.to_message()?;
+
+    server
+        .transport()?
+        .send(message)
+        .await
+        .context("The SMTP server rejected the message")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        time::Duration,
+    };
+
+    use super::{send_email, validate_address, Attachment, Email, SmtpSecurity, SmtpServer};
+
+    /// A mail sink that accepts one message and returns the commands and message it received.
+    pub fn smtp_sink() -> (u16, std::thread::JoinHandle<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let sink = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = Vec::new();
+            let mut message = String::new();
+
+            stream.write_all(b"220 sink ready\r\n").unwrap();
+
+            loop {
+                let mut line = String::new();
+
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+
+                let verb = line
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                commands.push(line.trim_end().to_string());
+
+                let reply: &[u8] = match verb.as_str() {
+                    "EHLO" => b"250-sink\r\n250 AUTH PLAIN\r\n",
+                    "AUTH" => b"235 ok\r\n",
+                    "DATA" => {
+                        stream.write_all(b"354 go ahead\r\n").unwrap();
+
+                        loop {
+                            let mut line = String::new();
+                            reader.read_line(&mut line).unwrap();
+
+                            if line == ".\r\n" {
+                                break;
+                            }
+
+                            message.push_str(&line);
+                        }
+
+                        b"250 queued\r\n"
+                    }
+                    "QUIT" => {
+                        stream.write_all(b"221 bye\r\n").unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                };
+
+                stream.write_all(reply).unwrap();
+            }
+
+            (commands, message)
+        });
+
+        (port, sink)
+    }
+
+    #[test]
+    fn only_accepts_bare_addresses() {
+        assert!(validate_address("manager@example.com").is_ok());
+        assert!(validate_address("Manager <manager@example.com>").is_err());
+        assert!(validate_address("manager@example.com\r\nBcc: x@example.com").is_err());
+        assert!(validate_address("a@example.com,b@example.com").is_err());
+        assert!(validate_address("manager").is_err());
+    }
+
+    #[test]
+    fn parses_the_security_setting() {
+        assert_eq!(
+            "STARTTLS".parse::<SmtpSecurity>().unwrap(),
+            SmtpSecurity::StartTls
+        );
+        assert_eq!("none".parse::<SmtpSecurity>().unwrap().default_port(), 25);
+        assert!("ssl".parse::<SmtpSecurity>().is_err());
+    }
+
+    #[tokio::test]
+    async fn sends_attachments_to_a_local_sink() {
+        let (port, sink) = smtp_sink();
+        let server = SmtpServer {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            timeout: Duration::from_secs(5),
+        };
+        let email = Email {
+            from: "reports@example.com".to_string(),
+            to: "manager@example.com".to_string(),
+            subject: "Consolidated report".to_string(),
+            body: "Attached.".to_string(),
+            attachments: vec![Attachment {
+                filename: "report Group A.csv".to_string(),
+                content_type: "text/csv".to_string(),
+                data: b"Teacher,Shift\n".to_vec(),
+            }],
+        };
+
+        send_email(&server, &email).await.unwrap();
+
+        let (commands, message) = sink.join().unwrap();
+
+        assert_eq!(
+            commands,
+            [
+                "EHLO sergio-ar-api",
+                "MAIL FROM:<reports@example.com>",
+                "RCPT TO:<manager@example.com>",
+                "DATA",
+                "QUIT",
+            ]
+        );
+        assert!(message.contains("To: manager@example.com\r\n"));
+        assert!(message.contains("filename=\"report Group A.csv\""));
+        // base64 of "Teacher,Shift\n"
+        assert!(message.contains("VGVhY2hlcixTaGlmdAo=\r\n"));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_send_credentials_without_tls() {
+        let server = SmtpServer {
+            host: "127.0.0.1".to_string(),
+            port: 25,
+            security: SmtpSecurity::None,
+            username: Some("reports".to_string()),
+            password: Some("secret".to_string()),
+            timeout: Duration::from_secs(5),
+        };
+        let email = Email {
+            from: "reports@example.com".to_string(),
+            to: "manager@example.com".to_string(),
+            subject: "Consolidated report".to_string(),
+            body: "Attached.".to_string(),
+            attachments: Vec::new(),
+        };
+
+        let error = send_email(&server, &email).await.unwrap_err();
+
+        assert!(error.to_string().contains("without TLS"), "{:#}", error);
+    }
+}
@@ -0,0 +1,132 @@
+use sqlx::{FromRow, Pool, Postgres};
+
+/// A name stored in more than one `teachers` row. The oldest row is kept.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct DuplicateTeacher {
+    pub name: String,
+    pub kept_id: i32,
+    pub merged_ids: Vec<i32>,
+}
+
+/// A schedule of a merged teacher that became identical to an older one. Timestamps are text
+/// so the merge works whichever migration the database stopped at.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct RemovedSchedule {
+    pub id: i32,
+    pub kept_id: i32,
+    pub teacher_id: i32,
+    pub shift_group: String,
+    pub shift: String,
+    pub shift_type: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TeacherMerge {
+    pub teachers: Vec<DuplicateTeacher>,
+    pub removed_schedules: Vec<RemovedSchedule>,
+}
+
+/// Points the schedules of every duplicate teacher at the oldest row with the same name, then
+/// deletes the other rows and the schedules that became identical. Runs in one transaction that
+/// is rolled back when `apply` is false, so the result can be reviewed first.
+pub async fn merge_duplicate_teachers(
+    db: &Pool<Postgres>,
+    apply: bool,
+) -> Result<TeacherMerge, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let teachers = sqlx::query_as::<_, DuplicateTeacher>(
+        r#"
+            SELECT
+                name::TEXT AS name,
+                MIN(id) AS kept_id,
+                ARRAY_REMOVE(ARRAY_AGG(id ORDER BY id), MIN(id)) AS merged_ids
+            FROM teachers
+            GROUP BY name
+            HAVING COUNT(*) > 1
+            ORDER BY name
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if teachers.is_empty() {
+        return Ok(TeacherMerge::default());
+    }
+
+    sqlx::query(
+        r#"
+            UPDATE schedules
+            SET teacher_id = kept.id
+            FROM teachers AS duplicate
+            JOIN (
+                SELECT name, MIN(id) AS id
+                FROM teachers
+                GROUP BY name
+            ) AS kept ON kept.name = duplicate.name
+            WHERE schedules.teacher_id = duplicate.id
+            AND duplicate.id <> kept.id
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let removed_schedules = sqlx::query_as::<_, RemovedSchedule>(
+        r#"
+            DELETE FROM schedules
+            USING (
+                SELECT
+                    id,
+                    FIRST_VALUE(id) OVER duplicates AS kept_id,
+                    ROW_NUMBER() OVER duplicates AS duplicate_number
+                FROM schedules
+                WHERE teacher_id IN (
+                    SELECT MIN(id) FROM teachers GROUP BY name HAVING COUNT(*) > 1
+                )
+                WINDOW duplicates AS (
+                    PARTITION BY teacher_id, start_date, end_date, shift, shift_type, shift_group
+                    ORDER BY id
+                )
+            ) AS ranked
+            WHERE ranked.id = schedules.id
+            AND ranked.duplicate_number > 1
+            RETURNING
+                schedules.id,
+                ranked.kept_id,
+                schedules.teacher_id,
+                schedules.shift_group::TEXT AS shift_group,
+                schedules.shift::TEXT AS shift,
+                schedules.shift_type::TEXT AS shift_type,
+                schedules.start_date::TEXT AS start_date,
+                schedules.end_date::TEXT AS end_date
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+            DELETE FROM teachers
+            WHERE id NOT IN (
+                SELECT MIN(id)
+                FROM teachers
+                GROUP BY name
+            )
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if apply {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+    }
+
+    Ok(TeacherMerge {
+        teachers,
+        removed_schedules,
+    })
+}
@@ -0,0 +1,384 @@
+use std::{sync::OnceLock, time::Duration};
+
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, Utc};
+use consolidation::ConsolidationSummary;
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::{FromRow, Pool, Postgres};
+use url::Url;
+use uuid::Uuid;
+
+use crate::utils::consolidation_jobs::ConsolidationJob;
+
+pub const SUCCEEDED_EVENT: &str = "consolidation.succeeded";
+pub const FAILED_EVENT: &str = "consolidation.failed";
+
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+/// `sha256=` and the hex HMAC-SHA256 of `{timestamp}.{body}`, keyed with the webhook secret.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// An endpoint notified when consolidation jobs finish. The secret is only shown on creation.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: Value,
+    /// `pending`, `delivered` or `failed` once every attempt is used up.
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A delivery claimed for sending, with what is needed to send it.
+#[derive(Debug, Clone, FromRow)]
+pub struct ClaimedDelivery {
+    pub id: i32,
+    pub url: String,
+    pub secret: String,
+    pub event: String,
+    pub payload: Value,
+    pub attempts: i32,
+}
+
+/// `http` and `https` URLs with a host. Anything else is rejected when a webhook is created.
+pub fn validate_webhook_url(url: &str) -> Result<Url, Error> {
+    let url = Url::parse(url.trim())?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("expected an http:// or https:// URL"));
+    }
+
+    if url.host_str().is_none() {
+        return Err(anyhow!("the URL has no host"));
+    }
+
+    Ok(url)
+}
+
+pub fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+pub fn succeeded_payload(job: &ConsolidationJob, summary: &ConsolidationSummary) -> Value {
+    let issues: serde_json::Map<String, Value> = summary
+        .diagnostics
+        .counts
+        .iter()
+        .map(|(kind, count)| (kind.clone(), json!(count)))
+        .collect();
+
+    json!({
+        "event": SUCCEEDED_EVENT,
+        "job_id": job.id,
+        "process_date": job.process_date,
+        "attempts": job.attempts,
+        "shift_groups": summary.shift_groups,
+        "shift_types": summary.shift_types,
+        "counts": {
+            "parsed_shifts": summary.parsed_shifts,
+            "new_shifts": summary.new_shifts,
+            "new_teachers": summary.new_teachers,
+            "parsed_invoices": summary.parsed_invoices,
+            "inserted_invoices": summary.inserted_invoices,
+            "updated_invoices": summary.updated_invoices,
+        },
+        "skipped": {
+            "shifts": summary.skipped_shifts,
+            "teachers": summary.skipped_teachers,
+            "invoices": summary.skipped_invoices,
+            "issues": issues,
+        },
+    })
+}
+
+pub fn failed_payload(job: &ConsolidationJob, error: &str) -> Value {
+    json!({
+        "event": FAILED_EVENT,
+        "job_id": job.id,
+        "process_date": job.process_date,
+        "attempts": job.attempts,
+        "error": {
+            "message": error,
+        },
+    })
+}
+
+/// Queues `payload` for every webhook. Returns how many deliveries were queued.
+pub async fn enqueue_event(
+    db: &Pool<Postgres>,
+    event: &str,
+    payload: &Value,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload)
+            SELECT id, $1, $2 FROM webhooks
+        "#,
+    )
+    .bind(event)
+    .bind(payload)
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Claims the next due delivery. It stays `pending` but is not due again for `lease`, so a
+/// delivery whose sender died is picked up again later.
+pub async fn claim_delivery(
+    db: &Pool<Postgres>,
+    lease: Duration,
+) -> Result<Option<ClaimedDelivery>, Error> {
+    let delivery = sqlx::query_as::<_, ClaimedDelivery>(
+        r#"
+            UPDATE webhook_deliveries AS delivery
+            SET
+                attempts = delivery.attempts + 1,
+                next_attempt_at = NOW() + MAKE_INTERVAL(secs => $1)
+            FROM webhooks
+            WHERE delivery.id = (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at, id
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+            )
+            AND webhooks.id = delivery.webhook_id
+            RETURNING
+                delivery.id,
+                webhooks.url,
+                webhooks.secret,
+                delivery.event,
+                delivery.payload,
+                delivery.attempts
+        "#,
+    )
+    .bind(lease.as_secs_f64())
+    .fetch_optional(db)
+    .await?;
+
+    Ok(delivery)
+}
+
+pub async fn mark_delivered(db: &Pool<Postgres>, id: i32, status_code: u16) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+            UPDATE webhook_deliveries
+            SET
+                status = 'delivered',
+                last_status_code = $2,
+                last_error = NULL,
+                delivered_at = NOW()
+            WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status_code as i32)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a failed attempt, and either schedules the next one after `retry_after` or gives up.
+pub async fn mark_attempt_failed(
+    db: &Pool<Postgres>,
+    id: i32,
+    status_code: Option<u16>,
+    error: &str,
+    retry_after: Option<Duration>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+            UPDATE webhook_deliveries
+            SET
+                status = CASE WHEN $4::FLOAT8 IS NULL THEN 'failed' ELSE 'pending' END,
+                last_status_code = $2,
+                last_error = $3,
+                next_attempt_at = NOW() + MAKE_INTERVAL(secs => COALESCE($4, 0))
+            WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status_code.map(i32::from))
+    .bind(error)
+    .bind(retry_after.map(|delay| delay.as_secs_f64()))
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Shared so connections are pooled across deliveries. Proxies are read from the environment.
+fn client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .user_agent(format!(
+                "{}/{}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()
+            .expect("the TLS backend is compiled in")
+    })
+}
+
+/// POSTs the signed payload and returns the response status. `timeout` covers the whole
+/// request, including redirects.
+pub async fn send_delivery(delivery: &ClaimedDelivery, timeout: Duration) -> Result<u16, Error> {
+    let url = validate_webhook_url(&delivery.url)?;
+    let body = serde_json::to_vec(&delivery.payload)?;
+    let timestamp = Utc::now().timestamp();
+
+    let response = client()
+        .post(url)
+        .timeout(timeout)
+        .header(CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &body))
+        .body(body)
+        .send()
+        .await?;
+
+    Ok(response.status().as_u16())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        time::Duration,
+    };
+
+    use serde_json::json;
+
+    use super::{send_delivery, sign, validate_webhook_url, ClaimedDelivery, SIGNATURE_HEADER};
+
+    /// The value of a `name: value` request line, matching the name in any case.
+    pub fn header_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+        let (key, value) = line.split_once(':')?;
+
+        key.eq_ignore_ascii_case(name).then(|| value.trim())
+    }
+
+    #[test]
+    fn signs_the_timestamp_and_body() {
+        // printf '1700000000.{}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000, b"{}"),
+            "sha256=b8569b78799ff9e3cbff0fc2d63a33a2b57f3282abd07c37ae5e8e7d79a5f163"
+        );
+        assert_ne!(
+            sign("secret", 1_700_000_001, b"{}"),
+            sign("secret", 1_700_000_000, b"{}")
+        );
+    }
+
+    #[test]
+    fn only_accepts_http_urls() {
+        assert!(validate_webhook_url("https://hooks.example.com/ar?token=1").is_ok());
+        assert!(validate_webhook_url("ftp://hooks.example.com").is_err());
+        assert!(validate_webhook_url("hooks.example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn posts_a_signed_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut headers = Vec::new();
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                if line.trim().is_empty() {
+                    break;
+                }
+
+                headers.push(line.trim().to_string());
+            }
+
+            let length = headers
+                .iter()
+                .find_map(|header| header_value(header, "Content-Length"))
+                .unwrap()
+                .parse::<usize>()
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+
+            (headers, body)
+        });
+
+        let delivery = ClaimedDelivery {
+            id: 7,
+            url: format!("http://127.0.0.1:{}/hooks/ar?source=test", port),
+            secret: "secret".to_string(),
+            event: "consolidation.succeeded".to_string(),
+            payload: json!({ "job_id": 3 }),
+            attempts: 1,
+        };
+
+        let status = send_delivery(&delivery, Duration::from_secs(5))
+            .await
+            .unwrap();
+        let (headers, body) = server.join().unwrap();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find_map(|header| header_value(header, name))
+                .unwrap()
+                .to_string()
+        };
+        let timestamp = header("X-Webhook-Timestamp").parse::<i64>().unwrap();
+
+        assert_eq!(status, 204);
+        assert_eq!(headers[0], "POST /hooks/ar?source=test HTTP/1.1");
+        assert_eq!(header("X-Webhook-Delivery"), "7");
+        assert_eq!(body, br#"{"job_id":3}"#);
+        assert_eq!(header(SIGNATURE_HEADER), sign("secret", timestamp, &body));
+    }
+}
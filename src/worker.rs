@@ -0,0 +1,508 @@
+//! Background workers that consolidate uploads from the `consolidation_jobs` queue, deliver the
+//! webhooks their results queue and email subscribed reports.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use chrono::{NaiveDate, Utc};
+use consolidation::{
+    datetime_profiles::DateTimeProfileSelection, ConsolidationInputs, ConsolidationSummary,
+};
+use serde_json::Value;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::{
+    routes::consolidator::upload_and_process::{
+        consolidate_into_database, remove_upload_directory,
+    },
+    utils::{
+        consolidation_jobs::{
+            cancel_requested, claim_job, finish_job, is_transient, requeue_interrupted_job,
+            retry_delay, retry_job, ConsolidationJob, CANCELLED, FAILED, SUCCEEDED,
+        },
+        report_emails::{
+            self, build_report_email, claim_due_subscription, finish_subscription_period,
+            log_delivery, reference_date, ReportSubscription,
+        },
+        reports::{report_period, shift_groups_in_period, DAILY, WEEKLY},
+        smtp::{send_email, SmtpServer},
+        webhooks::{
+            claim_delivery, enqueue_event, failed_payload, mark_attempt_failed, mark_delivered,
+            send_delivery, succeeded_payload, ClaimedDelivery, FAILED_EVENT, SUCCEEDED_EVENT,
+        },
+    },
+    AppState,
+};
+
+/// How often idle workers look for due jobs, and running ones check for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the mailer looks for subscriptions with a period to send.
+const REPORT_EMAIL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Starts `worker_concurrency` workers, one webhook sender and, with SMTP configured, one report
+/// mailer on `tasks`. They stop claiming work once `shutdown` is cancelled and exit after the
+/// job, delivery or email in hand. Cancelling `interrupt` as well makes workers abandon their
+/// consolidations and requeue the jobs themselves.
+pub fn spawn_workers(
+    app_state: &AppState,
+    tasks: &TaskTracker,
+    shutdown: CancellationToken,
+    interrupt: CancellationToken,
+) {
+    for worker in 1..=app_state.env.queue.worker_concurrency {
+        tasks.spawn(run_worker(
+            app_state.clone(),
+            worker,
+            shutdown.clone(),
+            interrupt.clone(),
+        ));
+    }
+
+    tasks.spawn(run_webhook_sender(app_state.clone(), shutdown.clone()));
+
+    match app_state.env.email.smtp.clone() {
+        Some(smtp) => {
+            tasks.spawn(run_report_mailer(app_state.clone(), smtp, shutdown));
+        }
+        None => tracing::info!("❕ SMTP_HOST is not set. Report emails are disabled."),
+    }
+}
+
+async fn run_worker(
+    app_state: AppState,
+    worker: usize,
+    shutdown: CancellationToken,
+    interrupt: CancellationToken,
+) {
+    tracing::debug!("🕐 Consolidation worker {} started.", worker);
+
+    while !shutdown.is_cancelled() {
+        let idle = match claim_job(&app_state.db).await {
+            Ok(Some(job)) => {
+                process_job(&app_state, worker, job, &interrupt).await;
+                continue;
+            }
+            Ok(None) => POLL_INTERVAL,
+            Err(error) => {
+                tracing::error!("🔥 Worker {} failed to claim a job: {:?}", worker, error);
+                POLL_INTERVAL * 5
+            }
+        };
+
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            _ = tokio::time::sleep(idle) => {}
+        }
+    }
+
+    tracing::debug!("🕐 Consolidation worker {} stopped.", worker);
+}
+
+async fn process_job(
+    app_state: &AppState,
+    worker: usize,
+    job: ConsolidationJob,
+    interrupt: &CancellationToken,
+) {
+    tracing::info!(
+        "📄 Worker {} consolidating {} (job {}, attempt {} of {}).",
+        worker,
+        job.process_date,
+        job.id,
+        job.attempts,
+        job.max_attempts
+    );
+
+    let started = Instant::now();
+    let writes_started = Arc::new(AtomicBool::new(false));
+
+    let result = tokio::select! {
+        result = consolidate_job(app_state, &job, writes_started.clone()) => Some(result),
+        _ = wait_for_cancellation(app_state, job.id, &writes_started) => None,
+        _ = interrupt.cancelled() => {
+            // The consolidation is dropped first, so nothing writes to the job after this.
+            tracing::warn!("⚠️ Interrupted consolidation job {}. Requeueing it.", job.id);
+
+            if let Err(error) = requeue_interrupted_job(&app_state.db, job.id).await {
+                tracing::error!(
+                    "🔥 Failed to requeue consolidation job {}: {:?}",
+                    job.id,
+                    error
+                );
+            }
+
+            return;
+        }
+    };
+
+    let Some(result) = result else {
+        tracing::info!("❕ Cancelled consolidation job {}.", job.id);
+        record_end(app_state, &job, CANCELLED, None).await;
+        return;
+    };
+
+    app_state.metrics.record_consolidation(
+        job.process_date,
+        started.elapsed(),
+        result.as_ref().ok(),
+    );
+
+    match result {
+        Ok(summary) => {
+            tracing::info!("✅ Consolidation job {} finished.", job.id);
+            record_end(app_state, &job, SUCCEEDED, None).await;
+            notify(
+                app_state,
+                SUCCEEDED_EVENT,
+                succeeded_payload(&job, &summary),
+            )
+            .await;
+        }
+        Err(error) if is_transient(&error) && job.attempts < job.max_attempts => {
+            let delay = retry_delay(app_state.env.queue.retry_delay, job.attempts);
+
+            tracing::warn!(
+                "⚠️ Consolidation job {} failed, retrying in {}s: {:#}",
+                job.id,
+                delay.as_secs(),
+                error
+            );
+
+            if let Err(retry_error) =
+                retry_job(&app_state.db, job.id, delay, format!("{:#}", error)).await
+            {
+                tracing::error!(
+                    "🔥 Failed to requeue consolidation job {}: {:?}",
+                    job.id,
+                    retry_error
+                );
+            }
+        }
+        Err(error) => {
+            tracing::error!("🔥 Consolidation job {} failed: {:?}", job.id, error);
+            let message = format!("{:#}", error);
+
+            record_end(app_state, &job, FAILED, Some(message.clone())).await;
+            notify(app_state, FAILED_EVENT, failed_payload(&job, &message)).await;
+        }
+    }
+}
+
+async fn consolidate_job(
+    app_state: &AppState,
+    job: &ConsolidationJob,
+    writes_started: Arc<AtomicBool>,
+) -> Result<ConsolidationSummary, Error> {
+    let profile_selection = match &job.datetime_profile {
+        Some(name) => DateTimeProfileSelection::resolve(name, &app_state.env.datetime_profiles)?,
+        None => app_state.env.default_datetime_profile.clone(),
+    };
+    let date = job.process_date.format("%Y-%m-%d").to_string();
+    // Jobs queued before every upload had its own directory share one per date.
+    let directory = match &job.upload_directory {
+        Some(directory) => directory.clone(),
+        None => app_state
+            .env
+            .server
+            .upload_directory
+            .join(&date)
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let inputs = ConsolidationInputs::from_upload_dir(&directory)?;
+
+    consolidate_into_database(
+        &app_state.db,
+        &app_state.env,
+        &inputs,
+        &date,
+        profile_selection,
+        writes_started,
+    )
+    .await
+}
+
+/// Resolves once the job is asked to stop, as long as it has not started writing. A run that
+/// has written a batch is left to finish, so a cancelled job never leaves half its results
+/// behind with its upload gone. Lookup errors are logged and the job left running.
+pub(crate) async fn wait_for_cancellation(
+    app_state: &AppState,
+    id: i32,
+    writes_started: &AtomicBool,
+) {
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        if writes_started.load(Ordering::SeqCst) {
+            return std::future::pending().await;
+        }
+
+        match cancel_requested(&app_state.db, id).await {
+            // The run cannot move on while this branch is polled, so the flag is current.
+            Ok(true) if !writes_started.load(Ordering::SeqCst) => return,
+            Ok(true) => {
+                tracing::info!(
+                    "❕ Consolidation job {} is already writing. Finishing it despite the cancellation.",
+                    id
+                );
+                return std::future::pending().await;
+            }
+            Ok(false) => {}
+            Err(error) => {
+                tracing::warn!(
+                    "⚠️ Could not check job {} for cancellation: {:?}",
+                    id,
+                    error
+                )
+            }
+        }
+    }
+}
+
+/// Records how the job ended and deletes its upload, which no retry will read.
+async fn record_end(
+    app_state: &AppState,
+    job: &ConsolidationJob,
+    status: &str,
+    error: Option<String>,
+) {
+    if let Err(finish_error) = finish_job(&app_state.db, job.id, status, error).await {
+        tracing::error!(
+            "🔥 Failed to record the end of consolidation job {}: {:?}",
+            job.id,
+            finish_error
+        );
+    }
+
+    if let Some(directory) = &job.upload_directory {
+        remove_upload_directory(Path::new(directory)).await;
+    }
+}
+
+/// Queues `event` for every webhook. A failure is logged and does not affect the job.
+async fn notify(app_state: &AppState, event: &str, payload: Value) {
+    if let Err(error) = enqueue_event(&app_state.db, event, &payload).await {
+        tracing::error!("🔥 Failed to queue {} webhooks: {:?}", event, error);
+    }
+}
+
+async fn run_webhook_sender(app_state: AppState, shutdown: CancellationToken) {
+    // Long enough that a delivery in flight is not claimed twice.
+    let lease = app_state.env.webhooks.timeout * 3;
+
+    while !shutdown.is_cancelled() {
+        let idle = match claim_delivery(&app_state.db, lease).await {
+            Ok(Some(delivery)) => {
+                deliver(&app_state, delivery).await;
+                continue;
+            }
+            Ok(None) => POLL_INTERVAL,
+            Err(error) => {
+                tracing::error!("🔥 Failed to claim a webhook delivery: {:?}", error);
+                POLL_INTERVAL * 5
+            }
+        };
+
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            _ = tokio::time::sleep(idle) => {}
+        }
+    }
+}
+
+async fn deliver(app_state: &AppState, delivery: ClaimedDelivery) {
+    let config = &app_state.env.webhooks;
+
+    let (status_code, error) = match send_delivery(&delivery, config.timeout).await {
+        Ok(status_code) if (200..300).contains(&status_code) => {
+            tracing::info!(
+                "✅ Delivered {} webhook {} to {}.",
+                delivery.event,
+                delivery.id,
+                delivery.url
+            );
+
+            if let Err(error) = mark_delivered(&app_state.db, delivery.id, status_code).await {
+                tracing::error!(
+                    "🔥 Failed to record webhook delivery {}: {:?}",
+                    delivery.id,
+                    error
+                );
+            }
+
+            return;
+        }
+        Ok(status_code) => (
+            Some(status_code),
+            format!("The endpoint responded with {}", status_code),
+        ),
+        Err(error) => (None, format!("{:#}", error)),
+    };
+
+    let retry_after = (delivery.attempts < config.max_attempts)
+        .then(|| retry_delay(config.retry_delay, delivery.attempts));
+
+    match retry_after {
+        Some(delay) => tracing::warn!(
+            "⚠️ Webhook delivery {} to {} failed, retrying in {}s: {}",
+            delivery.id,
+            delivery.url,
+            delay.as_secs(),
+            error
+        ),
+        None => tracing::error!(
+            "🔥 Webhook delivery {} to {} failed after {} attempts: {}",
+            delivery.id,
+            delivery.url,
+            delivery.attempts,
+            error
+        ),
+    }
+
+    if let Err(record_error) =
+        mark_attempt_failed(&app_state.db, delivery.id, status_code, &error, retry_after).await
+    {
+        tracing::error!(
+            "🔥 Failed to record webhook delivery {}: {:?}",
+            delivery.id,
+            record_error
+        );
+    }
+}
+
+async fn run_report_mailer(app_state: AppState, smtp: SmtpServer, shutdown: CancellationToken) {
+    // Long enough that an email being sent is not claimed twice.
+    let lease = smtp.timeout * 4;
+
+    while !shutdown.is_cancelled() {
+        let now = Utc::now().with_timezone(&app_state.env.app_timezone);
+        let today = reference_date(now, app_state.env.email.send_hour);
+        let daily = report_period(DAILY, today);
+        let weekly = report_period(WEEKLY, today);
+
+        let idle = match claim_due_subscription(&app_state.db, daily.1, weekly.1, lease).await {
+            Ok(Some(subscription)) => {
+                let period = match subscription.cadence.as_str() {
+                    WEEKLY => weekly,
+                    _ => daily,
+                };
+
+                email_report(&app_state, &smtp, subscription, period).await;
+                continue;
+            }
+            Ok(None) => REPORT_EMAIL_INTERVAL,
+            Err(error) => {
+                tracing::error!("🔥 Failed to claim a report subscription: {:?}", error);
+                REPORT_EMAIL_INTERVAL
+            }
+        };
+
+        tokio::select! {
+            _ = shutdown.cancelled() => {}
+            _ = tokio::time::sleep(idle) => {}
+        }
+    }
+}
+
+/// Sends one period's email, logs the delivery and schedules the next one, or a retry.
+async fn email_report(
+    app_state: &AppState,
+    smtp: &SmtpServer,
+    subscription: ReportSubscription,
+    period: (NaiveDate, NaiveDate),
+) {
+    let timezone = app_state.env.app_timezone;
+    let mut shift_groups = subscription.shift_groups.clone();
+    let mut attachments = Vec::new();
+
+    let result = async {
+        if shift_groups.is_empty() {
+            shift_groups =
+                shift_groups_in_period(&app_state.db, period.0, period.1, timezone).await?;
+        }
+
+        let email = build_report_email(
+            &app_state.db,
+            &subscription,
+            &app_state.env.email.from,
+            &shift_groups,
+            period,
+            timezone,
+        )
+        .await?;
+
+        attachments = email
+            .attachments
+            .iter()
+            .map(|attachment| attachment.filename.clone())
+            .collect();
+
+        send_email(smtp, &email).await
+    }
+    .await;
+
+    let (status, error, retry_after) = match result {
+        Ok(()) => {
+            tracing::info!(
+                "✅ Emailed the {} report for {} to {} ({} attachment(s)).",
+                subscription.cadence,
+                period.1,
+                subscription.recipient,
+                attachments.len()
+            );
+
+            (report_emails::SENT, None, None)
+        }
+        Err(error) => {
+            tracing::error!(
+                "🔥 Failed to email the {} report for {} to {}: {:?}",
+                subscription.cadence,
+                period.1,
+                subscription.recipient,
+                error
+            );
+
+            (
+                report_emails::FAILED,
+                Some(format!("{:#}", error)),
+                Some(app_state.env.email.retry_delay),
+            )
+        }
+    };
+
+    if let Err(error) = log_delivery(
+        &app_state.db,
+        &subscription,
+        period,
+        &shift_groups,
+        &attachments,
+        status,
+        error,
+    )
+    .await
+    {
+        tracing::error!(
+            "🔥 Failed to log the report email to {}: {:?}",
+            subscription.recipient,
+            error
+        );
+    }
+
+    if let Err(error) =
+        finish_subscription_period(&app_state.db, subscription.id, period.1, retry_after).await
+    {
+        tracing::error!(
+            "🔥 Failed to update report subscription {}: {:?}",
+            subscription.id,
+            error
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Single-sheet XLSX workbooks, for the API's report downloads and the consolidation engine's
+//! test fixtures. Kept out of the engine, which only reads spreadsheets.
+
+use std::io::{Cursor, Write};
+
+use anyhow::Error;
+use chrono::NaiveDateTime;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// A single cell in a generated worksheet.
+#[derive(Debug, Clone)]
+pub enum XlsxCell {
+    Text(String),
+    Number(f64),
+    /// Written as an Excel serial number with a date-time number format, so readers see a real
+    /// date cell rather than text.
+    DateTime(NaiveDateTime),
+    Empty,
+}
+
+impl XlsxCell {
+    pub fn text(value: impl Into<String>) -> XlsxCell {
+        XlsxCell::Text(value.into())
+    }
+}
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+/// Style 0 is the default, style 1 is the built-in `m/d/yy h:mm` date-time format (id 22).
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="1"><font><sz val="11"/><name val="Calibri"/></font></fonts><fills count="1"><fill><patternFill patternType="none"/></fill></fills><borders count="1"><border/></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="22" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/></cellXfs></styleSheet>"#;
+
+/// Excel refuses longer sheet names.
+const MAX_SHEET_NAME: usize = 31;
+
+pub const CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// Builds a single-sheet workbook in memory.
+pub fn write_xlsx(sheet_name: &str, rows: &[Vec<XlsxCell>]) -> Result<Vec<u8>, Error> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let workbook = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        escape_xml(&sheet_title(sheet_name))
+    );
+
+    let parts: [(&str, &str); 5] = [
+        ("[Content_Types].xml", CONTENT_TYPES),
+        ("_rels/.rels", ROOT_RELS),
+        ("xl/workbook.xml", &workbook),
+        ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS),
+        ("xl/styles.xml", STYLES),
+    ];
+
+    for (name, contents) in parts {
+        zip.start_file(name, options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+
+    zip.start_file("xl/worksheets/sheet1.xml", options)?;
+    zip.write_all(sheet_xml(rows).as_bytes())?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+fn sheet_xml(rows: &[Vec<XlsxCell>]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    );
+
+    for (row_index, row) in rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, row_index + 1));
+
+        for (column_index, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(column_index), row_index + 1);
+
+            match cell {
+                XlsxCell::Text(value) => xml.push_str(&format!(
+                    r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    reference,
+                    escape_xml(value)
+                )),
+                XlsxCell::Number(value) => {
+                    xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, value))
+                }
+                XlsxCell::DateTime(value) => xml.push_str(&format!(
+                    r#"<c r="{}" s="1"><v>{}</v></c>"#,
+                    reference,
+                    excel_serial(value)
+                )),
+                XlsxCell::Empty => {}
+            }
+        }
+
+        xml.push_str("</row>");
+    }
+
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Excel serial dates count days from 1899-12-30 (which absorbs the 1900 leap year bug).
+fn excel_serial(value: &NaiveDateTime) -> f64 {
+    let epoch = chrono::NaiveDate::from_ymd_opt(1899, 12, 30)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+
+    (*value - epoch).num_seconds() as f64 / 86_400.0
+}
+
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+
+        if index < 26 {
+            break;
+        }
+
+        index = index / 26 - 1;
+    }
+
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// Drops the characters Excel does not allow in sheet names and shortens it to fit.
+fn sheet_title(name: &str) -> String {
+    let title = name
+        .chars()
+        .filter(|c| !"[]:*?/\\".contains(*c))
+        .take(MAX_SHEET_NAME)
+        .collect::<String>();
+
+    match title.trim() {
+        "" => "Report".to_string(),
+        title => title.to_string(),
+    }
+}
+
+/// Escapes markup and drops control characters XML cannot hold.
+fn escape_xml(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .fold(String::with_capacity(value.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                _ => escaped.push(c),
+            }
+
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use calamine::{Data, Reader, Xlsx};
+
+    use super::{column_name, write_xlsx, XlsxCell};
+
+    #[test]
+    fn names_columns_like_excel() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn writes_a_workbook_calamine_can_read() {
+        let rows = vec![
+            vec![
+                XlsxCell::text("Teacher"),
+                XlsxCell::text("Shift"),
+                XlsxCell::Empty,
+                XlsxCell::text("Scheduled"),
+            ],
+            vec![
+                XlsxCell::text("Magongo, Babalwa <B> & Co"),
+                XlsxCell::text("08:00"),
+                XlsxCell::Empty,
+                XlsxCell::Number(3.0),
+            ],
+            vec![XlsxCell::text("John\u{1}"), XlsxCell::text("007")],
+        ];
+        let workbook = write_xlsx("Group: A/B", &rows).unwrap();
+        let mut workbook = Xlsx::new(Cursor::new(workbook)).unwrap();
+
+        assert_eq!(workbook.sheet_names(), ["Group AB"]);
+
+        let range = workbook.worksheet_range("Group AB").unwrap();
+
+        assert_eq!(
+            range.get_value((1, 0)),
+            Some(&Data::String("Magongo, Babalwa <B> & Co".into()))
+        );
+        assert_eq!(range.get_value((1, 3)), Some(&Data::Float(3.0)));
+        assert_eq!(range.get_value((2, 0)), Some(&Data::String("John".into())));
+        assert_eq!(range.get_value((2, 1)), Some(&Data::String("007".into())));
+    }
+}